@@ -1,11 +1,16 @@
-use crate::worker_thread::IOThread;
+use crate::worker_thread::{IOThread, IncompatibleServer};
 use eframe::{App, Frame};
 use egui::{Context, TextBuffer};
+use fishandchippy::events::RequestId;
 use fishandchippy::events::client::EventToClient;
+use fishandchippy::events::error_code::ErrorCode;
 use fishandchippy::events::server::EventToServer;
+use fishandchippy::game_types::cards::Card;
+use fishandchippy::game_types::hand_eval::HandRank;
 use fishandchippy::game_types::player::Player;
 use fishandchippy::game_types::pot::Pot;
-use std::collections::{HashMap, HashSet};
+use fishandchippy::game_types::table::{TableId, TableSummary};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use uuid::Uuid;
 
 pub struct ChippyApp {
@@ -17,6 +22,24 @@ pub struct ChippyApp {
 enum MessageSender {
     Player(Uuid),
     Admin,
+    Error(ErrorCode),
+}
+
+///Whose turn it is, and what they'd need to do to stay in.
+struct Turn {
+    player: Uuid,
+    current_bet: u32,
+    ///`None` if they can only call or fold, as a short all in hasn't reopened the betting.
+    min_raise_to: Option<u32>,
+}
+
+///What we can see of the hand being played, and the raise we're typing in.
+#[derive(Default)]
+struct HandView {
+    hole_cards: Vec<Card>,
+    board: Vec<Card>,
+    turn: Option<Turn>,
+    raise_buffer: String,
 }
 
 enum ChippyAppState {
@@ -26,13 +49,23 @@ enum ChippyAppState {
     },
     ErrorHappened {
         error: String,
+        needs_refresh: bool,
+    },
+    Lobby {
+        tables: BTreeMap<TableId, TableSummary>,
+        new_table_buffer: String,
+        last_error: Option<String>,
     },
     LoadedIn {
         send_msg_buffer: String,
         msgs_so_far: Vec<(MessageSender, String)>,
         our_uuid: Uuid,
+        table: TableSummary,
         players: HashMap<Uuid, Player>,
         pot: Pot,
+        hand: Box<HandView>,
+        //so we don't keep asking for the same player every frame
+        pending_player_reqs: HashMap<RequestId, Uuid>,
     },
 }
 
@@ -45,6 +78,16 @@ impl Default for ChippyAppState {
     }
 }
 
+impl ChippyAppState {
+    const fn lobby() -> Self {
+        Self::Lobby {
+            tables: BTreeMap::new(),
+            new_table_buffer: String::new(),
+            last_error: None,
+        }
+    }
+}
+
 impl ChippyApp {
     pub fn new() -> color_eyre::Result<Self> {
         Ok(Self {
@@ -53,6 +96,11 @@ impl ChippyApp {
         })
     }
 
+    ///Whether an event about `table` is for the table we're sat at - anything else is left over from one we've left.
+    fn is_current_table(&self, table: TableId) -> bool {
+        matches!(&self.state, ChippyAppState::LoadedIn { table: current, .. } if current.id == table)
+    }
+
     fn get_my_player_data(&self) -> Option<&Player> {
         if let ChippyAppState::LoadedIn {
             players, our_uuid, ..
@@ -65,12 +113,15 @@ impl ChippyApp {
     }
 
     fn game_update(&mut self) {
-        let mut reqs_to_send = HashSet::new();
+        let mut players_to_request = HashSet::new();
+        let mut wants_start_info = false;
+        let mut wants_table_list = false;
         let events = match self.io.poll_and_get_events() {
             Ok(events) => events,
             Err(e) => {
                 self.state = ChippyAppState::ErrorHappened {
                     error: e.to_string(),
+                    needs_refresh: e.downcast_ref::<IncompatibleServer>().is_some(),
                 };
                 return;
             }
@@ -78,62 +129,493 @@ impl ChippyApp {
 
         for result in events {
             match result {
-                EventToClient::TxtSent(uuid, content) => {
-                    if let ChippyAppState::LoadedIn {
-                        msgs_so_far,
-                        players,
-                        ..
-                    } = &mut self.state
-                    {
-                        msgs_so_far.push((MessageSender::Player(uuid), content));
-                        if !players.contains_key(&uuid) {
-                            reqs_to_send.insert(EventToServer::GetSpecificPlayer(uuid));
-                        }
-                    }
-                }
+                EventToClient::Welcome { .. } => {}
                 EventToClient::AdminMsg(content) => {
                     if let ChippyAppState::LoadedIn { msgs_so_far, .. } = &mut self.state {
                         msgs_so_far.push((MessageSender::Admin, content));
                     }
                 }
-                EventToClient::Introduced(uuid) => {
-                    info!("User state now loaded");
-                    self.state = ChippyAppState::LoadedIn {
-                        send_msg_buffer: "".to_string(),
-                        msgs_so_far: vec![],
-                        our_uuid: uuid,
-                        players: HashMap::new(),
-                        pot: Pot::default(),
-                    };
-                    reqs_to_send.insert(EventToServer::GetStartInformation);
-                }
-                EventToClient::Pot(new_pot) => {
-                    if let ChippyAppState::LoadedIn { pot, players, .. } = &mut self.state {
-                        *pot = new_pot;
-
-                        for uuid in pot.ready_to_put_in.keys() {
-                            if !players.contains_key(uuid) {
-                                reqs_to_send.insert(EventToServer::GetSpecificPlayer(*uuid));
+                EventToClient::Error {
+                    code,
+                    message,
+                    request_id,
+                } => {
+                    warn!("Server rejected request {request_id:?} with {code:?}: {message}");
+                    match &mut self.state {
+                        ChippyAppState::LoadedIn {
+                            msgs_so_far,
+                            pending_player_reqs,
+                            ..
+                        } => {
+                            if let Some(request_id) = request_id {
+                                pending_player_reqs.remove(&request_id);
                             }
+                            msgs_so_far.push((MessageSender::Error(code), message));
                         }
+                        ChippyAppState::Lobby { last_error, .. } => {
+                            *last_error = Some(message);
+                        }
+                        _ => {}
+                    }
+                }
+                EventToClient::Introduced { uuid, .. } => {
+                    if matches!(&self.state, ChippyAppState::LoadedIn { our_uuid, .. } if *our_uuid == uuid)
+                    {
+                        info!("Resumed our old session");
+                    } else {
+                        info!("Introduced, now in the lobby");
+                        self.state = ChippyAppState::lobby();
+                        wants_table_list = true;
+                    }
+                }
+                EventToClient::JoinedTable(joined) => {
+                    wants_start_info |= self.joined_table(joined);
+                }
+                EventToClient::LeftTable(table) => {
+                    if self.is_current_table(table) {
+                        info!("Left {table}, back to the lobby");
+                        self.state = ChippyAppState::lobby();
+                        wants_table_list = true;
+                    }
+                }
+                EventToClient::TableList(list, _) => {
+                    if let ChippyAppState::Lobby { tables, .. } = &mut self.state {
+                        *tables = list.into_iter().map(|table| (table.id, table)).collect();
                     }
                 }
-                EventToClient::AllPlayers(new_players) => {
-                    if let ChippyAppState::LoadedIn { players, .. } = &mut self.state {
-                        *players = new_players;
+                EventToClient::TableUpdated(table) => {
+                    if let ChippyAppState::Lobby { tables, .. } = &mut self.state {
+                        tables.insert(table.id, table);
                     }
                 }
-                EventToClient::SpecificPlayer(uuid, player) => {
-                    if let ChippyAppState::LoadedIn { players, .. } = &mut self.state {
-                        players.insert(uuid, player);
+                table_event => self.table_event(table_event, &mut players_to_request),
+            }
+        }
+
+        self.request_follow_ups(players_to_request, wants_table_list, wants_start_info);
+    }
+
+    ///Sits us down at `joined`, returning whether we need to ask what's going on there.
+    fn joined_table(&mut self, joined: TableSummary) -> bool {
+        if let ChippyAppState::LoadedIn {
+            table, msgs_so_far, ..
+        } = &mut self.state
+            && table.id == joined.id
+        {
+            //we've reconnected, so keep everything but catch up on what we missed
+            msgs_so_far.push((MessageSender::Admin, "Reconnected".to_string()));
+            *table = joined;
+            return true;
+        }
+
+        let Some(uuid) = self.io.is_connected() else {
+            return false;
+        };
+        info!("User state now loaded at {joined}");
+        self.state = ChippyAppState::LoadedIn {
+            send_msg_buffer: "".to_string(),
+            msgs_so_far: vec![],
+            our_uuid: uuid,
+            table: joined,
+            players: HashMap::new(),
+            pot: Pot::default(),
+            hand: Box::default(),
+            pending_player_reqs: HashMap::new(),
+        };
+        true
+    }
+
+    ///Handles an event about what's going on at a table, ignoring it if it's not the one we're sat at.
+    fn table_event(&mut self, event: EventToClient, players_to_request: &mut HashSet<Uuid>) {
+        let ChippyAppState::LoadedIn {
+            table: current,
+            msgs_so_far,
+            players,
+            pot,
+            hand,
+            pending_player_reqs,
+            ..
+        } = &mut self.state
+        else {
+            return;
+        };
+
+        match event {
+            EventToClient::TxtSent(table, uuid, content) if table == current.id => {
+                msgs_so_far.push((MessageSender::Player(uuid), content));
+                if !players.contains_key(&uuid) {
+                    players_to_request.insert(uuid);
+                }
+            }
+            EventToClient::Pot(table, new_pot, _) if table == current.id => {
+                *pot = new_pot;
+
+                for uuid in pot.ready_to_put_in.keys() {
+                    if !players.contains_key(uuid) {
+                        players_to_request.insert(*uuid);
                     }
                 }
             }
+            EventToClient::AllPlayers(table, new_players, _) if table == current.id => {
+                *players = new_players;
+            }
+            EventToClient::SpecificPlayer(table, uuid, player, request_id)
+                if table == current.id =>
+            {
+                if let Some(request_id) = request_id {
+                    pending_player_reqs.remove(&request_id);
+                }
+                players.insert(uuid, player);
+            }
+            EventToClient::HandStarted(table, _)
+            | EventToClient::HoleCards(table, _)
+            | EventToClient::Board(table, _)
+            | EventToClient::ToAct { table, .. }
+            | EventToClient::Showdown(table, _)
+                if table == current.id =>
+            {
+                hand.update(event, msgs_so_far, players);
+            }
+            _ => {}
+        }
+    }
+
+    ///Asks for whatever the events we've just handled left us missing.
+    fn request_follow_ups(
+        &mut self,
+        players_to_request: HashSet<Uuid>,
+        wants_table_list: bool,
+        wants_start_info: bool,
+    ) {
+        let mut reqs_to_send = vec![];
+        if wants_table_list {
+            reqs_to_send.push(EventToServer::ListTables {
+                request_id: Some(self.io.next_request_id()),
+            });
+        }
+        if wants_start_info {
+            reqs_to_send.push(EventToServer::GetStartInformation {
+                request_id: Some(self.io.next_request_id()),
+            });
+        }
+        if let ChippyAppState::LoadedIn {
+            pending_player_reqs,
+            ..
+        } = &mut self.state
+        {
+            for uuid in players_to_request {
+                if !pending_player_reqs.values().any(|pending| *pending == uuid) {
+                    let request_id = self.io.next_request_id();
+                    pending_player_reqs.insert(request_id, uuid);
+                    reqs_to_send.push(EventToServer::GetSpecificPlayer(uuid, Some(request_id)));
+                }
+            }
         }
         self.io.send_reqs(&reqs_to_send);
     }
 }
 
+impl HandView {
+    ///Keeps up with one of the events about the hand being played at our table.
+    fn update(
+        &mut self,
+        event: EventToClient,
+        msgs_so_far: &mut Vec<(MessageSender, String)>,
+        players: &HashMap<Uuid, Player>,
+    ) {
+        match event {
+            EventToClient::HandStarted(..) => {
+                self.hole_cards.clear();
+                self.board.clear();
+                self.turn = None;
+                msgs_so_far.push((MessageSender::Admin, "New hand dealt".to_string()));
+            }
+            EventToClient::HoleCards(_, cards) => self.hole_cards = cards,
+            EventToClient::Board(_, cards) => self.board = cards,
+            EventToClient::ToAct {
+                player,
+                current_bet,
+                min_raise_to,
+                ..
+            } => {
+                self.turn = Some(Turn {
+                    player,
+                    current_bet,
+                    min_raise_to,
+                });
+            }
+            EventToClient::Showdown(_, shown) => {
+                self.turn = None;
+                for hand in shown {
+                    let mut cards = self.board.clone();
+                    cards.extend_from_slice(&hand.hole_cards);
+                    let name = players
+                        .get(&hand.player)
+                        .map_or_else(|| hand.player.to_string(), ToString::to_string);
+                    let msg = match HandRank::evaluate(&cards) {
+                        Ok(rank) => {
+                            format!("{name} shows {} - {rank}", show_cards(&hand.hole_cards))
+                        }
+                        Err(_) => format!("{name} shows {}", show_cards(&hand.hole_cards)),
+                    };
+                    msgs_so_far.push((MessageSender::Admin, msg));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn show_cards(cards: &[Card]) -> String {
+    if cards.is_empty() {
+        return "-".to_string();
+    }
+    cards
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+///Where to connect and who as, returning why connecting failed if it did.
+fn connect_menu(
+    io: &mut IOThread,
+    ui: &mut egui::Ui,
+    server_buffer: &mut String,
+    write_name_buffer: &mut String,
+) -> Option<String> {
+    if !io.is_disconnected() {
+        ui.horizontal(|ui| {
+            ui.label("Connecting...");
+            ui.spinner();
+        });
+        return None;
+    }
+
+    ui.vertical(|ui| {
+        ui.horizontal(|ui| {
+            ui.label("Server: ");
+            ui.text_edit_singleline(server_buffer);
+        });
+        ui.horizontal(|ui| {
+            ui.label("Name: ");
+            ui.text_edit_singleline(write_name_buffer);
+        });
+        if ui.button("Connect").clicked()
+            && let Err(e) = io.connect(server_buffer.take(), write_name_buffer.take())
+        {
+            error!("Error connecting to server: {e:?}");
+            return Some(e.to_string());
+        }
+        None
+    })
+    .inner
+}
+
+fn reconnecting_banner(ctx: &Context, failed: u32) {
+    egui::TopBottomPanel::top("reconnecting").show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            ui.spinner();
+            if failed == 0 {
+                ui.label("Reconnecting…");
+            } else {
+                ui.label(format!("Reconnecting… ({failed} failed attempt(s))"));
+            }
+        });
+    });
+}
+
+///The tables on the server to pick from, returning whether we want to quit.
+fn table_list(
+    io: &mut IOThread,
+    ui: &mut egui::Ui,
+    tables: &BTreeMap<TableId, TableSummary>,
+    new_table_buffer: &mut String,
+    last_error: Option<&str>,
+) -> bool {
+    let mut wants_to_quit = false;
+    ui.heading("Tables");
+    egui::Grid::new("tables").striped(true).show(ui, |ui| {
+        ui.label("Name");
+        ui.label("Players");
+        ui.label("Pot");
+        ui.end_row();
+
+        for table in tables.values() {
+            ui.label(table.name.as_str());
+            ui.label(table.player_count.to_string());
+            ui.label(table.pot_size.to_string());
+            if ui.button("Join").clicked() {
+                io.send_req(EventToServer::JoinTable(table.id));
+            }
+            ui.end_row();
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.text_edit_singleline(new_table_buffer);
+        if ui.button("Create Table").clicked() {
+            io.send_req(EventToServer::CreateTable {
+                name: new_table_buffer.take(),
+            });
+        }
+    });
+    ui.horizontal(|ui| {
+        if ui.button("Refresh").clicked() {
+            let request_id = Some(io.next_request_id());
+            io.send_req(EventToServer::ListTables { request_id });
+        }
+        if ui.button("Quit").clicked() {
+            wants_to_quit = true;
+        }
+    });
+    if let Some(last_error) = last_error {
+        ui.colored_label(egui::Color32::RED, last_error);
+    }
+    wants_to_quit
+}
+
+fn message_log(
+    ui: &mut egui::Ui,
+    msgs_so_far: &[(MessageSender, String)],
+    players: &HashMap<Uuid, Player>,
+    our_uuid: Uuid,
+) {
+    for (sender, content) in msgs_so_far {
+        match sender {
+            MessageSender::Player(uuid) => {
+                if let Some(player) = players.get(uuid) {
+                    if our_uuid == *uuid {
+                        ui.label(format!("{player} (you): {content}"));
+                    } else {
+                        ui.label(format!("{player}: {content}"));
+                    }
+                }
+            } //should always be OK but whatever
+            MessageSender::Admin => {
+                ui.label(format!("SERVER: {content}"));
+            }
+            MessageSender::Error(code) => {
+                ui.colored_label(egui::Color32::RED, format!("ERROR ({code:?}): {content}"));
+            }
+        }
+    }
+}
+
+///The buttons for playing our turn, greyed out whenever it isn't.
+fn betting_buttons(
+    io: &mut IOThread,
+    ui: &mut egui::Ui,
+    hand: &mut HandView,
+    our_uuid: Uuid,
+    pot: &Pot,
+    players: &HashMap<Uuid, Player>,
+) {
+    let Some(turn) = &hand.turn else {
+        return;
+    };
+    let my_turn = turn.player == our_uuid;
+    let my_bet = pot.ready_to_put_in.get(&our_uuid).copied().unwrap_or(0);
+    let balance = players.get(&our_uuid).map_or(0, |p| p.balance);
+    let to_call = turn.current_bet.saturating_sub(my_bet);
+    let raise_to = hand.raise_buffer.trim().parse::<u32>().ok();
+
+    ui.horizontal(|ui| {
+        if ui
+            .add_enabled(my_turn && to_call == 0, egui::Button::new("Check"))
+            .clicked()
+        {
+            io.send_req(EventToServer::Check);
+        }
+        if ui
+            .add_enabled(
+                my_turn && to_call > 0 && to_call <= balance,
+                egui::Button::new(format!("Call {to_call}")),
+            )
+            .clicked()
+        {
+            io.send_req(EventToServer::Call);
+        }
+        ui.text_edit_singleline(&mut hand.raise_buffer);
+        let can_raise = turn.min_raise_to.is_some_and(|min_to| {
+            raise_to.is_some_and(|to| to >= min_to && to - my_bet <= balance)
+        });
+        let raise_label = turn.min_raise_to.map_or_else(
+            || "Raise".to_string(),
+            |min_to| format!("Raise (min {min_to})"),
+        );
+        if ui
+            .add_enabled(my_turn && can_raise, egui::Button::new(raise_label))
+            .clicked()
+            && let Some(to) = raise_to
+        {
+            io.send_req(EventToServer::Raise { to });
+            hand.raise_buffer.clear();
+        }
+        if ui.add_enabled(my_turn, egui::Button::new("Fold")).clicked() {
+            io.send_req(EventToServer::Fold);
+        }
+        if ui
+            .add_enabled(
+                //without a raise to make, going all in is only allowed as a call
+                my_turn && balance > 0 && (turn.min_raise_to.is_some() || balance <= to_call),
+                egui::Button::new("All In"),
+            )
+            .clicked()
+        {
+            io.send_req(EventToServer::AllIn);
+        }
+    });
+}
+
+///Who's at the table, what's in the pot, and the cards we can see.
+fn game_info(
+    ui: &mut egui::Ui,
+    table: &TableSummary,
+    players: &HashMap<Uuid, Player>,
+    our_uuid: Uuid,
+    pot: &Pot,
+    hand: &HandView,
+) {
+    let name_of = |uuid: &Uuid| {
+        players.get(uuid).map(|player| {
+            if our_uuid == *uuid {
+                format!("{player} (you)")
+            } else {
+                player.to_string()
+            }
+        })
+    };
+
+    ui.vertical(|ui| {
+        ui.heading(table.name.as_str());
+        ui.label("Players: ");
+        for (uuid, player) in players {
+            if let Some(name) = name_of(uuid) {
+                ui.label(format!("\t{name} - {}", player.balance));
+            }
+        }
+        ui.label("Pot: ");
+        ui.label(format!("\tCurrent Value: {}", pot.current_value));
+        for (uuid, amt) in &pot.ready_to_put_in {
+            if let Some(name) = name_of(uuid) {
+                ui.label(format!("\t{name} - {amt}"));
+            } //should always be OK but whatever
+        }
+        for (i, side_pot) in pot.side_pots.iter().enumerate() {
+            let eligible: Vec<_> = side_pot.eligible.iter().filter_map(name_of).collect();
+            ui.label(format!(
+                "\tSide Pot {}: {} ({})",
+                i + 1,
+                side_pot.amount,
+                eligible.join(", ")
+            ));
+        }
+        ui.label(format!("Board: {}", show_cards(&hand.board)));
+        ui.label(format!("Your cards: {}", show_cards(&hand.hole_cards)));
+    });
+}
+
 impl App for ChippyApp {
     fn update(&mut self, ctx: &Context, _frame: &mut Frame) {
         ctx.request_repaint();
@@ -141,57 +623,61 @@ impl App for ChippyApp {
 
         let mut needs_to_reset = false;
         let mut error = None;
+        if let Some(failed) = self.io.is_reconnecting() {
+            reconnecting_banner(ctx, failed);
+        }
         match &mut self.state {
             ChippyAppState::WaitingMenu {
                 write_name_buffer,
                 server_buffer,
             } => {
                 egui::CentralPanel::default().show(ctx, |ui| {
-                    if self.io.is_disconnected() {
-                        ui.vertical(|ui| {
-                            ui.horizontal(|ui| {
-                                ui.label("Server: ");
-                                ui.text_edit_singleline(server_buffer);
-                            });
-                            ui.horizontal(|ui| {
-                                ui.label("Name: ");
-                                ui.text_edit_singleline(write_name_buffer);
-                            });
-                            if ui.button("Connect").clicked() {
-                                if let Err(e) = self
-                                    .io
-                                    .connect(server_buffer.take(), write_name_buffer.take())
-                                {
-                                    error!("Error connecting to server: {e:?}");
-                                    error = Some(e.to_string());
-                                }
-                            }
-                        });
-                    } else if self.io.is_waiting() {
-                        ui.horizontal(|ui| {
-                            ui.label("Connecting...");
-                            ui.spinner();
-                        });
-                    }
+                    error = connect_menu(&mut self.io, ui, server_buffer, write_name_buffer);
                 });
             }
-            ChippyAppState::ErrorHappened { error } => {
+            ChippyAppState::ErrorHappened {
+                error,
+                needs_refresh,
+            } => {
                 egui::CentralPanel::default().show(ctx, |ui| {
                     ui.label("Error connecting to server: ");
-                    ui.code(error);
-                    if ui.button("Try again?").clicked() {
+                    ui.code(error.as_str());
+                    if *needs_refresh {
+                        ui.label(
+                            "This version of chippy can't talk to the server. Please refresh the page to get the latest version.",
+                        );
+                    } else if ui.button("Try again?").clicked() {
                         needs_to_reset = true;
                     }
                 });
             }
+            ChippyAppState::Lobby {
+                tables,
+                new_table_buffer,
+                last_error,
+            } => {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    needs_to_reset |= table_list(
+                        &mut self.io,
+                        ui,
+                        tables,
+                        new_table_buffer,
+                        last_error.as_deref(),
+                    );
+                });
+            }
             ChippyAppState::LoadedIn {
                 send_msg_buffer,
                 msgs_so_far,
                 our_uuid,
+                table,
                 players,
                 pot,
+                hand,
+                ..
             } => {
                 egui::TopBottomPanel::bottom("send msg").show(ctx, |ui| {
+                    betting_buttons(&mut self.io, ui, hand, *our_uuid, pot, players);
                     ui.horizontal(|ui| {
                         ui.text_edit_singleline(send_msg_buffer);
                         if ui.button("Send Msg").clicked() {
@@ -199,51 +685,22 @@ impl App for ChippyApp {
                                 content: std::mem::take(send_msg_buffer),
                             });
                         }
+                        if ui.button("Deal Hand").clicked() {
+                            self.io.send_req(EventToServer::StartHand);
+                        }
+                        if ui.button("Leave Table").clicked() {
+                            self.io.send_req(EventToServer::LeaveTable);
+                        }
                         if ui.button("Quit").clicked() {
                             needs_to_reset = true;
                         }
                     });
                 });
                 egui::SidePanel::right("view msg").show(ctx, |ui| {
-                    for (sender, content) in msgs_so_far {
-                        match sender {
-                            MessageSender::Player(uuid) => {
-                                if let Some(player) = players.get(uuid) {
-                                    if *our_uuid == *uuid {
-                                        ui.label(format!("{player} (you): {content}"));
-                                    } else {
-                                        ui.label(format!("{player}: {content}"));
-                                    }
-                                }
-                            } //should always be OK but whatever
-                            MessageSender::Admin => {
-                                ui.label(format!("SERVER: {content}"));
-                            }
-                        }
-                    }
+                    message_log(ui, msgs_so_far, players, *our_uuid);
                 });
                 egui::SidePanel::left("game info").show(ctx, |ui| {
-                    ui.vertical(|ui| {
-                        ui.label("Players: ");
-                        for (uuid, player) in players.iter() {
-                            if *our_uuid == *uuid {
-                                ui.label(format!("\t{player} (you) - {}", player.balance));
-                            } else {
-                                ui.label(format!("\t{player} - {}", player.balance));
-                            }
-                        }
-                        ui.label("Pot: ");
-                        ui.label(format!("\tCurrent Value: {}", pot.current_value));
-                        for (uuid, amt) in &pot.ready_to_put_in {
-                            if let Some(player) = players.get(uuid) {
-                                if *our_uuid == *uuid {
-                                    ui.label(format!("\t{player} (you)- {}", amt));
-                                } else {
-                                    ui.label(format!("\t{player} - {}", amt));
-                                }
-                            } //should always be OK but whatever
-                        }
-                    });
+                    game_info(ui, table, players, *our_uuid, pot, hand);
                 });
             }
         }
@@ -253,7 +710,10 @@ impl App for ChippyApp {
             self.state = ChippyAppState::default();
         } else if let Some(error) = error {
             self.io.quit();
-            self.state = ChippyAppState::ErrorHappened { error };
+            self.state = ChippyAppState::ErrorHappened {
+                error,
+                needs_refresh: false,
+            };
         }
     }
 
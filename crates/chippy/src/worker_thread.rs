@@ -1,11 +1,44 @@
 use ewebsock::{Options, WsEvent, WsMessage, WsReceiver, WsSender};
-use fishandchippy::events::client::{ClientEventDeserer, EventToClient};
+use fishandchippy::events::client::EventToClient;
+use fishandchippy::events::error_code::ErrorCode;
 use fishandchippy::events::server::EventToServer;
-use fishandchippy::ser_glue::{DeserMachine, Deserable, DesiredInput, FsmResult, Serable};
+use fishandchippy::events::{PROTOCOL_VERSION, RequestId, ResumeToken};
+use fishandchippy::ser_glue::{Deserable, Serable};
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
 use uuid::Uuid;
+use web_time::Instant;
+
+///How long to wait before the first reconnect attempt - this doubles after every failed attempt.
+const FIRST_RECONNECT_DELAY: Duration = Duration::from_millis(500);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+const MAX_RECONNECT_ATTEMPTS: u32 = 10;
+
+///The server turned us away before we joined, which almost always means this page is out of date.
+#[derive(Debug)]
+pub struct IncompatibleServer(pub String);
+
+impl Display for IncompatibleServer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Server refused connection: {}", self.0)
+    }
+}
+
+impl std::error::Error for IncompatibleServer {}
 
 pub struct IOThread {
     state: IOThreadState,
+    next_request_id: u32,
+    ///Set once we've been introduced, so we know how to get back in if the connection drops.
+    session: Option<Session>,
+    ///How many reconnects have failed since we were last introduced.
+    failed_reconnects: u32,
+}
+
+struct Session {
+    server: String,
+    name: String,
+    resume_token: Option<ResumeToken>,
 }
 
 enum IOThreadState {
@@ -13,25 +46,33 @@ enum IOThreadState {
     TryingToConnect {
         tx: WsSender,
         rx: WsReceiver,
+        server: String,
         name: String,
     },
     WaitingOnAcknowledgement {
         tx: WsSender,
         rx: WsReceiver,
+        server: String,
+        name: String,
     },
     Connected {
         tx: WsSender,
         rx: WsReceiver,
         uuid: Uuid,
     },
+    ///The connection dropped after we'd joined, and we're going to try again at `next_try_at`.
+    Reconnecting {
+        attempt: u32,
+        next_try_at: Instant,
+    },
 }
 
 impl IOThread {
     fn get_tx_rx(&mut self) -> Option<(&mut WsSender, &mut WsReceiver)> {
         match &mut self.state {
-            IOThreadState::Disconnected => None,
+            IOThreadState::Disconnected | IOThreadState::Reconnecting { .. } => None,
             IOThreadState::TryingToConnect { tx, rx, .. }
-            | IOThreadState::WaitingOnAcknowledgement { tx, rx }
+            | IOThreadState::WaitingOnAcknowledgement { tx, rx, .. }
             | IOThreadState::Connected { tx, rx, .. } => Some((tx, rx)),
         }
     }
@@ -39,9 +80,18 @@ impl IOThread {
     pub fn new() -> Self {
         Self {
             state: IOThreadState::Disconnected,
+            next_request_id: 0,
+            session: None,
+            failed_reconnects: 0,
         }
     }
 
+    pub const fn next_request_id(&mut self) -> RequestId {
+        let id = RequestId(self.next_request_id);
+        self.next_request_id = self.next_request_id.wrapping_add(1);
+        id
+    }
+
     pub fn is_disconnected(&self) -> bool {
         matches!(self.state, IOThreadState::Disconnected)
     }
@@ -57,28 +107,95 @@ impl IOThread {
             _ => None,
         }
     }
+    ///If we've lost the connection and are trying to get it back, how many attempts have failed so far.
+    pub fn is_reconnecting(&self) -> Option<u32> {
+        if self.session.is_none() || self.is_connected().is_some() {
+            None
+        } else {
+            Some(self.failed_reconnects)
+        }
+    }
 
     pub fn connect(&mut self, server: String, name: String) -> color_eyre::Result<()> {
-        let (tx, rx) = ewebsock::connect(server, Options::default()).map_err(string_to_eyre)?;
+        let (tx, rx) =
+            ewebsock::connect(server.clone(), Options::default()).map_err(string_to_eyre)?;
 
-        self.state = IOThreadState::TryingToConnect { tx, rx, name };
+        self.state = IOThreadState::TryingToConnect {
+            tx,
+            rx,
+            server,
+            name,
+        };
 
         Ok(())
     }
 
     pub fn quit(&mut self) {
+        self.session = None;
+        self.failed_reconnects = 0;
+        self.close_socket();
+
+        //TODO: more quit logic?
+    }
+
+    fn close_socket(&mut self) {
         let tx_and_rx = match std::mem::replace(&mut self.state, IOThreadState::Disconnected) {
-            IOThreadState::Disconnected => None,
+            IOThreadState::Disconnected | IOThreadState::Reconnecting { .. } => None,
             IOThreadState::TryingToConnect { tx, rx, .. } => Some((tx, rx)),
-            IOThreadState::WaitingOnAcknowledgement { tx, rx } => Some((tx, rx)),
+            IOThreadState::WaitingOnAcknowledgement { tx, rx, .. } => Some((tx, rx)),
             IOThreadState::Connected { tx, rx, .. } => Some((tx, rx)),
         };
         if let Some((mut tx, _rx)) = tx_and_rx {
             //_rx just in case i need to do anything with it in the future
             tx.close();
         }
+    }
 
-        //TODO: more quit logic?
+    ///Drops the current connection and schedules another go, waiting longer after each failure. Gives up after [`MAX_RECONNECT_ATTEMPTS`].
+    fn schedule_reconnect(&mut self, why: &str) -> color_eyre::Result<()> {
+        self.close_socket();
+
+        let attempt = self.failed_reconnects;
+        if attempt >= MAX_RECONNECT_ATTEMPTS {
+            self.quit();
+            return Err(color_eyre::Report::msg(format!(
+                "Gave up reconnecting after {attempt} attempts: {why}"
+            )));
+        }
+        self.failed_reconnects += 1;
+
+        let delay = FIRST_RECONNECT_DELAY
+            .saturating_mul(1 << attempt)
+            .min(MAX_RECONNECT_DELAY);
+        warn!("Connection lost ({why}), reconnecting in {delay:?}");
+        self.state = IOThreadState::Reconnecting {
+            attempt,
+            next_try_at: Instant::now() + delay,
+        };
+        Ok(())
+    }
+
+    ///Has another go at connecting, if it's time to.
+    fn poll_reconnect(&mut self) -> color_eyre::Result<()> {
+        let IOThreadState::Reconnecting {
+            attempt,
+            next_try_at,
+        } = self.state
+        else {
+            return Ok(());
+        };
+        if Instant::now() < next_try_at {
+            return Ok(());
+        }
+        let Some(Session { server, name, .. }) = &self.session else {
+            return Ok(());
+        };
+
+        info!("Reconnecting, attempt {}", attempt + 1);
+        if let Err(e) = self.connect(server.clone(), name.clone()) {
+            return self.schedule_reconnect(&e.to_string());
+        }
+        Ok(())
     }
 
     pub fn send_req(&mut self, req: EventToServer) {
@@ -100,119 +217,144 @@ impl IOThread {
         }
     }
 
-    pub fn poll_and_get_events(
-        &mut self,
-    ) -> color_eyre::Result<impl IntoIterator<Item = EventToClient>> {
+    ///Once the socket's open, tells the server which protocol we speak and who we are.
+    fn say_hello(&mut self) {
+        info!("Connection opened :)");
+        let IOThreadState::TryingToConnect {
+            tx: mut new_tx,
+            rx: new_rx,
+            server,
+            name,
+        } = std::mem::replace(&mut self.state, IOThreadState::Disconnected)
+        else {
+            unreachable!();
+        };
+
+        new_tx.send(WsMessage::Binary(
+            EventToServer::Hello {
+                protocol_version: PROTOCOL_VERSION,
+            }
+            .ser()
+            .1,
+        ));
+        //if we've been here before, try to pick up where we left off
+        let join = self
+            .session
+            .as_ref()
+            .and_then(|s| s.resume_token)
+            .map_or_else(
+                || EventToServer::Introduction { name: name.clone() },
+                |token| EventToServer::Resume { token },
+            );
+        new_tx.send(WsMessage::Binary(join.ser().1));
+        self.state = IOThreadState::WaitingOnAcknowledgement {
+            tx: new_tx,
+            rx: new_rx,
+            server,
+            name,
+        };
+    }
+
+    ///The server's let us in, so remember how to get back in if we drop out.
+    fn finish_joining(&mut self, uuid: Uuid, resume_token: ResumeToken) {
+        info!("Acknowledgement received, joining server");
+        let IOThreadState::WaitingOnAcknowledgement {
+            tx: new_tx,
+            rx: new_rx,
+            server,
+            name,
+        } = std::mem::replace(&mut self.state, IOThreadState::Disconnected)
+        else {
+            unreachable!()
+        };
+
+        info!("IO now connected and introduced");
+        self.session = Some(Session {
+            server,
+            name,
+            resume_token: Some(resume_token),
+        });
+        self.failed_reconnects = 0;
+        self.state = IOThreadState::Connected {
+            tx: new_tx,
+            rx: new_rx,
+            uuid,
+        };
+    }
+
+    pub fn poll_and_get_events(&mut self) -> color_eyre::Result<Vec<EventToClient>> {
+        self.poll_reconnect()?;
+
+        //can't ask self while rx is borrowed from it
+        let mut waiting = self.is_waiting();
         let Some((_tx, mut rx)) = self.get_tx_rx() else {
             return Ok(vec![]);
         };
 
         let mut evts = vec![];
+        let mut needs_reintroduction = false;
 
         while let Some(to_be_processed) = rx.try_recv() {
             match to_be_processed {
                 WsEvent::Opened => {
-                    info!("Connection opened :)");
-                    let IOThreadState::TryingToConnect {
-                        tx: mut new_tx,
-                        rx: new_rx,
-                        name,
-                    } = std::mem::replace(&mut self.state, IOThreadState::Disconnected)
-                    else {
-                        unreachable!();
-                    };
-
-                    new_tx.send(WsMessage::Binary(
-                        EventToServer::Introduction { name }.ser().1,
-                    ));
-                    self.state = IOThreadState::WaitingOnAcknowledgement {
-                        tx: new_tx,
-                        rx: new_rx,
-                    };
-
+                    self.say_hello();
                     let (_, new_new_rx) = self.get_tx_rx().unwrap();
                     rx = new_new_rx;
                 }
                 WsEvent::Error(uhoh) => {
+                    if self.session.is_some() {
+                        self.schedule_reconnect(&uhoh)?;
+                        return Ok(evts);
+                    }
                     return Err(string_to_eyre(uhoh));
                 }
                 WsEvent::Closed => {
+                    if self.session.is_some() {
+                        self.schedule_reconnect("connection closed")?;
+                        return Ok(evts);
+                    }
                     self.quit();
+                    if waiting {
+                        return Err(IncompatibleServer(
+                            "connection closed before we could join".to_string(),
+                        )
+                        .into());
+                    }
                     return Ok(evts);
                 }
                 WsEvent::Message(msg) => {
                     info!("recevied {msg:?}");
                     match msg {
                         WsMessage::Binary(binary) => {
-                            let mut binary = binary.into_iter().peekable();
-                            let mut deserer = EventToClient::deser();
-
-                            loop {
-                                if binary.peek().is_none()
-                                    && matches!(deserer, ClientEventDeserer::Start(_))
+                            for evt in EventToClient::driver().feed_all(&binary)? {
+                                if let EventToClient::Welcome { protocol_version } = evt {
+                                    info!("Server accepted protocol version {protocol_version}");
+                                }
+                                //the server only sends admin messages before we join to say why it's turning us away
+                                if waiting && let EventToClient::AdminMsg(reason) = evt {
+                                    self.quit();
+                                    return Err(IncompatibleServer(reason).into());
+                                }
+                                //we were gone too long, so join as someone new instead
+                                if waiting
+                                    && let EventToClient::Error {
+                                        code: ErrorCode::UnknownResumeToken,
+                                        ..
+                                    } = evt
                                 {
-                                    info!("breaking @ {deserer:?}");
-                                    break;
+                                    needs_reintroduction = true;
+                                    continue;
                                 }
 
-                                match deserer.wants_read() {
-                                    DesiredInput::Byte(space) => {
-                                        if let Some(byte) = binary.next() {
-                                            *space = byte;
-                                            info!("writing {byte:x}");
-                                            deserer.finish_bytes_for_writing(1);
-                                        }
-                                    }
-                                    DesiredInput::Bytes(space) => {
-                                        let mut n = 0;
-                                        for i in 0..space.len() {
-                                            let Some(byte) = binary.next() else {
-                                                break;
-                                            };
-                                            space[i] = byte;
-
-                                            n += 1;
-                                        }
-                                        deserer.finish_bytes_for_writing(n);
-                                    }
-                                    DesiredInput::ProcessMe => {
-                                        deserer = match deserer.process()? {
-                                            FsmResult::Continue(cont) => cont,
-                                            FsmResult::Done(evt) => {
-                                                if let EventToClient::Introduced(uuid) = evt {
-                                                    info!(
-                                                        "Acknowledgement received, joining server"
-                                                    );
-                                                    let IOThreadState::WaitingOnAcknowledgement {
-                                                        tx: new_tx,
-                                                        rx: new_rx,
-                                                    } = std::mem::replace(
-                                                        &mut self.state,
-                                                        IOThreadState::Disconnected,
-                                                    )
-                                                    else {
-                                                        unreachable!()
-                                                    };
-
-                                                    info!("IO now connected and introduced");
-                                                    self.state = IOThreadState::Connected {
-                                                        tx: new_tx,
-                                                        rx: new_rx,
-                                                        uuid,
-                                                    };
-
-                                                    let (_, new_new_rx) = self.get_tx_rx().unwrap();
-                                                    rx = new_new_rx;
-                                                }
-
-                                                info!("\tparsed as {evt:?}");
-                                                evts.push(evt);
-                                                EventToClient::deser()
-                                            }
-                                        }
-                                    }
-                                    DesiredInput::Extra => unreachable!(),
+                                if let EventToClient::Introduced { uuid, resume_token } = evt {
+                                    self.finish_joining(uuid, resume_token);
+                                    waiting = false;
+                                    let (_, new_new_rx) = self.get_tx_rx().unwrap();
+                                    rx = new_new_rx;
                                 }
+
+                                info!("\tparsed as {evt:?}");
+                                evts.push(evt);
                             }
                         }
                         unexpected => warn!("Received unexpected message: {unexpected:?}"),
@@ -221,6 +363,18 @@ impl IOThread {
             }
         }
 
+        if needs_reintroduction
+            && let IOThreadState::WaitingOnAcknowledgement { tx, name, .. } = &mut self.state
+        {
+            warn!("Couldn't resume, introducing ourselves again");
+            if let Some(session) = &mut self.session {
+                session.resume_token = None;
+            }
+            tx.send(WsMessage::Binary(
+                EventToServer::Introduction { name: name.clone() }.ser().1,
+            ));
+        }
+
         Ok(evts)
     }
 }
@@ -1,146 +1,986 @@
-use crate::Table;
+use crate::hand::{Action, ActionError, StartHandError};
+use crate::lobby::{CreateTableError, Lobby, TableHandle};
+use crate::metrics::METRICS;
+use crate::table::{AwardVote, BalanceChangeReason, BalanceError};
 use fishandchippy::events::client::EventToClient;
+use fishandchippy::events::error_code::ErrorCode;
 use fishandchippy::events::server::EventToServer;
-use fishandchippy::game_types::player::Player;
+use fishandchippy::events::{PROTOCOL_VERSION, RequestId, ResumeToken};
+use fishandchippy::game_types::table::TableId;
 use std::fmt::{Display, Formatter};
 use std::net::SocketAddr;
+use std::sync::Arc;
 use tokio::sync::RwLock;
+use tokio::sync::broadcast::Receiver;
+use tokio::sync::broadcast::error::RecvError;
 use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tracing::{Span, info, warn};
 use uuid::Uuid;
 
+fn note_resync(missed: u64, from: &str) {
+    let total = METRICS.broadcast_lagged(missed);
+    warn!(missed, from, total, "Fell behind, resyncing");
+}
+
 enum ClientState {
+    AwaitingHello,
     Connected,
-    Introduced { uuid: Uuid },
+    Introduced {
+        uuid: Uuid,
+        name: String,
+        resume_token: ResumeToken,
+    },
+    Rejected,
     Closed,
 }
 
+///The table a client is sat at, and where they hear about what's going on there.
+struct Seat {
+    table: Arc<TableHandle>,
+    events: Receiver<EventToClient>,
+    ///Set when we hear a hand has been dealt to us, until we've sent our hole cards on.
+    owed_hole_cards: bool,
+}
+
+impl Seat {
+    fn new(table: Arc<TableHandle>) -> Self {
+        Self {
+            events: table.events.subscribe(),
+            table,
+            owed_hole_cards: false,
+        }
+    }
+}
+
 pub struct Client {
     state: ClientState,
+    seat: Option<Seat>,
+    ///Where we hear about tables changing, once we've introduced ourselves.
+    lobby_events: Option<Receiver<EventToClient>>,
     addr: SocketAddr,
-    global_msgs_to_send: Vec<EventToClient>,
     local_msgs_to_send: Vec<EventToClient>,
 }
 
 impl Client {
     pub const fn new(addr: SocketAddr) -> Self {
         Self {
-            state: ClientState::Connected,
+            state: ClientState::AwaitingHello,
+            seat: None,
+            lobby_events: None,
             addr,
-            global_msgs_to_send: vec![],
             local_msgs_to_send: vec![],
         }
     }
 }
 
+impl Drop for Client {
+    fn drop(&mut self) {
+        //connections that error out never get closed properly
+        if matches!(self.state, ClientState::Introduced { .. }) {
+            METRICS.player_left();
+        }
+    }
+}
+
 impl Display for Client {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match &self.state {
-            ClientState::Connected => write!(f, "[{}]", self.addr.ip()),
-            ClientState::Introduced { uuid } => write!(f, "[{uuid}]"),
-            ClientState::Closed => write!(f, "[closed]"),
+            ClientState::AwaitingHello | ClientState::Connected => {
+                write!(f, "[{}]", self.addr.ip())
+            }
+            ClientState::Introduced { uuid, .. } => write!(f, "[{uuid}]"),
+            ClientState::Rejected | ClientState::Closed => write!(f, "[closed]"),
         }
     }
 }
 
-impl Client {
-    #[allow(clippy::significant_drop_tightening)]
-    pub async fn close(&mut self, reason: Option<&CloseFrame>, table: &RwLock<Table>) {
-        println!("{self} closing for {reason:?}");
-
-        if let ClientState::Introduced { uuid, .. } = &self.state {
-            let table = table.write().await;
+async fn recv_or_pending(
+    rx: Option<&mut Receiver<EventToClient>>,
+) -> Result<EventToClient, RecvError> {
+    match rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
 
-            let quit_msg = table.players.get(uuid).map_or_else(
-                || format!("{uuid:?} left the server"),
-                |player| format!("{:?} left the server", player.name),
-            );
-            self.global_msgs_to_send
-                .push(EventToClient::AdminMsg(quit_msg));
+impl Client {
+    ///Closes the connection. Introduced players keep their seat for a while, in case they [`EventToServer::Resume`].
+    pub async fn close(&mut self, reason: Option<&CloseFrame>, lobby: &RwLock<Lobby>) {
+        info!(?reason, "Closing");
 
-            table.pot.ready_to_put_in.remove(uuid);
-            table.players.remove(uuid);
+        if let ClientState::Introduced {
+            uuid,
+            name,
+            resume_token,
+        } = std::mem::replace(&mut self.state, ClientState::Closed)
+        {
+            METRICS.player_left();
+            let table = self.seat.take().map(|seat| seat.table);
+            if let Some(table) = &table {
+                table.broadcast(EventToClient::AdminMsg(format!("{name:?} lost connection")));
+                table.set_away(uuid, true).await;
+            }
+            lobby.write().await.detach(resume_token, uuid, name, table);
         }
+    }
 
-        self.state = ClientState::Closed;
+    ///Tells the client why it can't stay, in a way that even old clients can show, and then closes it.
+    fn reject(&mut self, reason: String) {
+        warn!(%reason, "Rejected");
+        self.local_msgs_to_send
+            .push(EventToClient::AdminMsg(reason));
+        self.state = ClientState::Rejected;
+    }
+    ///Lets the client know that their request was turned down.
+    fn send_error(
+        &mut self,
+        code: ErrorCode,
+        message: impl Into<String>,
+        request_id: Option<RequestId>,
+    ) {
+        let message = message.into();
+        warn!(?code, %message, "Request rejected");
+        self.local_msgs_to_send.push(EventToClient::Error {
+            code,
+            message,
+            request_id,
+        });
+    }
+    pub const fn was_rejected(&self) -> bool {
+        matches!(self.state, ClientState::Rejected)
     }
 
     pub const fn should_quit(&self) -> bool {
-        matches!(self.state, ClientState::Closed)
+        matches!(self.state, ClientState::Rejected | ClientState::Closed)
     }
     pub const fn can_interact(&self) -> Option<Uuid> {
         match self.state {
-            ClientState::Introduced { uuid } => Some(uuid),
+            ClientState::Introduced { uuid, .. } => Some(uuid),
             _ => None,
         }
     }
+    ///Our UUID and the table we're sat at, or sends an error explaining why the client can't be `doing` anything yet.
+    fn seated(
+        &mut self,
+        doing: &str,
+        request_id: Option<RequestId>,
+    ) -> Option<(Uuid, Arc<TableHandle>)> {
+        let Some(uuid) = self.can_interact() else {
+            self.send_error(
+                ErrorCode::NotIntroduced,
+                format!("You need to introduce yourself before {doing}"),
+                request_id,
+            );
+            return None;
+        };
+        let Some(seat) = &self.seat else {
+            self.send_error(
+                ErrorCode::NotAtTable,
+                format!("You need to sit at a table before {doing}"),
+                request_id,
+            );
+            return None;
+        };
+        Some((uuid, seat.table.clone()))
+    }
 
     pub fn local_msgs_to_send(&mut self) -> impl Iterator<Item = EventToClient> {
         self.local_msgs_to_send.drain(..)
     }
-    pub fn global_msgs_to_send(&mut self) -> impl Iterator<Item = EventToClient> {
-        self.global_msgs_to_send.drain(..)
+    ///Waits for something to happen at our table, or in the lobby if we're not sat at one.
+    ///
+    /// If we fell too far behind to hear everything, this gives back `None` after queueing up fresh copies of whatever we might have missed. It does the same after a hand is dealt to us, with our hole cards.
+    pub async fn next_broadcast(&mut self, lobby: &RwLock<Lobby>) -> Option<EventToClient> {
+        //only cleared once they've been queued, so getting cancelled while waiting for the table just means trying again next time
+        if let Some(seat) = self.seat.as_mut().filter(|seat| seat.owed_hole_cards) {
+            let table = seat.table.clone();
+            self.send_hole_cards(&table).await;
+            if let Some(seat) = &mut self.seat {
+                seat.owed_hole_cards = false;
+            }
+            return None;
+        }
+
+        loop {
+            tokio::select! {
+                evt = recv_or_pending(self.seat.as_mut().map(|seat| &mut seat.events)) => {
+                    return match evt {
+                        Ok(evt) => {
+                            let uuid = self.can_interact();
+                            if let (EventToClient::HandStarted(_, players), Some(seat)) =
+                                (&evt, &mut self.seat)
+                            {
+                                seat.owed_hole_cards = uuid.is_some_and(|uuid| players.contains(&uuid));
+                            }
+                            Some(evt)
+                        }
+                        Err(RecvError::Lagged(missed)) => {
+                            if let Some(seat) = &self.seat {
+                                let table = seat.table.clone();
+                                note_resync(missed, &format!("table {}", table.id));
+                                self.send_table_state(&table, None).await;
+                            }
+                            None
+                        }
+                        //we hold onto the table, so its sender can't have gone
+                        Err(RecvError::Closed) => None,
+                    };
+                },
+                evt = recv_or_pending(self.lobby_events.as_mut()) => {
+                    //still have to keep up with the lobby while sat down, but nothing there is interesting
+                    if self.seat.is_some() {
+                        continue;
+                    }
+                    return match evt {
+                        Ok(evt) => Some(evt),
+                        Err(RecvError::Lagged(missed)) => {
+                            note_resync(missed, "the lobby");
+                            let tables = lobby.read().await.summaries().await;
+                            self.local_msgs_to_send
+                                .push(EventToClient::TableList(tables, None));
+                            None
+                        }
+                        Err(RecvError::Closed) => {
+                            self.lobby_events = None;
+                            None
+                        }
+                    };
+                }
+            }
+        }
+    }
+
+    ///Sends everything the client needs to draw the table from scratch.
+    async fn send_table_state(&mut self, handle: &TableHandle, request_id: Option<RequestId>) {
+        let table = handle.state.read().await;
+        self.local_msgs_to_send.push(EventToClient::AllPlayers(
+            handle.id,
+            table.players.clone(),
+            request_id,
+        ));
+        self.local_msgs_to_send
+            .push(EventToClient::Pot(handle.id, table.pot.clone(), request_id));
+        if let Some(hand) = &table.hand {
+            self.local_msgs_to_send
+                .push(EventToClient::Board(handle.id, hand.board().to_vec()));
+            if let Some(player) = hand.to_act() {
+                self.local_msgs_to_send.push(EventToClient::ToAct {
+                    table: handle.id,
+                    player,
+                    current_bet: hand.current_bet(),
+                    min_raise_to: hand.can_raise().then_some(hand.min_raise_to()),
+                });
+            }
+        }
+        drop(table);
+        self.send_hole_cards(handle).await;
     }
 
-    pub async fn process_event(&mut self, evt: EventToServer, table: &RwLock<Table>) {
+    ///Privately sends us our cards in the hand being played, if we've been dealt in.
+    async fn send_hole_cards(&mut self, handle: &TableHandle) {
+        let Some(uuid) = self.can_interact() else {
+            return;
+        };
+        let hole_cards = handle
+            .state
+            .read()
+            .await
+            .hand
+            .as_ref()
+            .and_then(|hand| hand.hole_cards(uuid));
+        if let Some(hole_cards) = hole_cards {
+            self.local_msgs_to_send
+                .push(EventToClient::HoleCards(handle.id, hole_cards.to_vec()));
+        }
+    }
+
+    pub async fn process_event(&mut self, evt: EventToServer, lobby: &RwLock<Lobby>) {
+        if self.should_quit() {
+            return;
+        }
+        if matches!(self.state, ClientState::AwaitingHello) {
+            self.process_hello(&evt);
+            return;
+        }
+
         match evt {
+            EventToServer::Hello { .. } => {
+                self.send_error(ErrorCode::AlreadyGreeted, "You've already said hello", None);
+            }
             EventToServer::Introduction { name } => {
                 if matches!(self.state, ClientState::Connected) {
-                    let uuid = Uuid::new_v4();
-
-                    self.local_msgs_to_send
-                        .push(EventToClient::Introduced(uuid));
-                    self.global_msgs_to_send
-                        .push(EventToClient::AdminMsg(format!(
-                            "\"{name}\" joined the server"
-                        )));
-
-                    table.write().await.players.insert(
-                        uuid,
-                        Player {
-                            name: name.clone(),
-                            balance: 0,
-                        },
+                    self.introduce(Uuid::new_v4(), name, lobby).await;
+                } else {
+                    self.send_error(
+                        ErrorCode::AlreadyIntroduced,
+                        "You've already introduced yourself",
+                        None,
                     );
-
-                    self.state = ClientState::Introduced { uuid };
                 }
             }
+            EventToServer::Resume { token } => self.resume(token, lobby).await,
             EventToServer::SendMessage { content } => {
-                if let Some(uuid) = self.can_interact() {
-                    self.global_msgs_to_send
-                        .push(EventToClient::TxtSent(uuid, content));
+                if let Some((uuid, table)) = self.seated("sending messages", None) {
+                    table.broadcast(EventToClient::TxtSent(table.id, uuid, content));
                 }
             }
-            EventToServer::GetStartInformation => {
-                let table = table.read().await;
-                self.local_msgs_to_send
-                    .push(EventToClient::AllPlayers(table.players.clone()));
+            EventToServer::GetStartInformation { request_id } => {
+                if let Some((_, handle)) = self.seated("asking about the table", request_id) {
+                    self.send_table_state(&handle, request_id).await;
+                }
+            }
+            EventToServer::GetSpecificPlayer(their_uuid, request_id) => {
+                let Some((_, handle)) = self.seated("asking about players", request_id) else {
+                    return;
+                };
+                let player = handle.state.read().await.players.get(&their_uuid).cloned();
+                if let Some(player) = player {
+                    self.local_msgs_to_send.push(EventToClient::SpecificPlayer(
+                        handle.id, their_uuid, player, request_id,
+                    ));
+                } else {
+                    self.send_error(
+                        ErrorCode::UnknownPlayer,
+                        format!("No player found with UUID {their_uuid}"),
+                        request_id,
+                    );
+                }
+            }
+            EventToServer::AddToPot(value) => self.add_to_pot(value).await,
+            EventToServer::CloseBettingRound => self.close_betting_round().await,
+            EventToServer::AwardPot { winner } => self.award_pot(&[winner]).await,
+            EventToServer::SplitPot { winners } => self.award_pot(&winners).await,
+            EventToServer::BuyIn(amount) => self.buy_in(Some(amount)).await,
+            EventToServer::Rebuy => self.buy_in(None).await,
+            EventToServer::ListTables { request_id } => {
+                let tables = lobby.read().await.summaries().await;
                 self.local_msgs_to_send
-                    .push(EventToClient::Pot(table.pot.clone()));
+                    .push(EventToClient::TableList(tables, request_id));
             }
-            EventToServer::GetSpecificPlayer(their_uuid) => {
-                let table = table.read().await;
-                if let Some(player) = table.players.get(&their_uuid).cloned() {
-                    self.local_msgs_to_send
-                        .push(EventToClient::SpecificPlayer(their_uuid, player));
+            EventToServer::CreateTable { name } => self.create_table(name, lobby).await,
+            EventToServer::JoinTable(id) => self.join_table(id, lobby).await,
+            EventToServer::StartHand => self.start_hand().await,
+            EventToServer::Check => self.act(Action::Check).await,
+            EventToServer::Call => self.act(Action::Call).await,
+            EventToServer::Raise { to } => self.act(Action::Raise { to }).await,
+            EventToServer::Fold => self.act(Action::Fold).await,
+            EventToServer::AllIn => self.act(Action::AllIn).await,
+            EventToServer::LeaveTable => {
+                if let Some(id) = self.leave_table().await {
+                    self.local_msgs_to_send.push(EventToClient::LeftTable(id));
+                } else {
+                    self.send_error(ErrorCode::NotAtTable, "You're not at a table", None);
                 }
             }
-            EventToServer::AddToPot(value) => {
-                if let Some(uuid) = self.can_interact() {
-                    let mut table = table.write().await;
-                    if let Some(player) = table.players.get_mut(&uuid)
-                        && let Some(new_balance) = player.balance.checked_sub(value)
-                    {
-                        player.balance = new_balance;
-                        self.local_msgs_to_send
-                            .push(EventToClient::SpecificPlayer(uuid, player.clone()));
-
-                        *table.pot.ready_to_put_in.entry(uuid).or_default() += value;
-                        self.global_msgs_to_send
-                            .push(EventToClient::Pot(table.pot.clone()));
-                    }
+        }
+    }
+
+    ///Handles the first event on a connection, which has to be a compatible [`EventToServer::Hello`].
+    fn process_hello(&mut self, evt: &EventToServer) {
+        match *evt {
+            EventToServer::Hello { protocol_version } if protocol_version == PROTOCOL_VERSION => {
+                self.local_msgs_to_send
+                    .push(EventToClient::Welcome { protocol_version });
+                self.state = ClientState::Connected;
+            }
+            EventToServer::Hello { protocol_version } => self.reject(format!(
+                "This server speaks protocol version {PROTOCOL_VERSION}, but your client speaks version {protocol_version}. Please refresh the page to get a compatible version."
+            )),
+            _ => self.reject(
+                "Your client didn't say which protocol version it speaks. Please refresh the page to get the latest version.".to_string(),
+            ),
+        }
+    }
+
+    ///Moves a connected client into the lobby as `uuid`, with a fresh resume token.
+    async fn introduce(&mut self, uuid: Uuid, name: String, lobby: &RwLock<Lobby>) {
+        let resume_token = ResumeToken::new_random();
+        //the connection's span was made before we knew who they were
+        Span::current().record("uuid", tracing::field::display(uuid));
+        info!(%name, "Introduced");
+        METRICS.player_introduced();
+        self.local_msgs_to_send
+            .push(EventToClient::Introduced { uuid, resume_token });
+        {
+            let mut lobby = lobby.write().await;
+            lobby.go_online(resume_token, uuid, name.clone());
+            self.lobby_events = Some(lobby.subscribe());
+        }
+        self.state = ClientState::Introduced {
+            uuid,
+            name,
+            resume_token,
+        };
+    }
+
+    async fn resume(&mut self, token: ResumeToken, lobby: &RwLock<Lobby>) {
+        if !matches!(self.state, ClientState::Connected) {
+            self.send_error(
+                ErrorCode::AlreadyIntroduced,
+                "You can only resume instead of introducing yourself",
+                None,
+            );
+            return;
+        }
+        let session = lobby.write().await.reattach(token);
+        let Some(session) = session else {
+            self.send_error(
+                ErrorCode::UnknownResumeToken,
+                "That session has expired - please introduce yourself again",
+                None,
+            );
+            return;
+        };
+
+        let name = session.name.clone();
+        self.introduce(session.uuid, session.name, lobby).await;
+        info!("Resumed");
+
+        if let Some(table) = session.table {
+            self.seat = Some(Seat::new(table.clone()));
+            table.set_away(session.uuid, false).await;
+            table.broadcast(EventToClient::AdminMsg(format!("{name:?} reconnected")));
+            self.local_msgs_to_send
+                .push(EventToClient::JoinedTable(table.summary().await));
+        }
+    }
+
+    async fn create_table(&mut self, name: String, lobby: &RwLock<Lobby>) {
+        if self.can_interact().is_none() {
+            self.send_error(
+                ErrorCode::NotIntroduced,
+                "You need to introduce yourself before making a table",
+                None,
+            );
+            return;
+        }
+
+        let result = lobby.write().await.create_table(&name);
+        match result {
+            Ok(table) => {
+                info!(table = %table.id, name = %table.name, "Made table");
+                self.sit_at(table).await;
+            }
+            Err(CreateTableError::TooManyTables { max }) => self.send_error(
+                ErrorCode::TooManyTables,
+                format!("This server can only have {max} tables"),
+                None,
+            ),
+            Err(CreateTableError::InvalidName) => self.send_error(
+                ErrorCode::InvalidTableName,
+                "Table names can't be blank or too long",
+                None,
+            ),
+        }
+    }
+
+    async fn join_table(&mut self, id: TableId, lobby: &RwLock<Lobby>) {
+        if self.can_interact().is_none() {
+            self.send_error(
+                ErrorCode::NotIntroduced,
+                "You need to introduce yourself before joining a table",
+                None,
+            );
+            return;
+        }
+
+        let table = lobby.read().await.get(id);
+        match table {
+            Some(table) => self.sit_at(table).await,
+            None => self.send_error(
+                ErrorCode::UnknownTable,
+                format!("No table found with ID {id}"),
+                None,
+            ),
+        }
+    }
+
+    ///Moves us to `table`, leaving any other one first.
+    async fn sit_at(&mut self, table: Arc<TableHandle>) {
+        let ClientState::Introduced { uuid, name, .. } = &self.state else {
+            return;
+        };
+        let (uuid, name) = (*uuid, name.clone());
+
+        if self
+            .seat
+            .as_ref()
+            .is_none_or(|seat| !Arc::ptr_eq(&seat.table, &table))
+        {
+            if table.state.read().await.is_full() {
+                self.send_error(
+                    ErrorCode::TableFull,
+                    format!("{} is full", table.name),
+                    None,
+                );
+                return;
+            }
+            self.leave_table().await;
+
+            {
+                let mut state = table.state.write().await;
+                //someone else might have sat down while we were getting up
+                if state.is_full() {
+                    drop(state);
+                    self.send_error(
+                        ErrorCode::TableFull,
+                        format!("{} is full", table.name),
+                        None,
+                    );
+                    return;
                 }
+                state.add_player(uuid, name.clone());
+            }
+            self.seat = Some(Seat::new(table.clone()));
+            info!(table = %table.id, "Joined table");
+            table.broadcast(EventToClient::AdminMsg(format!(
+                "{name:?} joined the table"
+            )));
+            table.announce().await;
+        }
+
+        self.local_msgs_to_send
+            .push(EventToClient::JoinedTable(table.summary().await));
+    }
+
+    ///Gets up from our table, if we're at one, giving back which it was.
+    async fn leave_table(&mut self) -> Option<TableId> {
+        let Seat { table, .. } = self.seat.take()?;
+        let uuid = self.can_interact()?;
+
+        info!(table = %table.id, "Left table");
+        table.remove_player(uuid).await;
+        Some(table.id)
+    }
+
+    async fn add_to_pot(&mut self, value: u32) {
+        let Some((uuid, handle)) = self.seated("adding to the pot", None) else {
+            return;
+        };
+
+        let mut table = handle.state.write().await;
+        if table.hand.is_some() {
+            drop(table);
+            self.send_error(
+                ErrorCode::HandInProgress,
+                "Use the betting actions while a hand is being played",
+                None,
+            );
+            return;
+        }
+        match table.debit(uuid, value, BalanceChangeReason::AddedToPot) {
+            Ok(player) => {
+                self.local_msgs_to_send.push(EventToClient::SpecificPlayer(
+                    handle.id,
+                    uuid,
+                    player.clone(),
+                    None,
+                ));
+            }
+            Err(BalanceError::UnknownPlayer) => {
+                self.send_error(ErrorCode::UnknownPlayer, "You're not at the table", None);
+                return;
+            }
+            Err(BalanceError::NotEnough { balance }) => {
+                let message =
+                    format!("You can't add {value} to the pot, as you only have {balance}");
+                self.send_error(ErrorCode::NotEnoughBalance, message, None);
+                return;
             }
         }
+
+        info!(table = %handle.id, amount = value, "Added to the pot");
+        *table.pot.ready_to_put_in.entry(uuid).or_default() += value;
+        handle.broadcast(EventToClient::Pot(handle.id, table.pot.clone(), None));
+        drop(table);
+        handle.announce().await;
+    }
+
+    async fn close_betting_round(&mut self) {
+        let Some((_, handle)) = self.seated("closing the betting round", None) else {
+            return;
+        };
+
+        let mut table = handle.state.write().await;
+        if table.hand.is_some() {
+            drop(table);
+            self.send_error(
+                ErrorCode::HandInProgress,
+                "Betting rounds end by themselves while a hand is being played",
+                None,
+            );
+            return;
+        }
+        let swept = table.pot.close_betting_round(&[]);
+        handle.broadcast(EventToClient::Pot(handle.id, table.pot.clone(), None));
+        for uuid in swept.into_keys() {
+            if let Some(player) = table.players.get(&uuid) {
+                handle.broadcast(EventToClient::SpecificPlayer(
+                    handle.id,
+                    uuid,
+                    player.clone(),
+                    None,
+                ));
+            }
+        }
+    }
+
+    async fn start_hand(&mut self) {
+        let Some((_, handle)) = self.seated("dealing a hand", None) else {
+            return;
+        };
+
+        let mut table = handle.state.write().await;
+        let players = match table.start_hand(&mut rand::rng()) {
+            Ok(hand) => hand.players().collect::<Vec<_>>(),
+            Err(e) => {
+                let code = match e {
+                    StartHandError::InProgress => ErrorCode::HandInProgress,
+                    StartHandError::WrongPlayerCount { .. } => ErrorCode::WrongPlayerCount,
+                };
+                drop(table);
+                self.send_error(code, e.to_string(), None);
+                return;
+            }
+        };
+
+        info!(table = %handle.id, ?players, "Dealt a hand");
+        handle.broadcast(EventToClient::HandStarted(handle.id, players));
+        //the blinds have come off their balances
+        handle.broadcast(EventToClient::AllPlayers(
+            handle.id,
+            table.players.clone(),
+            None,
+        ));
+        //short stacks can go all in on the blinds, which might leave nobody to act
+        let progress = table.settle();
+        handle.report(&mut table, progress);
+        drop(table);
+        handle.announce().await;
+    }
+
+    async fn act(&mut self, action: Action) {
+        let Some((uuid, handle)) = self.seated("betting", None) else {
+            return;
+        };
+
+        let mut table = handle.state.write().await;
+        let progress = match table.act(uuid, action) {
+            Ok(progress) => progress,
+            Err(e) => {
+                drop(table);
+                let code = match e {
+                    ActionError::NoHand => ErrorCode::NoHandInProgress,
+                    ActionError::NotYourTurn => ErrorCode::NotYourTurn,
+                    ActionError::NotEnoughChips { .. } => ErrorCode::NotEnoughBalance,
+                    ActionError::NotInHand
+                    | ActionError::CantCheck { .. }
+                    | ActionError::NothingToCall
+                    | ActionError::RaiseTooSmall { .. }
+                    | ActionError::BettingNotReopened
+                    | ActionError::NoChips => ErrorCode::IllegalAction,
+                };
+                self.send_error(code, e.to_string(), None);
+                return;
+            }
+        };
+
+        info!(table = %handle.id, ?action, "Acted");
+        if let Some(player) = table.players.get(&uuid) {
+            handle.broadcast(EventToClient::AdminMsg(format!("{player} {action}")));
+            handle.broadcast(EventToClient::SpecificPlayer(
+                handle.id,
+                uuid,
+                player.clone(),
+                None,
+            ));
+        }
+        handle.report(&mut table, progress);
+        drop(table);
+        handle.announce().await;
+    }
+
+    ///Votes to split the pot between `winners`, crediting their balances once everyone still connected to the table agrees.
+    async fn award_pot(&mut self, winners: &[Uuid]) {
+        let Some((uuid, handle)) = self.seated("awarding the pot", None) else {
+            return;
+        };
+        if winners.is_empty() {
+            self.send_error(
+                ErrorCode::NoWinners,
+                "The pot needs at least one winner",
+                None,
+            );
+            return;
+        }
+
+        let mut table = handle.state.write().await;
+        if table.hand.is_some() {
+            drop(table);
+            self.send_error(
+                ErrorCode::HandInProgress,
+                "The winner of this hand gets decided at the showdown",
+                None,
+            );
+            return;
+        }
+        if !table.pot.ready_to_put_in.is_empty() {
+            drop(table);
+            self.send_error(
+                ErrorCode::BettingRoundOpen,
+                "The betting round needs closing before the pot can be awarded",
+                None,
+            );
+            return;
+        }
+        if let Some(unknown) = winners
+            .iter()
+            .find(|uuid| !table.players.contains_key(uuid))
+        {
+            drop(table);
+            self.send_error(
+                ErrorCode::UnknownPlayer,
+                format!("No player found with UUID {unknown}"),
+                None,
+            );
+            return;
+        }
+
+        match table.vote_to_award(uuid, winners) {
+            AwardVote::Passed(winners) => {
+                info!(table = %handle.id, ?winners, "Awarding the pot");
+                handle.pay_out(&mut table, &[winners]);
+                drop(table);
+                handle.announce().await;
+            }
+            AwardVote::Waiting { votes, needed } => {
+                let names: Vec<_> = winners
+                    .iter()
+                    .filter_map(|uuid| table.players.get(uuid))
+                    .map(ToString::to_string)
+                    .collect();
+                let voter = table
+                    .players
+                    .get(&uuid)
+                    .map_or_else(|| uuid.to_string(), ToString::to_string);
+                drop(table);
+                info!(table = %handle.id, ?winners, votes, needed, "Voted to award the pot");
+                handle.broadcast(EventToClient::AdminMsg(format!(
+                    "{voter} wants the pot to go to {} ({votes}/{needed} votes)",
+                    names.join(", ")
+                )));
+            }
+        }
+    }
+
+    ///Handles both [`EventToServer::BuyIn`] (with an amount) and [`EventToServer::Rebuy`] (without).
+    #[allow(clippy::significant_drop_tightening)]
+    async fn buy_in(&mut self, amount: Option<u32>) {
+        let Some((uuid, handle)) = self.seated("buying chips", None) else {
+            return;
+        };
+
+        let mut table = handle.state.write().await;
+        let result = match amount {
+            Some(amount) => table.buy_in(uuid, amount),
+            None => table.rebuy(uuid),
+        };
+        match result {
+            Ok(player) => handle.broadcast(EventToClient::SpecificPlayer(
+                handle.id,
+                uuid,
+                player.clone(),
+                None,
+            )),
+            Err(e) => self.send_error(ErrorCode::BuyInRejected, e.to_string(), None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::client::Client;
+    use crate::lobby::Lobby;
+    use crate::table::TableRules;
+    use fishandchippy::events::PROTOCOL_VERSION;
+    use fishandchippy::events::client::EventToClient;
+    use fishandchippy::events::error_code::ErrorCode;
+    use fishandchippy::events::server::EventToServer;
+    use fishandchippy::game_types::table::TableId;
+    use tokio::sync::RwLock;
+
+    ///A client that's introduced itself and sat down at the main table.
+    async fn seated_client(name: &str, lobby: &RwLock<Lobby>) -> Client {
+        let mut client = Client::new("127.0.0.1:1234".parse().unwrap());
+        for evt in [
+            EventToServer::Hello {
+                protocol_version: PROTOCOL_VERSION,
+            },
+            EventToServer::Introduction {
+                name: name.to_string(),
+            },
+            EventToServer::JoinTable(TableId::MAIN),
+        ] {
+            client.process_event(evt, lobby).await;
+        }
+        client.local_msgs_to_send().for_each(drop);
+        client
+    }
+
+    ///Everything broadcast to `client` up to and including the first event that matches `until`.
+    async fn broadcasts_until(
+        client: &mut Client,
+        lobby: &RwLock<Lobby>,
+        until: impl Fn(&EventToClient) -> bool,
+    ) -> Vec<EventToClient> {
+        let mut seen = vec![];
+        while !seen.last().is_some_and(&until) {
+            if let Some(evt) = client.next_broadcast(lobby).await {
+                seen.push(evt);
+            }
+        }
+        seen
+    }
+
+    #[tokio::test]
+    async fn deals_hole_cards_privately_and_pays_the_winner() {
+        let lobby = RwLock::new(Lobby::new(TableRules::default(), 64));
+        let mut alice = seated_client("Alice", &lobby).await;
+        let mut bob = seated_client("Bob", &lobby).await;
+        let table = lobby.read().await.get(TableId::MAIN).unwrap();
+
+        alice.process_event(EventToServer::StartHand, &lobby).await;
+        alice.process_event(EventToServer::StartHand, &lobby).await;
+        assert!(matches!(
+            alice.local_msgs_to_send().collect::<Vec<_>>().as_slice(),
+            [EventToClient::Error {
+                code: ErrorCode::HandInProgress,
+                ..
+            }]
+        ));
+
+        for client in [&mut alice, &mut bob] {
+            let uuid = client.can_interact().unwrap();
+            broadcasts_until(client, &lobby, |evt| {
+                matches!(evt, EventToClient::HandStarted(TableId::MAIN, players) if players.contains(&uuid))
+            })
+            .await;
+            assert!(client.next_broadcast(&lobby).await.is_none());
+
+            let expected = table
+                .state
+                .read()
+                .await
+                .hand
+                .as_ref()
+                .unwrap()
+                .hole_cards(uuid)
+                .unwrap()
+                .to_vec();
+            let sent: Vec<_> = client.local_msgs_to_send().collect();
+            assert_eq!(sent, [EventToClient::HoleCards(TableId::MAIN, expected)]);
+        }
+
+        let to_act = table.state.read().await.hand.as_ref().unwrap().to_act();
+        let (first, second) = if to_act == alice.can_interact() {
+            (&mut alice, &mut bob)
+        } else {
+            (&mut bob, &mut alice)
+        };
+        let last_error = |client: &mut Client| match client.local_msgs_to_send().last() {
+            Some(EventToClient::Error { code, .. }) => Some(code),
+            _ => None,
+        };
+
+        second.process_event(EventToServer::Check, &lobby).await;
+        assert_eq!(last_error(second), Some(ErrorCode::NotYourTurn));
+        first
+            .process_event(EventToServer::AddToPot(100), &lobby)
+            .await;
+        assert_eq!(last_error(first), Some(ErrorCode::HandInProgress));
+        first
+            .process_event(EventToServer::Raise { to: 5 }, &lobby)
+            .await;
+        assert_eq!(last_error(first), Some(ErrorCode::IllegalAction));
+
+        first
+            .process_event(EventToServer::Raise { to: 100 }, &lobby)
+            .await;
+        second.process_event(EventToServer::Call, &lobby).await;
+        let winner = second.can_interact().unwrap();
+        second
+            .process_event(EventToServer::AwardPot { winner }, &lobby)
+            .await;
+        assert_eq!(last_error(second), Some(ErrorCode::HandInProgress));
+        //heads up, the button goes first before the flop and last after it
+        for _ in 0..3 {
+            second.process_event(EventToServer::Check, &lobby).await;
+            first.process_event(EventToServer::Check, &lobby).await;
+        }
+        assert_eq!(last_error(first), None);
+        assert_eq!(last_error(second), None);
+
+        let seen = broadcasts_until(&mut alice, &lobby, |evt| {
+            matches!(evt, EventToClient::Showdown(..))
+        })
+        .await;
+        let boards: Vec<_> = seen
+            .iter()
+            .filter_map(|evt| match evt {
+                EventToClient::Board(_, board) => Some(board.len()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(boards, [3, 4, 5]);
+        assert!(matches!(seen.last(), Some(EventToClient::Showdown(_, shown)) if shown.len() == 2));
+
+        let state = table.state.read().await;
+        assert!(state.hand.is_none());
+        assert_eq!(state.pot.total(), 0);
+        let balances: u32 = state.players.values().map(|player| player.balance).sum();
+        drop(state);
+        assert_eq!(balances, 2_000);
+    }
+
+    #[tokio::test]
+    async fn resyncs_after_falling_behind() {
+        let lobby = RwLock::new(Lobby::new(TableRules::default(), 1));
+        let mut client = Client::new("127.0.0.1:1234".parse().unwrap());
+        for evt in [
+            EventToServer::Hello {
+                protocol_version: PROTOCOL_VERSION,
+            },
+            EventToServer::Introduction {
+                name: "Ferris".to_string(),
+            },
+            EventToServer::JoinTable(TableId::MAIN),
+        ] {
+            client.process_event(evt, &lobby).await;
+        }
+        assert!(
+            client
+                .local_msgs_to_send()
+                .any(|evt| matches!(evt, EventToClient::JoinedTable(_)))
+        );
+
+        let table = lobby.read().await.get(TableId::MAIN).unwrap();
+        for n in 0..3 {
+            table.broadcast(EventToClient::AdminMsg(n.to_string()));
+        }
+
+        assert!(client.next_broadcast(&lobby).await.is_none());
+        let resynced: Vec<_> = client.local_msgs_to_send().collect();
+        assert!(matches!(
+            resynced.as_slice(),
+            [
+                EventToClient::AllPlayers(TableId::MAIN, players, None),
+                EventToClient::Pot(TableId::MAIN, _, None),
+            ] if players.len() == 1
+        ));
+        //only the newest event fits in the channel
+        assert!(matches!(
+            client.next_broadcast(&lobby).await,
+            Some(EventToClient::AdminMsg(msg)) if msg == "2"
+        ));
     }
 }
@@ -0,0 +1,265 @@
+use crate::hand;
+use crate::table::TableRules;
+use clap::{Parser, ValueEnum};
+use serde::Deserialize;
+use std::fmt::{Display, Formatter};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for tracing::Level {
+    fn from(value: LogLevel) -> Self {
+        match value {
+            LogLevel::Error => Self::ERROR,
+            LogLevel::Warn => Self::WARN,
+            LogLevel::Info => Self::INFO,
+            LogLevel::Debug => Self::DEBUG,
+            LogLevel::Trace => Self::TRACE,
+        }
+    }
+}
+
+impl Display for LogLevel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Error => "error",
+            Self::Warn => "warn",
+            Self::Info => "info",
+            Self::Debug => "debug",
+            Self::Trace => "trace",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    ///Coloured, one line per event, for reading in a terminal.
+    Human,
+    ///One JSON object per line, for log collectors.
+    Json,
+}
+
+impl Display for LogFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Human => write!(f, "human"),
+            Self::Json => write!(f, "json"),
+        }
+    }
+}
+
+///Every setting, all optional so that flags, environment variables and the config file can be layered on top of each other.
+#[derive(Debug, Default, clap::Args, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct Settings {
+    ///Address to listen for WebSocket connections on.
+    #[arg(long, env = "FISHAND_BIND")]
+    bind: Option<SocketAddr>,
+    ///How many events each table can queue up before slow clients start missing them.
+    #[arg(long, env = "FISHAND_CHANNEL_CAPACITY")]
+    channel_capacity: Option<usize>,
+    ///Chips every player gets when they first sit down.
+    #[arg(long, env = "FISHAND_STARTING_BALANCE")]
+    starting_balance: Option<u32>,
+    ///How many players can sit at each table, up to 22.
+    #[arg(long, env = "FISHAND_MAX_PLAYERS_PER_TABLE")]
+    max_players_per_table: Option<usize>,
+    ///Seconds a connection can go without us hearing anything from it before it's dropped.
+    #[arg(long, env = "FISHAND_IDLE_TIMEOUT_SECS")]
+    idle_timeout_secs: Option<u64>,
+    ///How much to log.
+    #[arg(long, env = "FISHAND_LOG_LEVEL")]
+    log_level: Option<LogLevel>,
+    #[arg(long, env = "FISHAND_LOG_FORMAT")]
+    log_format: Option<LogFormat>,
+    ///Leave chat messages out of the logs.
+    #[arg(long, env = "FISHAND_REDACT_CHAT", num_args = 0..=1, default_missing_value = "true")]
+    redact_chat: Option<bool>,
+    ///Address to serve Prometheus metrics on, at `/metrics`. Off unless given.
+    #[arg(long, env = "FISHAND_METRICS_BIND")]
+    metrics_bind: Option<SocketAddr>,
+    ///Where to save tables between restarts, relative to the working directory. The Docker image sets this to `/data/fishand.snapshot`, on its volume.
+    #[arg(long, env = "FISHAND_SNAPSHOT_PATH")]
+    snapshot_path: Option<PathBuf>,
+    ///Seconds connections get to close when shutting down.
+    #[arg(long, env = "FISHAND_SHUTDOWN_DEADLINE_SECS")]
+    shutdown_deadline_secs: Option<u64>,
+}
+
+impl Settings {
+    ///Takes each setting from `self`, falling back to `other`.
+    fn or(self, other: Self) -> Self {
+        Self {
+            bind: self.bind.or(other.bind),
+            channel_capacity: self.channel_capacity.or(other.channel_capacity),
+            starting_balance: self.starting_balance.or(other.starting_balance),
+            max_players_per_table: self.max_players_per_table.or(other.max_players_per_table),
+            idle_timeout_secs: self.idle_timeout_secs.or(other.idle_timeout_secs),
+            log_level: self.log_level.or(other.log_level),
+            log_format: self.log_format.or(other.log_format),
+            redact_chat: self.redact_chat.or(other.redact_chat),
+            metrics_bind: self.metrics_bind.or(other.metrics_bind),
+            snapshot_path: self.snapshot_path.or(other.snapshot_path),
+            shutdown_deadline_secs: self.shutdown_deadline_secs.or(other.shutdown_deadline_secs),
+        }
+    }
+}
+
+///Flags take priority over environment variables, which take priority over the config file.
+#[derive(Debug, Parser)]
+#[command(version, about = "The fishandchippy poker server")]
+struct Cli {
+    ///A TOML file with any of the settings below, in kebab-case.
+    #[arg(long, env = "FISHAND_CONFIG")]
+    config: Option<PathBuf>,
+    #[command(flatten)]
+    settings: Settings,
+}
+
+///The settings the server actually runs with.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub bind: SocketAddr,
+    pub channel_capacity: usize,
+    pub rules: TableRules,
+    pub idle_timeout: Duration,
+    pub log_level: LogLevel,
+    pub log_format: LogFormat,
+    pub redact_chat: bool,
+    pub metrics_bind: Option<SocketAddr>,
+    pub snapshot_path: PathBuf,
+    pub shutdown_deadline: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            bind: SocketAddr::from(([0, 0, 0, 0], 8080)),
+            channel_capacity: 16,
+            rules: TableRules::default(),
+            idle_timeout: Duration::from_secs(60),
+            log_level: LogLevel::Info,
+            log_format: LogFormat::Human,
+            redact_chat: false,
+            metrics_bind: None,
+            snapshot_path: PathBuf::from("fishand.snapshot"),
+            shutdown_deadline: Duration::from_secs(10),
+        }
+    }
+}
+
+impl Config {
+    ///Reads the command line, the environment, and the config file if one was given.
+    pub fn load() -> color_eyre::Result<Self> {
+        let Cli { config, settings } = Cli::parse();
+        let file_settings = match config {
+            Some(path) => {
+                let contents = std::fs::read_to_string(&path)?;
+                toml::from_str(&contents)?
+            }
+            None => Settings::default(),
+        };
+        Ok(Self::from_settings(settings.or(file_settings)))
+    }
+
+    fn from_settings(settings: Settings) -> Self {
+        let defaults = Self::default();
+        Self {
+            bind: settings.bind.unwrap_or(defaults.bind),
+            //tokio panics on empty channels
+            channel_capacity: settings
+                .channel_capacity
+                .unwrap_or(defaults.channel_capacity)
+                .max(1),
+            rules: TableRules {
+                starting_balance: settings
+                    .starting_balance
+                    .unwrap_or(defaults.rules.starting_balance),
+                //any more and the deck would run out partway through a hand
+                max_players: settings
+                    .max_players_per_table
+                    .unwrap_or(defaults.rules.max_players)
+                    .clamp(1, hand::MAX_PLAYERS),
+                ..defaults.rules
+            },
+            //the keepalive timer can't tick every 0 seconds
+            idle_timeout: settings
+                .idle_timeout_secs
+                .map_or(defaults.idle_timeout, |secs| {
+                    Duration::from_secs(secs.max(1))
+                }),
+            log_level: settings.log_level.unwrap_or(defaults.log_level),
+            log_format: settings.log_format.unwrap_or(defaults.log_format),
+            redact_chat: settings.redact_chat.unwrap_or(defaults.redact_chat),
+            metrics_bind: settings.metrics_bind.or(defaults.metrics_bind),
+            snapshot_path: settings.snapshot_path.unwrap_or(defaults.snapshot_path),
+            shutdown_deadline: settings
+                .shutdown_deadline_secs
+                .map_or(defaults.shutdown_deadline, Duration::from_secs),
+        }
+    }
+}
+
+impl Display for Config {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "bind = {}", self.bind)?;
+        writeln!(f, "channel-capacity = {}", self.channel_capacity)?;
+        writeln!(f, "starting-balance = {}", self.rules.starting_balance)?;
+        writeln!(f, "max-players-per-table = {}", self.rules.max_players)?;
+        writeln!(f, "idle-timeout-secs = {}", self.idle_timeout.as_secs())?;
+        writeln!(f, "log-level = {}", self.log_level)?;
+        writeln!(f, "log-format = {}", self.log_format)?;
+        writeln!(f, "redact-chat = {}", self.redact_chat)?;
+        match self.metrics_bind {
+            Some(addr) => writeln!(f, "metrics-bind = {addr}")?,
+            None => writeln!(f, "metrics-bind = off")?,
+        }
+        writeln!(f, "snapshot-path = {}", self.snapshot_path.display())?;
+        write!(
+            f,
+            "shutdown-deadline-secs = {}",
+            self.shutdown_deadline.as_secs()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{Cli, Config, LogLevel, Settings};
+    use clap::Parser;
+
+    #[test]
+    fn flags_override_file_which_overrides_defaults() {
+        let file: Settings = toml::from_str(
+            r#"
+            bind = "127.0.0.1:9000"
+            starting-balance = 250
+            log-level = "debug"
+            "#,
+        )
+        .unwrap();
+        let cli =
+            Cli::try_parse_from(["fishand", "--starting-balance", "500", "--redact-chat"]).unwrap();
+
+        let config = Config::from_settings(cli.settings.or(file));
+        assert_eq!(config.bind.to_string(), "127.0.0.1:9000");
+        assert_eq!(config.rules.starting_balance, 500);
+        assert_eq!(config.log_level, LogLevel::Debug);
+        assert!(config.redact_chat);
+        assert_eq!(config.channel_capacity, Config::default().channel_capacity);
+
+        assert!(toml::from_str::<Settings>("port = 80").is_err());
+    }
+}
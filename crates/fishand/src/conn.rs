@@ -1,30 +1,98 @@
-use crate::Table;
 use crate::client::Client;
+use crate::config::Config;
+use crate::lobby::Lobby;
+use crate::metrics::METRICS;
 use fishandchippy::events::client::EventToClient;
-use fishandchippy::events::server::{EventToServer, ServerEventDeserer};
-use fishandchippy::ser_glue::{DeserMachine, Deserable, DesiredInput, FsmResult, Serable};
+use fishandchippy::events::server::EventToServer;
+use fishandchippy::ser_glue::driver::DriverError;
+use fishandchippy::ser_glue::{DecodeLimits, Deserable, Serable};
 use futures::{SinkExt, StreamExt};
 use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::net::TcpStream;
-use tokio::sync::RwLock;
-use tokio::sync::broadcast::{Receiver, Sender};
-use tokio_tungstenite::accept_async;
+use tokio::sync::{RwLock, watch};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::{CloseFrame, WebSocketConfig};
 use tokio_tungstenite::tungstenite::{Bytes, Message};
+use tokio_tungstenite::{WebSocketStream, accept_async_with_config};
+use tracing::{debug, info, warn};
 
+///Clients only ever send small events, so anything bigger than this is rejected and the client disconnected.
+const DECODE_LIMITS: DecodeLimits = DecodeLimits {
+    max_string_bytes: 1024,
+    max_collection_len: 64,
+    max_message_bytes: 4096,
+};
+
+///What gets logged for each event, hiding chat if [`Config::redact_chat`] is set.
+struct Loggable<'a> {
+    evt: &'a EventToServer,
+    redact_chat: bool,
+}
+
+impl Display for Loggable<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.evt {
+            EventToServer::SendMessage { content } if self.redact_chat => write!(
+                f,
+                "SendMessage {{ content: <{} chars redacted> }}",
+                content.chars().count()
+            ),
+            evt => write!(f, "{evt:?}"),
+        }
+    }
+}
+
+#[tracing::instrument(name = "conn", skip_all, err(Display), fields(%peer, uuid = tracing::field::Empty))]
 pub async fn handle_connection(
     peer: SocketAddr,
     stream: TcpStream,
-    global_send_event: Sender<EventToClient>,
-    mut global_recv_event: Receiver<EventToClient>,
-    table: Arc<RwLock<Table>>,
+    lobby: Arc<RwLock<Lobby>>,
+    config: Arc<Config>,
+    mut shutdown: watch::Receiver<()>,
 ) -> color_eyre::Result<()> {
-    let mut ws_stream = accept_async(stream).await.expect("Failed to accept");
-    println!("New WebSocket connection: {peer}");
+    //each websocket message is one event, so tungstenite can stop oversized ones before buffering them
+    let ws_config = WebSocketConfig::default()
+        .max_message_size(Some(DECODE_LIMITS.max_message_bytes))
+        .max_frame_size(Some(DECODE_LIMITS.max_message_bytes));
+    let mut ws_stream = match accept_async_with_config(stream, Some(ws_config)).await {
+        Ok(ws_stream) => ws_stream,
+        Err(e) => {
+            //usually something that isn't a websocket client at all, like a port scanner, so not worth an error
+            info!(error = %e, "Bad WebSocket handshake");
+            return Ok(());
+        }
+    };
+    info!("New WebSocket connection");
+    let _open = METRICS.connection_opened();
+    let mut client = Client::new(peer);
+    let result = serve(&mut ws_stream, &mut client, &lobby, &config, &mut shutdown).await;
+    //connections that error out still need detaching, so the player's seat gets freed up eventually
+    if !client.should_quit() {
+        client.close(None, &lobby).await;
+    }
+    result
+}
 
+///Passes events back and forth until either side closes the connection.
+async fn serve(
+    ws_stream: &mut WebSocketStream<TcpStream>,
+    client: &mut Client,
+    lobby: &RwLock<Lobby>,
+    config: &Config,
+    shutdown: &mut watch::Receiver<()>,
+) -> color_eyre::Result<()> {
     let mut msgs_to_process: VecDeque<Message> = VecDeque::new();
-    let mut client = Client::new(peer);
+
+    //browsers answer pings by themselves, so anyone who's quiet for the whole idle timeout has gone
+    let mut last_heard = Instant::now();
+    let mut keepalive = tokio::time::interval_at(
+        tokio::time::Instant::now() + config.idle_timeout / 2,
+        config.idle_timeout / 2,
+    );
 
     loop {
         for msg in client.local_msgs_to_send() {
@@ -32,12 +100,15 @@ pub async fn handle_connection(
                 .send(Message::Binary(Bytes::from_owner(msg.ser().1)))
                 .await?;
         }
-        for msg in client.global_msgs_to_send() {
-            if global_send_event.send(msg).is_err() {
-                eprintln!("Error sending global message...");
-            }
-        }
         if client.should_quit() {
+            if client.was_rejected() {
+                //the full reason has already gone out as an AdminMsg, and close reasons have to be short
+                let frame = CloseFrame {
+                    code: CloseCode::Policy,
+                    reason: "incompatible client".into(),
+                };
+                ws_stream.send(Message::Close(Some(frame))).await?;
+            }
             break;
         }
 
@@ -45,79 +116,136 @@ pub async fn handle_connection(
             msg = ws_stream.next() => {
                 match msg {
                     None => {
-                        client.close(None, &table).await;
+                        client.close(None, lobby).await;
                     }
                     Some(Err(e)) => {
-                        eprintln!("Error receiving message from {client}: {e}");
-                        return Err(e.into());
+                        //includes messages going over the size limit
+                        warn!(error = %e, "Error receiving message");
+                        client.close(None, lobby).await;
                     }
                     Some(Ok(msg)) => {
+                        last_heard = Instant::now();
                         msgs_to_process.push_back(msg);
                     }
                 }
             },
-            evt = global_recv_event.recv() => {
-                if let Ok(evt) = evt {
+            evt = client.next_broadcast(lobby) => {
+                if let Some(evt) = evt {
                     ws_stream.send(Message::Binary(Bytes::from_owner(evt.ser().1))).await?;
                 }
             }
+            _ = keepalive.tick() => {
+                if last_heard.elapsed() >= config.idle_timeout {
+                    close_with(ws_stream, client, lobby, CloseCode::Away, "idle for too long").await?;
+                    break;
+                }
+                ws_stream.send(Message::Ping(Bytes::new())).await?;
+            }
+            _ = shutdown.changed() => {
+                let msg = EventToClient::AdminMsg("The server is shutting down".to_string());
+                ws_stream.send(Message::Binary(Bytes::from_owner(msg.ser().1))).await?;
+                close_with(ws_stream, client, lobby, CloseCode::Restart, "server shutting down").await?;
+                break;
+            }
         }
 
-        while let Some(to_be_processed) = msgs_to_process.pop_front() {
-            match to_be_processed {
-                Message::Binary(binary) => {
-                    let mut binary = binary.into_iter().peekable();
-                    let mut deserer = EventToServer::deser();
-                    loop {
-                        //TODO: no bytes left but not done?
-                        if matches!(deserer, ServerEventDeserer::Start(_))
-                            && binary.peek().is_none()
-                        {
-                            break;
-                        }
+        process_msgs(&mut msgs_to_process, ws_stream, client, lobby, config).await?;
+    }
+
+    Ok(())
+}
 
-                        match deserer.wants_read() {
-                            DesiredInput::Byte(space) => {
-                                if let Some(byte) = binary.next() {
-                                    *space = byte;
-                                    deserer.finish_bytes_for_writing(1);
-                                }
-                            }
-                            DesiredInput::Bytes(space) => {
-                                let mut n = 0;
-                                for next_space in space {
-                                    let Some(byte) = binary.next() else {
-                                        break;
-                                    };
-                                    *next_space = byte;
-
-                                    n += 1;
-                                }
-                                deserer.finish_bytes_for_writing(n);
-                            }
-                            DesiredInput::ProcessMe => {
-                                deserer = match deserer.process()? {
-                                    FsmResult::Continue(cont) => cont,
-                                    FsmResult::Done(evt) => {
-                                        println!("{client} sent {evt:?}");
-                                        client.process_event(evt, &table).await;
-                                        EventToServer::deser()
-                                    }
-                                }
-                            }
-                            DesiredInput::Extra => unreachable!(),
+///Closes the socket from our end, and detaches the client so they can resume later.
+async fn close_with(
+    ws_stream: &mut WebSocketStream<TcpStream>,
+    client: &mut Client,
+    lobby: &RwLock<Lobby>,
+    code: CloseCode,
+    reason: &'static str,
+) -> color_eyre::Result<()> {
+    let frame = CloseFrame {
+        code,
+        reason: reason.into(),
+    };
+    ws_stream.send(Message::Close(Some(frame.clone()))).await?;
+    client.close(Some(&frame), lobby).await;
+    Ok(())
+}
+
+///Handles everything the client has sent, stopping early if it sends something we can't read.
+async fn process_msgs(
+    msgs_to_process: &mut VecDeque<Message>,
+    ws_stream: &mut WebSocketStream<TcpStream>,
+    client: &mut Client,
+    lobby: &RwLock<Lobby>,
+    config: &Config,
+) -> color_eyre::Result<()> {
+    while let Some(to_be_processed) = msgs_to_process.pop_front() {
+        match to_be_processed {
+            Message::Binary(binary) => {
+                match EventToServer::driver()
+                    .with_limits(DECODE_LIMITS)
+                    .feed_all(&binary)
+                {
+                    Ok(evts) => {
+                        for evt in evts {
+                            METRICS.event_received(&evt);
+                            debug!(
+                                evt = %Loggable {
+                                    evt: &evt,
+                                    redact_chat: config.redact_chat,
+                                },
+                                "Received event"
+                            );
+                            client.process_event(evt, lobby).await;
                         }
                     }
+                    Err(e) => {
+                        warn!(error = %e, "Rejecting message");
+                        METRICS.decode_failed(&e);
+
+                        let code = match e {
+                            DriverError::TooLong { .. } => CloseCode::Size,
+                            _ => CloseCode::Invalid,
+                        };
+                        close_with(ws_stream, client, lobby, code, "invalid message").await?;
+                        break;
+                    }
                 }
-                Message::Close(close) => {
-                    client.close(close.as_ref(), &table).await;
-                }
-                unexpected => {
-                    eprintln!("received unexpected msg from {client}: {unexpected:?}");
-                }
+            }
+            Message::Close(close) => {
+                client.close(close.as_ref(), lobby).await;
+            }
+            //only here to show the connection's still alive
+            Message::Pong(_) => {}
+            unexpected => {
+                warn!(?unexpected, "Received unexpected message");
             }
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::conn::Loggable;
+    use fishandchippy::events::server::EventToServer;
+
+    #[test]
+    fn redacts_chat() {
+        let evt = EventToServer::SendMessage {
+            content: "my pin is 1234".to_string(),
+        };
+        let logged = |redact_chat| {
+            Loggable {
+                evt: &evt,
+                redact_chat,
+            }
+            .to_string()
+        };
+
+        assert_eq!(logged(true), "SendMessage { content: <14 chars redacted> }");
+        assert!(logged(false).contains("my pin is 1234"));
+    }
+}
@@ -0,0 +1,745 @@
+use fishandchippy::game_types::cards::{Card, Deck};
+use fishandchippy::game_types::hand_eval::{HandRank, ShownHand};
+use rand::RngCore;
+use std::fmt::{Display, Formatter};
+use uuid::Uuid;
+
+///Every hand needs two hole cards each, plus five on the board and a burn card before each of the three streets.
+pub const MAX_PLAYERS: usize = (52 - 5 - 3) / 2;
+const MIN_PLAYERS: usize = 2;
+
+///Which betting round a hand is on.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Street {
+    Preflop,
+    Flop,
+    Turn,
+    River,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum StartHandError {
+    InProgress,
+    WrongPlayerCount { found: usize },
+}
+
+impl Display for StartHandError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InProgress => write!(f, "There's already a hand being played"),
+            Self::WrongPlayerCount { found } => write!(
+                f,
+                "Hands need {MIN_PLAYERS} to {MAX_PLAYERS} players with chips, but there are {found}"
+            ),
+        }
+    }
+}
+
+///What a player can do when it's their turn.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Action {
+    Check,
+    Call,
+    ///Brings the player's bet for this betting round up to `to`.
+    Raise {
+        to: u32,
+    },
+    Fold,
+    AllIn,
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Check => write!(f, "checks"),
+            Self::Call => write!(f, "calls"),
+            Self::Raise { to } => write!(f, "raises to {to}"),
+            Self::Fold => write!(f, "folds"),
+            Self::AllIn => write!(f, "goes all in"),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ActionError {
+    NoHand,
+    NotInHand,
+    NotYourTurn,
+    CantCheck {
+        to_call: u32,
+    },
+    NothingToCall,
+    RaiseTooSmall {
+        min_to: u32,
+    },
+    ///They've already acted, and the only raise since was an all in that wasn't big enough to let them raise again.
+    BettingNotReopened,
+    NotEnoughChips {
+        needed: u32,
+        balance: u32,
+    },
+    NoChips,
+}
+
+impl Display for ActionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoHand => write!(f, "There isn't a hand being played"),
+            Self::NotInHand => write!(f, "You're not in this hand"),
+            Self::NotYourTurn => write!(f, "It's not your turn"),
+            Self::CantCheck { to_call } => {
+                write!(f, "You can't check, as there's {to_call} to call")
+            }
+            Self::NothingToCall => write!(f, "There's nothing to call - check instead"),
+            Self::RaiseTooSmall { min_to } => write!(f, "You have to raise to at least {min_to}"),
+            Self::BettingNotReopened => write!(
+                f,
+                "Nobody has raised by enough for you to raise again - you can only call or fold"
+            ),
+            Self::NotEnoughChips { needed, balance } => write!(
+                f,
+                "That needs {needed} chip(s), but you only have {balance} - go all in instead"
+            ),
+            Self::NoChips => write!(f, "You don't have any chips to go all in with"),
+        }
+    }
+}
+
+///How a hand finished, once everyone still in it has turned their cards over.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Showdown {
+    ///Empty if everyone else folded, as the winner doesn't have to show.
+    pub shown: Vec<ShownHand>,
+    ///Everyone still in, grouped by how good their hand is, best first. Empty if everyone left before the end.
+    pub ranking: Vec<Vec<Uuid>>,
+}
+
+///Someone who hasn't folded yet.
+#[derive(Debug, Clone)]
+struct InHand {
+    player: Uuid,
+    hole_cards: [Card; 2],
+    ///What they've put in during this betting round.
+    bet: u32,
+    ///Whether they've acted since the bet last went up.
+    acted: bool,
+    all_in: bool,
+}
+
+///One hand of Texas Hold'em, from the deal to the showdown. This only keeps track of whose turn it is and what's legal - the chips themselves are moved by the [`Table`](crate::table::Table).
+#[derive(Debug, Clone)]
+pub struct HandState {
+    deck: Deck,
+    ///The dealer for this hand, who acts last.
+    button: Uuid,
+    street: Street,
+    board: Vec<Card>,
+    ///Everyone still in, in the order they were dealt to, which is also the order they act in - starting after the button.
+    in_hand: Vec<InHand>,
+    ///An index into `in_hand`, or `None` once the betting round is over.
+    to_act: Option<usize>,
+    ///What everyone has to put in this betting round to stay in.
+    current_bet: u32,
+    ///How much the next raise has to go up by - the last raise, or the table's minimum bet.
+    min_raise: u32,
+    min_bet: u32,
+}
+
+impl HandState {
+    ///Shuffles a fresh deck, deals two cards to each player, and posts the blinds from their balances. Bets and raises have to be at least `min_bet`, which is also the big blind - the small blind is half that.
+    ///
+    /// The button moves round to the next player after `last_button`, going by UUID, so everyone takes a turn acting last and paying the blinds. The first hand has no last button, so its button goes to the highest UUID.
+    ///
+    /// The two players after the button post the blinds, and whoever's after them acts first before the flop. Heads up, the button posts the small blind and acts first instead. The blinds are already in [`Self::bets`], but still need taking off their balances.
+    ///
+    /// # Errors
+    /// If there are too few or too many players to play a hand.
+    pub fn deal(
+        players: impl IntoIterator<Item = (Uuid, u32)>,
+        last_button: Option<Uuid>,
+        min_bet: u32,
+        rng: &mut impl RngCore,
+    ) -> Result<Self, StartHandError> {
+        let mut players: Vec<_> = players.into_iter().collect();
+        //players come out of a HashMap, so sort them to deal in the same order every time
+        players.sort_unstable_by_key(|(uuid, _)| *uuid);
+        players.dedup_by_key(|(uuid, _)| *uuid);
+        if !(MIN_PLAYERS..=MAX_PLAYERS).contains(&players.len()) {
+            return Err(StartHandError::WrongPlayerCount {
+                found: players.len(),
+            });
+        }
+        //works even if the last button has left since
+        let dealer = last_button.map_or(players.len() - 1, |last| {
+            players
+                .iter()
+                .position(|(uuid, _)| *uuid > last)
+                .unwrap_or(0)
+        });
+        players.rotate_left(dealer + 1);
+        let (button, _) = players[players.len() - 1];
+        let (players, balances): (Vec<_>, Vec<_>) = players.into_iter().unzip();
+
+        let mut deck = Deck::shuffled(rng);
+        //one card to each player at a time, like a real dealer
+        let mut firsts = Vec::with_capacity(players.len());
+        for _ in &players {
+            firsts.push(deck.deal().expect("checked there are enough cards"));
+        }
+        let in_hand = players
+            .into_iter()
+            .zip(firsts)
+            .map(|(player, first)| {
+                let second = deck.deal().expect("checked there are enough cards");
+                InHand {
+                    player,
+                    hole_cards: [first, second],
+                    bet: 0,
+                    acted: false,
+                    all_in: false,
+                }
+            })
+            .collect();
+
+        let mut hand = Self {
+            deck,
+            button,
+            street: Street::Preflop,
+            board: Vec::with_capacity(5),
+            in_hand,
+            to_act: None,
+            current_bet: min_bet,
+            min_raise: min_bet,
+            min_bet,
+        };
+        let (small, big) = if hand.in_hand.len() == 2 {
+            (1, 0)
+        } else {
+            (0, 1)
+        };
+        hand.post_blind(small, min_bet / 2, balances[small]);
+        hand.post_blind(big, min_bet, balances[big]);
+        hand.update_to_act(big + 1);
+        Ok(hand)
+    }
+
+    ///Puts in as much of `blind` as the player at `i` can afford from their `balance`. It doesn't count as acting, so they still get their turn.
+    fn post_blind(&mut self, i: usize, blind: u32, balance: u32) {
+        let seat = &mut self.in_hand[i];
+        seat.bet = blind.min(balance);
+        seat.all_in = seat.bet > 0 && seat.bet == balance;
+    }
+
+    pub const fn button(&self) -> Uuid {
+        self.button
+    }
+
+    pub const fn street(&self) -> Street {
+        self.street
+    }
+
+    pub fn board(&self) -> &[Card] {
+        &self.board
+    }
+
+    pub const fn current_bet(&self) -> u32 {
+        self.current_bet
+    }
+
+    ///The smallest amount anyone can raise to right now.
+    pub const fn min_raise_to(&self) -> u32 {
+        self.current_bet.saturating_add(self.min_raise)
+    }
+
+    ///What everyone still in the hand has put in this betting round, leaving out anyone who hasn't put anything in.
+    pub fn bets(&self) -> impl Iterator<Item = (Uuid, u32)> + '_ {
+        self.in_hand
+            .iter()
+            .filter(|seat| seat.bet > 0)
+            .map(|seat| (seat.player, seat.bet))
+    }
+
+    ///Everyone still in the hand.
+    pub fn players(&self) -> impl Iterator<Item = Uuid> + '_ {
+        self.in_hand.iter().map(|seat| seat.player)
+    }
+
+    ///Everyone still in the hand who has no chips left to bet.
+    pub fn all_in(&self) -> impl Iterator<Item = Uuid> + '_ {
+        self.in_hand
+            .iter()
+            .filter(|seat| seat.all_in)
+            .map(|seat| seat.player)
+    }
+
+    ///Whether whoever's turn it is can raise, rather than only call or fold. Anyone who's acted and still has to act again is only facing a short all in, which doesn't reopen the betting.
+    pub fn can_raise(&self) -> bool {
+        self.to_act.is_some_and(|i| !self.in_hand[i].acted)
+    }
+
+    ///Whose turn it is, or `None` if the betting round is over.
+    pub fn to_act(&self) -> Option<Uuid> {
+        self.to_act.map(|i| self.in_hand[i].player)
+    }
+
+    ///Whether nobody else can act until the next street is dealt.
+    pub const fn betting_done(&self) -> bool {
+        self.to_act.is_none()
+    }
+
+    ///The cards dealt to `player`, if they're still in the hand.
+    pub fn hole_cards(&self, player: Uuid) -> Option<[Card; 2]> {
+        self.seat(player).map(|i| self.in_hand[i].hole_cards)
+    }
+
+    fn seat(&self, player: Uuid) -> Option<usize> {
+        self.in_hand.iter().position(|seat| seat.player == player)
+    }
+
+    ///Works out how many chips `action` needs from `player`, who has `balance` left, and moves the turn on. Nothing changes if the action isn't legal.
+    ///
+    /// # Errors
+    /// If it isn't `player`'s turn, or they can't do that right now.
+    pub fn act(&mut self, player: Uuid, action: Action, balance: u32) -> Result<u32, ActionError> {
+        let i = self.seat(player).ok_or(ActionError::NotInHand)?;
+        if self.to_act != Some(i) {
+            return Err(ActionError::NotYourTurn);
+        }
+        let bet = self.in_hand[i].bet;
+        let to_call = self.current_bet - bet;
+        let reopened = self.can_raise();
+
+        let amount = match action {
+            Action::Check if to_call > 0 => return Err(ActionError::CantCheck { to_call }),
+            Action::Check => 0,
+            Action::Call if to_call == 0 => return Err(ActionError::NothingToCall),
+            Action::Call => to_call,
+            Action::Raise { .. } if !reopened => return Err(ActionError::BettingNotReopened),
+            Action::AllIn if !reopened && balance > to_call => {
+                return Err(ActionError::BettingNotReopened);
+            }
+            Action::Raise { to } if to < self.min_raise_to() => {
+                return Err(ActionError::RaiseTooSmall {
+                    min_to: self.min_raise_to(),
+                });
+            }
+            Action::Raise { to } => to - bet,
+            Action::Fold => {
+                self.fold(player);
+                return Ok(0);
+            }
+            Action::AllIn if balance == 0 => return Err(ActionError::NoChips),
+            Action::AllIn => balance,
+        };
+        if amount > balance {
+            return Err(ActionError::NotEnoughChips {
+                needed: amount,
+                balance,
+            });
+        }
+
+        let seat = &mut self.in_hand[i];
+        seat.bet = bet.saturating_add(amount);
+        seat.acted = true;
+        seat.all_in = amount > 0 && amount == balance;
+        let bet = seat.bet;
+        if bet > self.current_bet {
+            let raised_by = bet - self.current_bet;
+            self.current_bet = bet;
+            //all ins that don't raise by enough still have to be called, but don't let anyone who's already acted raise again
+            if raised_by >= self.min_raise {
+                self.min_raise = raised_by;
+                for (j, other) in self.in_hand.iter_mut().enumerate() {
+                    other.acted &= j == i;
+                }
+            }
+        }
+        self.update_to_act(i + 1);
+        Ok(amount)
+    }
+
+    ///Takes a player out of the hand, giving back whether they were in it. This works whether or not it's their turn, so players can leave the table.
+    pub fn fold(&mut self, player: Uuid) -> bool {
+        let Some(i) = self.seat(player) else {
+            return false;
+        };
+        self.in_hand.remove(i);
+        if let Some(to_act) = self.to_act {
+            //everyone after them has moved up a place
+            let from = if to_act > i { to_act - 1 } else { to_act };
+            self.update_to_act(from);
+        }
+        true
+    }
+
+    ///Finds the next player who still needs to act, starting with `from`.
+    fn update_to_act(&mut self, from: usize) {
+        let can_act = || self.in_hand.iter().filter(|seat| !seat.all_in);
+        //someone on their own can't bet against anyone, unless they've still got an all in to call
+        let alone = can_act().count() <= 1 && can_act().all(|seat| seat.bet >= self.current_bet);
+        let len = self.in_hand.len();
+
+        self.to_act = if alone || len == 0 {
+            None
+        } else {
+            (0..len).map(|n| (from + n) % len).find(|i| {
+                let seat = &self.in_hand[*i];
+                !seat.all_in && (!seat.acted || seat.bet < self.current_bet)
+            })
+        };
+    }
+
+    ///The winner, if everyone else has folded.
+    pub fn last_one_standing(&self) -> Option<Uuid> {
+        match self.in_hand.as_slice() {
+            [seat] => Some(seat.player),
+            _ => None,
+        }
+    }
+
+    ///Ends the current betting round. This deals the next street, or gives back the [`Showdown`] if that was the river - the hand is over after that.
+    pub fn advance(&mut self) -> Option<Showdown> {
+        let (next, to_deal) = match self.street {
+            Street::Preflop => (Street::Flop, 3),
+            Street::Flop => (Street::Turn, 1),
+            Street::Turn => (Street::River, 1),
+            Street::River => return Some(self.showdown()),
+        };
+
+        let mut deal = || self.deck.deal().expect("checked there are enough cards");
+        let _burnt = deal();
+        let dealt: Vec<_> = (0..to_deal).map(|_| deal()).collect();
+        self.board.extend(dealt);
+        self.street = next;
+
+        for seat in &mut self.in_hand {
+            seat.bet = 0;
+            seat.acted = false;
+        }
+        self.current_bet = 0;
+        self.min_raise = self.min_bet;
+        self.update_to_act(0);
+        None
+    }
+
+    fn showdown(&self) -> Showdown {
+        let mut ranked: Vec<_> = self
+            .in_hand
+            .iter()
+            .map(|seat| {
+                let mut cards = self.board.clone();
+                cards.extend_from_slice(&seat.hole_cards);
+                let rank = HandRank::evaluate(&cards).expect("7 cards from one deck");
+                (seat.player, rank)
+            })
+            .collect();
+
+        ranked.sort_unstable_by(|(_, a), (_, b)| b.cmp(a));
+        let ranking = ranked
+            .chunk_by(|(_, a), (_, b)| a == b)
+            .map(|group| group.iter().map(|(uuid, _)| *uuid).collect())
+            .collect();
+        let shown = self
+            .in_hand
+            .iter()
+            .map(|seat| ShownHand {
+                player: seat.player,
+                hole_cards: seat.hole_cards.to_vec(),
+            })
+            .collect();
+        Showdown { shown, ranking }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hand::{Action, ActionError, HandState, MAX_PLAYERS, StartHandError, Street};
+    use fishandchippy::game_types::hand_eval::HandRank;
+    use rand::SeedableRng;
+    use rand_pcg::Pcg32;
+    use std::collections::HashSet;
+    use uuid::Uuid;
+
+    ///Everyone in `players` with plenty of chips.
+    fn with_chips(players: &[Uuid]) -> impl Iterator<Item = (Uuid, u32)> + '_ {
+        players.iter().map(|uuid| (*uuid, 1_000))
+    }
+
+    ///A hand between `n` players, sorted into the order they act in. Everyone's called the blinds, so it's on the flop and nobody's bet anything yet.
+    fn deal(n: usize) -> (HandState, Vec<Uuid>) {
+        let mut players: Vec<_> = (0..n).map(|_| Uuid::new_v4()).collect();
+        players.sort_unstable();
+        let mut hand =
+            HandState::deal(with_chips(&players), None, 10, &mut Pcg32::seed_from_u64(3)).unwrap();
+        while let Some(player) = hand.to_act() {
+            let bet = hand
+                .bets()
+                .find(|(uuid, _)| *uuid == player)
+                .map_or(0, |(_, bet)| bet);
+            let action = if bet < hand.current_bet() {
+                Action::Call
+            } else {
+                Action::Check
+            };
+            hand.act(player, action, 1_000).unwrap();
+        }
+        assert_eq!(hand.advance(), None);
+        (hand, players)
+    }
+
+    #[test]
+    fn plays_through_to_showdown() {
+        let (mut hand, players) = deal(3);
+        assert_eq!(hand.street(), Street::Flop);
+        assert_eq!(hand.board().len(), 3);
+
+        for (street, board_len) in [(Street::Turn, 4), (Street::River, 5)] {
+            assert_eq!(hand.advance(), None);
+            assert_eq!(hand.street(), street);
+            assert_eq!(hand.board().len(), board_len);
+        }
+
+        let mut seen: HashSet<_> = hand.board().iter().copied().collect();
+        for uuid in &players {
+            seen.extend(hand.hole_cards(*uuid).unwrap());
+        }
+        assert_eq!(seen.len(), 5 + 2 * 3, "every card is different");
+
+        let rank_of = |uuid: Uuid| {
+            let mut cards = hand.board().to_vec();
+            cards.extend(hand.hole_cards(uuid).unwrap());
+            HandRank::evaluate(&cards).unwrap()
+        };
+        let best = players.iter().map(|uuid| rank_of(*uuid)).max().unwrap();
+
+        let showdown = hand.clone().advance().unwrap();
+        assert_eq!(showdown.shown.len(), 3);
+        assert_eq!(showdown.ranking.iter().flatten().count(), 3);
+        for uuid in &players {
+            assert_eq!(showdown.ranking[0].contains(uuid), rank_of(*uuid) == best);
+        }
+        for pair in showdown.ranking.windows(2) {
+            assert!(rank_of(pair[0][0]) > rank_of(pair[1][0]));
+        }
+    }
+
+    #[test]
+    fn enforces_betting_rules() {
+        let (mut hand, players) = deal(3);
+        let [a, b, c] = players[..] else {
+            unreachable!()
+        };
+        assert_eq!(hand.to_act(), Some(a));
+        assert_eq!(
+            hand.act(b, Action::Check, 100),
+            Err(ActionError::NotYourTurn)
+        );
+        assert_eq!(
+            hand.act(a, Action::Call, 100),
+            Err(ActionError::NothingToCall)
+        );
+        assert_eq!(
+            hand.act(a, Action::Raise { to: 5 }, 100),
+            Err(ActionError::RaiseTooSmall { min_to: 10 })
+        );
+        assert_eq!(hand.act(a, Action::Raise { to: 20 }, 100), Ok(20));
+
+        assert_eq!(
+            hand.act(b, Action::Check, 100),
+            Err(ActionError::CantCheck { to_call: 20 })
+        );
+        //raises have to go up by at least as much as the last one
+        assert_eq!(
+            hand.act(b, Action::Raise { to: 30 }, 100),
+            Err(ActionError::RaiseTooSmall { min_to: 40 })
+        );
+        assert_eq!(
+            hand.act(b, Action::Raise { to: 200 }, 100),
+            Err(ActionError::NotEnoughChips {
+                needed: 200,
+                balance: 100
+            })
+        );
+        assert_eq!(hand.act(b, Action::Raise { to: 50 }, 100), Ok(50));
+        assert_eq!(hand.act(c, Action::Fold, 100), Ok(0));
+        assert_eq!(hand.act(c, Action::Check, 100), Err(ActionError::NotInHand));
+
+        //the raise means a has to act again
+        assert_eq!(hand.to_act(), Some(a));
+        assert!(!hand.betting_done());
+        assert_eq!(hand.act(a, Action::Call, 80), Ok(30));
+        assert!(hand.betting_done());
+
+        assert_eq!(hand.advance(), None);
+        assert_eq!((hand.current_bet(), hand.min_raise_to()), (0, 10));
+        assert_eq!(hand.to_act(), Some(a));
+        assert_eq!(hand.act(a, Action::Check, 50), Ok(0));
+        assert_eq!(hand.act(b, Action::Fold, 50), Ok(0));
+        assert_eq!(hand.last_one_standing(), Some(a));
+    }
+
+    #[test]
+    fn all_in_players_stop_acting() {
+        let (mut hand, players) = deal(3);
+        let [a, b, c] = players[..] else {
+            unreachable!()
+        };
+        assert_eq!(hand.act(a, Action::AllIn, 30), Ok(30));
+        //calling with everything left is the same as going all in
+        assert_eq!(hand.act(b, Action::Call, 30), Ok(30));
+        assert_eq!(
+            hand.act(c, Action::Call, 20),
+            Err(ActionError::NotEnoughChips {
+                needed: 30,
+                balance: 20
+            })
+        );
+        //short all ins still have to be called, but don't count as raises
+        assert_eq!(hand.act(c, Action::AllIn, 20), Ok(20));
+        assert!(hand.betting_done());
+        assert_eq!(
+            hand.all_in().collect::<HashSet<_>>(),
+            HashSet::from([a, b, c])
+        );
+
+        //nobody can bet any more, so the rest of the board just gets dealt
+        assert_eq!(hand.advance(), None);
+        assert!(hand.betting_done());
+    }
+
+    #[test]
+    fn button_moves_round() {
+        let (hand, players) = deal(3);
+        assert_eq!(hand.button(), players[2]);
+
+        let mut rng = Pcg32::seed_from_u64(4);
+        let mut button = Some(hand.button());
+        for expected in [0, 1, 2, 0] {
+            let hand = HandState::deal(with_chips(&players), button, 10, &mut rng).unwrap();
+            assert_eq!(hand.button(), players[expected]);
+            //the two after the button post the blinds, which leaves the button to go first
+            assert_eq!(
+                hand.bets().collect::<HashSet<_>>(),
+                HashSet::from([
+                    (players[(expected + 1) % 3], 5),
+                    (players[(expected + 2) % 3], 10)
+                ])
+            );
+            assert_eq!(hand.to_act(), Some(players[expected]));
+            button = Some(hand.button());
+        }
+
+        //if the button leaves, it goes to whoever was next
+        let hand =
+            HandState::deal(with_chips(&players[1..]), Some(players[0]), 10, &mut rng).unwrap();
+        assert_eq!(hand.button(), players[1]);
+    }
+
+    #[test]
+    fn posts_the_blinds() {
+        let mut rng = Pcg32::seed_from_u64(5);
+        let mut players: Vec<_> = (0..3).map(|_| Uuid::new_v4()).collect();
+        players.sort_unstable();
+        let [a, b, c] = players[..] else {
+            unreachable!()
+        };
+
+        //heads up, the button posts the small blind and goes first
+        let mut hand = HandState::deal(with_chips(&[a, b]), None, 10, &mut rng).unwrap();
+        assert_eq!(hand.button(), b);
+        assert_eq!(
+            hand.bets().collect::<HashSet<_>>(),
+            HashSet::from([(a, 10), (b, 5)])
+        );
+        assert_eq!((hand.to_act(), hand.current_bet()), (Some(b), 10));
+        assert_eq!(hand.act(b, Action::Call, 995), Ok(5));
+        //the big blind still gets a turn, even though everyone's called
+        assert_eq!(hand.to_act(), Some(a));
+        assert!(hand.can_raise());
+        assert_eq!(hand.act(a, Action::Check, 990), Ok(0));
+        assert!(hand.betting_done());
+        //and the button acts last after the flop
+        assert_eq!(hand.advance(), None);
+        assert_eq!(hand.to_act(), Some(a));
+
+        //anyone who can't cover their blind is all in for what they've got
+        let hand = HandState::deal([(a, 1_000), (b, 4), (c, 1_000)], None, 10, &mut rng).unwrap();
+        assert_eq!(
+            hand.bets().collect::<HashSet<_>>(),
+            HashSet::from([(a, 5), (b, 4)])
+        );
+        assert_eq!(hand.all_in().collect::<Vec<_>>(), [b]);
+        assert_eq!((hand.to_act(), hand.current_bet()), (Some(c), 10));
+    }
+
+    #[test]
+    fn short_all_ins_dont_reopen_betting() {
+        let (mut hand, players) = deal(3);
+        let [a, b, c] = players[..] else {
+            unreachable!()
+        };
+        assert_eq!(hand.act(a, Action::Raise { to: 20 }, 100), Ok(20));
+        assert_eq!(hand.act(b, Action::Call, 100), Ok(20));
+        assert!(hand.can_raise());
+        //only goes up by 10, when raises have to go up by 20
+        assert_eq!(hand.act(c, Action::AllIn, 30), Ok(30));
+        assert_eq!(hand.min_raise_to(), 50);
+
+        assert_eq!(hand.to_act(), Some(a));
+        assert!(!hand.can_raise());
+        assert_eq!(
+            hand.act(a, Action::Raise { to: 60 }, 80),
+            Err(ActionError::BettingNotReopened)
+        );
+        assert_eq!(
+            hand.act(a, Action::AllIn, 80),
+            Err(ActionError::BettingNotReopened)
+        );
+        assert_eq!(hand.act(a, Action::Call, 80), Ok(10));
+        assert_eq!(hand.act(b, Action::Call, 80), Ok(10));
+        assert!(hand.betting_done());
+    }
+
+    #[test]
+    fn players_can_leave_out_of_turn() {
+        let (mut hand, players) = deal(3);
+        let [a, b, c] = players[..] else {
+            unreachable!()
+        };
+        assert_eq!(hand.act(a, Action::Check, 100), Ok(0));
+        assert!(hand.fold(a));
+        assert!(!hand.fold(a));
+        assert_eq!(hand.hole_cards(a), None);
+        assert_eq!(hand.to_act(), Some(b));
+
+        assert!(hand.fold(b));
+        assert_eq!(hand.to_act(), None);
+        assert_eq!(hand.last_one_standing(), Some(c));
+    }
+
+    #[test]
+    fn needs_the_right_number_of_players() {
+        let mut rng = Pcg32::seed_from_u64(0);
+        let one = Uuid::new_v4();
+        assert_eq!(
+            HandState::deal([(one, 100), (one, 100)], None, 10, &mut rng).unwrap_err(),
+            StartHandError::WrongPlayerCount { found: 1 }
+        );
+        let too_many = (0..=MAX_PLAYERS).map(|_| (Uuid::new_v4(), 100));
+        assert_eq!(
+            HandState::deal(too_many, None, 10, &mut rng).unwrap_err(),
+            StartHandError::WrongPlayerCount {
+                found: MAX_PLAYERS + 1
+            }
+        );
+        assert!(
+            HandState::deal(
+                (0..MAX_PLAYERS).map(|_| (Uuid::new_v4(), 100)),
+                None,
+                10,
+                &mut rng
+            )
+            .is_ok()
+        );
+    }
+}
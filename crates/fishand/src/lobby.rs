@@ -0,0 +1,511 @@
+use crate::hand::HandState;
+use crate::table::{BalanceChangeReason, HandProgress, Table, TableRules, TableSnapshot};
+use fishandchippy::events::ResumeToken;
+use fishandchippy::events::client::EventToClient;
+use fishandchippy::game_types::table::{TableId, TableSummary};
+use fishandchippy::ser_glue::{Deserable, Serable};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{RwLock, broadcast};
+use tracing::info;
+use uuid::Uuid;
+
+///Stops clients from making tables until the server falls over.
+const MAX_TABLES: usize = 64;
+const MAX_TABLE_NAME_CHARS: usize = 32;
+///How long a disconnected player keeps their seat for, waiting to be resumed.
+pub const RESUME_GRACE_PERIOD: Duration = Duration::from_secs(60);
+///Bump this whenever [`LobbySnapshot`] or anything in it changes shape, so old snapshots get ignored rather than misread.
+pub const SNAPSHOT_VERSION: u32 = 4;
+
+///One table, along with the channel used to tell everyone sat at it what's going on.
+#[derive(Debug)]
+pub struct TableHandle {
+    pub id: TableId,
+    pub name: String,
+    pub state: RwLock<Table>,
+    pub events: broadcast::Sender<EventToClient>,
+    lobby_updates: broadcast::Sender<EventToClient>,
+}
+
+impl TableHandle {
+    ///Tells everyone at this table about something.
+    pub fn broadcast(&self, evt: EventToClient) {
+        //only fails if no-one is listening, which is fine
+        let _ = self.events.send(evt);
+    }
+
+    ///Gets a player up from the table, folding any hand they're in, and lets everyone know.
+    pub async fn remove_player(&self, uuid: Uuid) {
+        let mut table = self.state.write().await;
+        let player = table.remove_player(&uuid);
+        let quit_msg = player.map_or_else(
+            || format!("{uuid:?} left the table"),
+            |player| format!("{:?} left the table", player.name),
+        );
+        self.broadcast(EventToClient::AdminMsg(quit_msg));
+        let progress = table.settle();
+        self.report(&mut table, progress);
+        drop(table);
+        self.announce().await;
+    }
+
+    ///Marks a player as having lost their connection or come back, folding them out of any hand they're in if they've gone.
+    #[allow(clippy::significant_drop_tightening)]
+    pub async fn set_away(&self, uuid: Uuid, away: bool) {
+        let mut table = self.state.write().await;
+        let in_hand = table
+            .hand
+            .as_ref()
+            .is_some_and(|hand| hand.players().any(|player| player == uuid));
+        let progress = table.set_away(uuid, away);
+        if away && in_hand {
+            if let Some(player) = table.players.get(&uuid) {
+                self.broadcast(EventToClient::AdminMsg(format!("{player} folds")));
+            }
+            self.report(&mut table, progress);
+        }
+    }
+
+    ///Lets everyone know how the hand being played has moved on, paying out the pot if it's over.
+    pub fn report(&self, table: &mut Table, progress: HandProgress) {
+        self.broadcast(EventToClient::Pot(self.id, table.pot.clone(), None));
+        if let Some(board) = progress.board {
+            let street = table.hand.as_ref().map(HandState::street);
+            info!(table = %self.id, ?street, "Dealt more of the board");
+            self.broadcast(EventToClient::Board(self.id, board));
+        }
+        if let Some(hand) = &table.hand
+            && let Some(player) = hand.to_act()
+        {
+            self.broadcast(EventToClient::ToAct {
+                table: self.id,
+                player,
+                current_bet: hand.current_bet(),
+                min_raise_to: hand.can_raise().then_some(hand.min_raise_to()),
+            });
+        }
+
+        let Some(showdown) = progress.showdown else {
+            return;
+        };
+        info!(table = %self.id, ranking = ?showdown.ranking, "Hand over");
+        self.broadcast(EventToClient::Showdown(self.id, showdown.shown));
+        //if everyone left, the pot stays put for next time
+        self.pay_out(table, &showdown.ranking);
+    }
+
+    ///Awards the pot, and each side pot, to the best players in `ranking` who can win it. Everyone in `ranking` must be at the table. Lets everyone know who got what.
+    pub fn pay_out(&self, table: &mut Table, ranking: &[Vec<Uuid>]) {
+        for (uuid, winnings) in table.pot.award(ranking) {
+            let Some(player) = table.credit(uuid, winnings, BalanceChangeReason::WonPot) else {
+                continue;
+            };
+
+            self.broadcast(EventToClient::AdminMsg(format!(
+                "{player} won {winnings} chip(s)"
+            )));
+            self.broadcast(EventToClient::SpecificPlayer(
+                self.id,
+                uuid,
+                player.clone(),
+                None,
+            ));
+        }
+        self.broadcast(EventToClient::Pot(self.id, table.pot.clone(), None));
+    }
+
+    ///Lets everyone in the lobby know that this table has changed. The table mustn't be locked when this is called.
+    pub async fn announce(&self) {
+        //only fails if no-one is in the lobby, which is fine
+        let _ = self
+            .lobby_updates
+            .send(EventToClient::TableUpdated(self.summary().await));
+    }
+
+    pub async fn summary(&self) -> TableSummary {
+        let table = self.state.read().await;
+        TableSummary {
+            id: self.id,
+            name: self.name.clone(),
+            player_count: u32::try_from(table.players.len()).unwrap_or(u32::MAX),
+            pot_size: table.pot.total(),
+        }
+    }
+}
+
+///A player whose connection dropped, who can be picked back up with their [`ResumeToken`].
+#[derive(Debug)]
+pub struct DetachedSession {
+    pub uuid: Uuid,
+    pub name: String,
+    ///Still sat here, with their balance and anything ready to put in the pot.
+    pub table: Option<Arc<TableHandle>>,
+    expires_at: Instant,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CreateTableError {
+    TooManyTables { max: usize },
+    InvalidName,
+}
+
+///A [`TableHandle`] as it gets saved.
+#[derive(Debug, Clone, Eq, PartialEq, Serable, Deserable)]
+pub struct SavedTable {
+    pub id: TableId,
+    pub name: String,
+    pub state: TableSnapshot,
+}
+
+///A [`DetachedSession`] as it gets saved. The grace period starts again when it's loaded.
+#[derive(Debug, Clone, Eq, PartialEq, Serable, Deserable)]
+pub struct SavedSession {
+    pub token: ResumeToken,
+    pub uuid: Uuid,
+    pub name: String,
+    pub table: Option<TableId>,
+}
+
+///Everything needed to get a [`Lobby`] back after a restart.
+#[derive(Debug, Clone, Eq, PartialEq, Serable, Deserable)]
+pub struct LobbySnapshot {
+    ///Always [`SNAPSHOT_VERSION`] when made by this build.
+    pub version: u32,
+    pub next_id: u32,
+    pub tables: Vec<SavedTable>,
+    pub detached: Vec<SavedSession>,
+}
+
+///Every table on the server.
+#[derive(Debug)]
+pub struct Lobby {
+    tables: HashMap<TableId, Arc<TableHandle>>,
+    next_id: u32,
+    rules: TableRules,
+    channel_capacity: usize,
+    updates: broadcast::Sender<EventToClient>,
+    detached: HashMap<ResumeToken, DetachedSession>,
+    ///Everyone connected right now, with their resume token and name, so they can resume after a restart too.
+    online: HashMap<Uuid, (ResumeToken, String)>,
+}
+
+impl Lobby {
+    ///Makes a lobby with just the [`TableId::MAIN`] table in it.
+    pub fn new(rules: TableRules, channel_capacity: usize) -> Self {
+        let mut lobby = Self {
+            tables: HashMap::new(),
+            next_id: TableId::MAIN.0,
+            rules,
+            channel_capacity,
+            updates: broadcast::channel(channel_capacity).0,
+            detached: HashMap::new(),
+            online: HashMap::new(),
+        };
+        lobby
+            .create_table("Main table")
+            .expect("the first table is always allowed");
+        lobby
+    }
+
+    ///Rebuilds a lobby from a [`LobbySnapshot`], using the current rules rather than whatever they were when it was saved.
+    ///
+    /// Everyone gets [`RESUME_GRACE_PERIOD`] to resume, including anyone who was still connected when the snapshot was taken. Any hands that were being played are called off, giving everyone back their bets.
+    pub fn restore(snapshot: LobbySnapshot, rules: TableRules, channel_capacity: usize) -> Self {
+        let updates = broadcast::channel(channel_capacity).0;
+        let mut tables = HashMap::with_capacity(snapshot.tables.len());
+        for SavedTable { id, name, state } in snapshot.tables {
+            let mut state = Table::restore(rules, state);
+            let unclaimed: Vec<Uuid> = state
+                .players
+                .keys()
+                .filter(|uuid| {
+                    !snapshot
+                        .detached
+                        .iter()
+                        .any(|session| session.uuid == **uuid && session.table == Some(id))
+                })
+                .copied()
+                .collect();
+            for uuid in unclaimed {
+                state.remove_player(&uuid);
+            }
+
+            let (events, _) = broadcast::channel(channel_capacity);
+            let handle = Arc::new(TableHandle {
+                id,
+                name,
+                state: RwLock::new(state),
+                events,
+                lobby_updates: updates.clone(),
+            });
+            tables.insert(id, handle);
+        }
+
+        let expires_at = Instant::now() + RESUME_GRACE_PERIOD;
+        let detached = snapshot
+            .detached
+            .into_iter()
+            .map(|session| {
+                (
+                    session.token,
+                    DetachedSession {
+                        uuid: session.uuid,
+                        name: session.name,
+                        table: session.table.and_then(|id| tables.get(&id).cloned()),
+                        expires_at,
+                    },
+                )
+            })
+            .collect();
+
+        let mut lobby = Self {
+            next_id: snapshot.next_id,
+            tables,
+            rules,
+            channel_capacity,
+            updates,
+            detached,
+            online: HashMap::new(),
+        };
+        if lobby.tables.is_empty() {
+            lobby
+                .create_table("Main table")
+                .expect("the first table is always allowed");
+        }
+        lobby
+    }
+
+    ///Copies out everything worth saving. This briefly locks each table in turn.
+    pub async fn snapshot(&self) -> LobbySnapshot {
+        let mut tables = Vec::with_capacity(self.tables.len());
+        for handle in self.tables.values() {
+            tables.push(SavedTable {
+                id: handle.id,
+                name: handle.name.clone(),
+                state: handle.state.read().await.snapshot(),
+            });
+        }
+        tables.sort_unstable_by_key(|table| table.id);
+
+        let online = self.online.iter().map(|(uuid, (token, name))| {
+            let table = tables
+                .iter()
+                .find(|table| table.state.players.contains_key(uuid))
+                .map(|table| table.id);
+            SavedSession {
+                token: *token,
+                uuid: *uuid,
+                name: name.clone(),
+                table,
+            }
+        });
+        let mut detached: Vec<_> = self
+            .detached
+            .iter()
+            .map(|(token, session)| SavedSession {
+                token: *token,
+                uuid: session.uuid,
+                name: session.name.clone(),
+                table: session.table.as_ref().map(|table| table.id),
+            })
+            .chain(online)
+            .collect();
+        //keeps the order stable, so unchanged lobbies give equal snapshots
+        detached.sort_unstable_by_key(|session| session.token.0);
+
+        LobbySnapshot {
+            version: SNAPSHOT_VERSION,
+            next_id: self.next_id,
+            tables,
+            detached,
+        }
+    }
+
+    pub fn create_table(&mut self, name: &str) -> Result<Arc<TableHandle>, CreateTableError> {
+        let name = name.trim().to_string();
+        if name.is_empty() || name.chars().count() > MAX_TABLE_NAME_CHARS {
+            return Err(CreateTableError::InvalidName);
+        }
+        if self.tables.len() >= MAX_TABLES {
+            return Err(CreateTableError::TooManyTables { max: MAX_TABLES });
+        }
+
+        let id = TableId(self.next_id);
+        self.next_id += 1;
+
+        let (events, _) = broadcast::channel(self.channel_capacity);
+        let handle = Arc::new(TableHandle {
+            id,
+            name,
+            state: RwLock::new(Table::new(self.rules)),
+            events,
+            lobby_updates: self.updates.clone(),
+        });
+        self.tables.insert(id, handle.clone());
+        Ok(handle)
+    }
+
+    ///Hears about every [`EventToClient::TableUpdated`].
+    pub fn subscribe(&self) -> broadcast::Receiver<EventToClient> {
+        self.updates.subscribe()
+    }
+
+    pub fn get(&self, id: TableId) -> Option<Arc<TableHandle>> {
+        self.tables.get(&id).cloned()
+    }
+
+    pub fn table_count(&self) -> usize {
+        self.tables.len()
+    }
+
+    ///Every chip in a player's stack or a pot, at every table.
+    pub async fn chips_in_play(&self) -> u64 {
+        let mut total = 0;
+        for handle in self.tables.values() {
+            let table = handle.state.read().await;
+            total += u64::from(table.pot.total());
+            total += table
+                .players
+                .values()
+                .map(|player| u64::from(player.balance))
+                .sum::<u64>();
+        }
+        total
+    }
+
+    ///Summaries of every table, in the order they were made.
+    pub async fn summaries(&self) -> Vec<TableSummary> {
+        let mut summaries = Vec::with_capacity(self.tables.len());
+        for handle in self.tables.values() {
+            summaries.push(handle.summary().await);
+        }
+        summaries.sort_unstable_by_key(|summary| summary.id);
+        summaries
+    }
+
+    ///Notes that a player has connected, so they can resume if the server restarts.
+    pub fn go_online(&mut self, token: ResumeToken, uuid: Uuid, name: String) {
+        self.online.insert(uuid, (token, name));
+    }
+
+    ///Keeps a disconnected player around for [`RESUME_GRACE_PERIOD`].
+    pub fn detach(
+        &mut self,
+        token: ResumeToken,
+        uuid: Uuid,
+        name: String,
+        table: Option<Arc<TableHandle>>,
+    ) {
+        self.online.remove(&uuid);
+        self.detached.insert(
+            token,
+            DetachedSession {
+                uuid,
+                name,
+                table,
+                expires_at: Instant::now() + RESUME_GRACE_PERIOD,
+            },
+        );
+    }
+
+    ///Picks a disconnected player back up. Each token only works once.
+    ///
+    /// Expired sessions are left for [`Self::take_expired`], so they still get removed from their tables.
+    pub fn reattach(&mut self, token: ResumeToken) -> Option<DetachedSession> {
+        if self.detached.get(&token)?.expires_at <= Instant::now() {
+            return None;
+        }
+        self.detached.remove(&token)
+    }
+
+    ///Forgets about everyone whose grace period is up, giving them back so they can be removed from their tables.
+    pub fn take_expired(&mut self) -> Vec<DetachedSession> {
+        let now = Instant::now();
+        self.detached
+            .extract_if(|_, session| session.expires_at <= now)
+            .map(|(_, session)| session)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hand::Action;
+    use crate::lobby::Lobby;
+    use crate::table::TableRules;
+    use fishandchippy::events::ResumeToken;
+    use fishandchippy::game_types::table::TableId;
+    use rand::SeedableRng;
+    use rand_pcg::Pcg32;
+    use std::time::Instant;
+    use uuid::Uuid;
+
+    #[test]
+    fn resume_tokens_only_work_once() {
+        let mut lobby = Lobby::new(TableRules::default(), 4);
+        let token = ResumeToken::new_random();
+        let uuid = Uuid::new_v4();
+        lobby.detach(token, uuid, "Ferris".to_string(), lobby.get(TableId::MAIN));
+
+        assert!(lobby.reattach(ResumeToken::new_random()).is_none());
+        assert!(lobby.take_expired().is_empty());
+
+        let session = lobby.reattach(token).unwrap();
+        assert_eq!(session.uuid, uuid);
+        assert_eq!(session.table.unwrap().id, TableId::MAIN);
+        assert!(lobby.reattach(token).is_none());
+    }
+
+    #[tokio::test]
+    async fn restoring_calls_off_hands_and_keeps_everyone_seated() {
+        let mut lobby = Lobby::new(TableRules::default(), 4);
+        let table = lobby.get(TableId::MAIN).unwrap();
+        let (alice, bob) = (Uuid::new_v4(), Uuid::new_v4());
+        let (alice_token, bob_token) = (ResumeToken::new_random(), ResumeToken::new_random());
+        {
+            let mut state = table.state.write().await;
+            state.add_player(alice, "Alice".to_string());
+            state.add_player(bob, "Bob".to_string());
+            state.start_hand(&mut Pcg32::seed_from_u64(1)).unwrap();
+            let first = state.hand.as_ref().unwrap().to_act().unwrap();
+            state.act(first, Action::Raise { to: 100 }).unwrap();
+        }
+        lobby.go_online(alice_token, alice, "Alice".to_string());
+        lobby.detach(bob_token, bob, "Bob".to_string(), Some(table));
+
+        let snapshot = lobby.snapshot().await;
+        let mut restored = Lobby::restore(snapshot, TableRules::default(), 4);
+        let state = restored
+            .get(TableId::MAIN)
+            .unwrap()
+            .state
+            .read()
+            .await
+            .snapshot();
+        assert_eq!(state.pot.total(), 0);
+        assert_eq!(state.players[&alice].balance, 1_000);
+        assert_eq!(state.players[&bob].balance, 1_000);
+
+        for (token, uuid) in [(alice_token, alice), (bob_token, bob)] {
+            let session = restored.reattach(token).unwrap();
+            assert_eq!(session.uuid, uuid);
+            assert_eq!(session.table.unwrap().id, TableId::MAIN);
+        }
+    }
+
+    #[test]
+    fn expired_sessions_still_get_removed() {
+        let mut lobby = Lobby::new(TableRules::default(), 4);
+        let token = ResumeToken::new_random();
+        let uuid = Uuid::new_v4();
+        lobby.detach(token, uuid, "Ferris".to_string(), lobby.get(TableId::MAIN));
+        lobby.detached.get_mut(&token).unwrap().expires_at = Instant::now();
+
+        assert!(lobby.reattach(token).is_none());
+        let expired = lobby.take_expired();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].uuid, uuid);
+    }
+}
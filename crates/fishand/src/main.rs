@@ -1,42 +1,191 @@
 #![warn(clippy::all, clippy::nursery, clippy::pedantic)]
 
 mod client;
+mod config;
 mod conn;
+mod hand;
+mod lobby;
+mod metrics;
+mod persistence;
+mod table;
 
+use crate::config::{Config, LogFormat};
 use crate::conn::handle_connection;
-use fishandchippy::game_types::player::Player;
-use fishandchippy::game_types::pot::Pot;
-use std::collections::HashMap;
+use crate::lobby::{Lobby, LobbySnapshot};
+use crate::persistence::{FileStore, Store};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::TcpListener;
-use tokio::sync::{RwLock, broadcast};
-use uuid::Uuid;
-
-#[derive(Debug, Default, Clone)]
-pub struct Table {
-    pub pot: Pot,
-    pub players: HashMap<Uuid, Player>,
-}
+use tokio::sync::{RwLock, watch};
+use tokio::task::JoinSet;
+use tracing::{error, info, warn};
 
 #[tokio::main]
 async fn main() -> color_eyre::Result<()> {
     color_eyre::install().expect("unable to install color eyre");
 
-    let (send_event, _) = broadcast::channel(16);
-    let table = Arc::new(RwLock::new(Table::default()));
-    let listener = TcpListener::bind("0.0.0.0:8080").await?;
+    let config = Arc::new(Config::load()?);
+    init_logging(&config);
+    info!("Starting with config:\n{config}");
+
+    let (rules, capacity) = (config.rules, config.channel_capacity);
+    let store: Arc<dyn Store> = Arc::new(FileStore::new(&config.snapshot_path));
+    let lobby = match store.load() {
+        Ok(Some(snapshot)) => {
+            info!(tables = snapshot.tables.len(), "Restored snapshot");
+            Lobby::restore(snapshot, rules, capacity)
+        }
+        Ok(None) => Lobby::new(rules, capacity),
+        Err(e) => {
+            error!(error = %e, "Couldn't load snapshot, starting afresh");
+            Lobby::new(rules, capacity)
+        }
+    };
+    let lobby = Arc::new(RwLock::new(lobby));
+
+    tokio::task::spawn(expire_detached_sessions(lobby.clone()));
+    let last_saved = lobby.read().await.snapshot().await;
+    tokio::task::spawn(save_snapshots(lobby.clone(), store.clone(), last_saved));
+    let shutdown_deadline = config.shutdown_deadline;
+    let listener = TcpListener::bind(config.bind).await?;
+    if let Some(addr) = config.metrics_bind {
+        let metrics_listener = TcpListener::bind(addr).await?;
+        info!(%addr, "Serving metrics");
+        tokio::task::spawn(metrics::serve(metrics_listener, lobby.clone()));
+    }
 
-    while let Ok((stream, addr)) = listener.accept().await {
-        let send_event = send_event.clone();
-        let recv_event = send_event.subscribe();
-        let table = table.clone();
+    let (shutdown_tx, shutdown_rx) = watch::channel(());
+    let mut connections = JoinSet::new();
+    let stop = shutdown_signal();
+    tokio::pin!(stop);
 
-        tokio::task::spawn(async move {
-            if let Err(e) = handle_connection(addr, stream, send_event, recv_event, table).await {
-                eprintln!("Error serving conn: {e}");
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, addr) = match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        //usually temporary, like running out of file descriptors, so back off and try again
+                        warn!(error = %e, "Couldn't accept connection");
+                        tokio::time::sleep(ACCEPT_BACKOFF).await;
+                        continue;
+                    }
+                };
+                let lobby = lobby.clone();
+                let config = config.clone();
+                let shutdown_rx = shutdown_rx.clone();
+
+                //errors get logged in the connection's span
+                connections.spawn(handle_connection(addr, stream, lobby, config, shutdown_rx));
+            }
+            //tidy up finished connections as we go, so they don't pile up
+            Some(_) = connections.join_next() => {}
+            () = &mut stop => {
+                info!(deadline = ?shutdown_deadline, "Shutting down");
+                break;
             }
-        });
+        }
+    }
+    drop(listener);
+
+    //everyone still connected gets told and detached, so they can resume once we're back
+    let _ = shutdown_tx.send(());
+    let drained = tokio::time::timeout(shutdown_deadline, async {
+        while connections.join_next().await.is_some() {}
+    })
+    .await;
+    if drained.is_err() {
+        warn!(
+            remaining = connections.len(),
+            "Connections didn't close in time, dropping them"
+        );
+        connections.shutdown().await;
     }
 
+    let snapshot = lobby.read().await.snapshot().await;
+    save(store, snapshot).await;
+
     Ok(())
 }
+
+fn init_logging(config: &Config) {
+    let builder = tracing_subscriber::fmt().with_max_level(tracing::Level::from(config.log_level));
+    match config.log_format {
+        LogFormat::Human => builder.init(),
+        LogFormat::Json => builder.json().init(),
+    }
+}
+
+///Finishes on Ctrl-C, or when something like Docker asks us to stop with `SIGTERM`.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = sigterm.recv() => {}
+                }
+            }
+            Err(e) => {
+                error!(error = %e, "Unable to listen for SIGTERM");
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+///How long to wait before accepting again after an error, on both the game and metrics listeners.
+pub const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+///How often to check whether anything's changed that needs saving.
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(10);
+
+///Saves the lobby whenever it's changed since the last save.
+async fn save_snapshots(
+    lobby: Arc<RwLock<Lobby>>,
+    store: Arc<dyn Store>,
+    mut last_saved: LobbySnapshot,
+) {
+    let mut interval = tokio::time::interval(SNAPSHOT_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        let snapshot = lobby.read().await.snapshot().await;
+        if snapshot != last_saved {
+            last_saved = snapshot.clone();
+            save(store.clone(), snapshot).await;
+        }
+    }
+}
+
+///Saves on a blocking thread, as the [`Store`] might be slow.
+async fn save(store: Arc<dyn Store>, snapshot: LobbySnapshot) {
+    match tokio::task::spawn_blocking(move || store.save(&snapshot)).await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => error!(error = %e, "Error saving snapshot"),
+        Err(e) => error!(error = %e, "Snapshot task failed"),
+    }
+}
+
+///Gets players up from their tables once they've been disconnected for too long to resume.
+async fn expire_detached_sessions(lobby: Arc<RwLock<Lobby>>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(5));
+    loop {
+        interval.tick().await;
+
+        let expired = lobby.write().await.take_expired();
+        for session in expired {
+            info!(uuid = %session.uuid, name = %session.name, "Session expired");
+            if let Some(table) = session.table {
+                table.remove_player(session.uuid).await;
+            }
+        }
+    }
+}
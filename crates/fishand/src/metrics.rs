@@ -0,0 +1,334 @@
+use crate::ACCEPT_BACKOFF;
+use crate::lobby::Lobby;
+use fishandchippy::events::EventReadError;
+use fishandchippy::events::server::EventToServer;
+use fishandchippy::ser_glue::driver::DriverError;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+///Everything the server counts, shared between every connection.
+pub static METRICS: Metrics = Metrics::new();
+
+///Scrapers only send a request line and a few headers, so anything bigger than this is given up on.
+const MAX_REQUEST_BYTES: usize = 4096;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+pub struct Metrics {
+    connected_clients: AtomicU64,
+    introduced_players: AtomicU64,
+    broadcast_lags: AtomicU64,
+    missed_broadcasts: AtomicU64,
+    events_received: Mutex<BTreeMap<&'static str, u64>>,
+    decode_errors: Mutex<BTreeMap<&'static str, u64>>,
+}
+
+///Counts a connection as open until it's dropped.
+#[must_use]
+pub struct OpenConnection<'a>(&'a Metrics);
+
+impl Drop for OpenConnection<'_> {
+    fn drop(&mut self) {
+        self.0.connected_clients.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Metrics {
+    pub const fn new() -> Self {
+        Self {
+            connected_clients: AtomicU64::new(0),
+            introduced_players: AtomicU64::new(0),
+            broadcast_lags: AtomicU64::new(0),
+            missed_broadcasts: AtomicU64::new(0),
+            events_received: Mutex::new(BTreeMap::new()),
+            decode_errors: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn connection_opened(&self) -> OpenConnection<'_> {
+        self.connected_clients.fetch_add(1, Ordering::Relaxed);
+        OpenConnection(self)
+    }
+
+    pub fn player_introduced(&self) {
+        self.introduced_players.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn player_left(&self) {
+        self.introduced_players.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    ///Counts a client falling `missed` events behind, giving back how many times that's happened in total.
+    pub fn broadcast_lagged(&self, missed: u64) -> u64 {
+        self.missed_broadcasts.fetch_add(missed, Ordering::Relaxed);
+        self.broadcast_lags.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    pub fn event_received(&self, evt: &EventToServer) {
+        increment(&self.events_received, event_kind(evt));
+    }
+
+    pub fn decode_failed(&self, e: &DriverError<EventReadError>) {
+        increment(&self.decode_errors, decode_error_kind(e));
+    }
+
+    ///Everything in the Prometheus text format, along with what can only be worked out by looking at the lobby.
+    pub async fn render(&self, lobby: &RwLock<Lobby>) -> String {
+        let (tables, chips_in_play) = {
+            let lobby = lobby.read().await;
+            (lobby.table_count(), lobby.chips_in_play().await)
+        };
+
+        let mut out = String::new();
+        let gauges = [
+            (
+                "fishand_connected_clients",
+                "WebSocket connections currently open.",
+                self.connected_clients.load(Ordering::Relaxed),
+            ),
+            (
+                "fishand_introduced_players",
+                "Connections that have introduced themselves.",
+                self.introduced_players.load(Ordering::Relaxed),
+            ),
+            ("fishand_tables", "Tables in the lobby.", tables as u64),
+            (
+                "fishand_chips_in_play",
+                "Chips in players' stacks and pots, across every table.",
+                chips_in_play,
+            ),
+        ];
+        for (name, help, value) in gauges {
+            write_metric(&mut out, name, help, "gauge", &[(None, value)]);
+        }
+
+        write_metric(
+            &mut out,
+            "fishand_broadcast_lag_total",
+            "Times a client fell behind a broadcast channel and was resynced.",
+            "counter",
+            &[(None, self.broadcast_lags.load(Ordering::Relaxed))],
+        );
+        write_metric(
+            &mut out,
+            "fishand_missed_broadcasts_total",
+            "Broadcast events skipped by clients that fell behind.",
+            "counter",
+            &[(None, self.missed_broadcasts.load(Ordering::Relaxed))],
+        );
+
+        let labelled = |counts: &Mutex<BTreeMap<&'static str, u64>>, label: &str| {
+            lock(counts)
+                .iter()
+                .map(|(value, count)| (Some(format!("{label}=\"{value}\"")), *count))
+                .collect::<Vec<_>>()
+        };
+        write_metric(
+            &mut out,
+            "fishand_events_received_total",
+            "Events received from clients, by kind.",
+            "counter",
+            &labelled(&self.events_received, "kind"),
+        );
+        write_metric(
+            &mut out,
+            "fishand_decode_errors_total",
+            "Messages from clients that couldn't be read, by what went wrong.",
+            "counter",
+            &labelled(&self.decode_errors, "variant"),
+        );
+
+        out
+    }
+}
+
+///A poisoned lock only means another thread panicked part of the way through adding one, so the counts are still fine to use.
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+fn increment(counts: &Mutex<BTreeMap<&'static str, u64>>, key: &'static str) {
+    *lock(counts).entry(key).or_default() += 1;
+}
+
+fn write_metric(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    kind: &str,
+    samples: &[(Option<String>, u64)],
+) {
+    //writing to a String can't fail
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+    for (labels, value) in samples {
+        match labels {
+            Some(labels) => {
+                let _ = writeln!(out, "{name}{{{labels}}} {value}");
+            }
+            None => {
+                let _ = writeln!(out, "{name} {value}");
+            }
+        }
+    }
+}
+
+const fn event_kind(evt: &EventToServer) -> &'static str {
+    match evt {
+        EventToServer::Hello { .. } => "Hello",
+        EventToServer::SendMessage { .. } => "SendMessage",
+        EventToServer::Introduction { .. } => "Introduction",
+        EventToServer::Resume { .. } => "Resume",
+        EventToServer::GetStartInformation { .. } => "GetStartInformation",
+        EventToServer::GetSpecificPlayer(..) => "GetSpecificPlayer",
+        EventToServer::AddToPot(_) => "AddToPot",
+        EventToServer::CloseBettingRound => "CloseBettingRound",
+        EventToServer::AwardPot { .. } => "AwardPot",
+        EventToServer::SplitPot { .. } => "SplitPot",
+        EventToServer::BuyIn(_) => "BuyIn",
+        EventToServer::Rebuy => "Rebuy",
+        EventToServer::ListTables { .. } => "ListTables",
+        EventToServer::CreateTable { .. } => "CreateTable",
+        EventToServer::JoinTable(_) => "JoinTable",
+        EventToServer::LeaveTable => "LeaveTable",
+        EventToServer::StartHand => "StartHand",
+        EventToServer::Check => "Check",
+        EventToServer::Call => "Call",
+        EventToServer::Raise { .. } => "Raise",
+        EventToServer::Fold => "Fold",
+        EventToServer::AllIn => "AllIn",
+    }
+}
+
+const fn decode_error_kind(e: &DriverError<EventReadError>) -> &'static str {
+    match e {
+        DriverError::Truncated { .. } => "Truncated",
+        DriverError::TooLong { .. } => "TooLong",
+        DriverError::Deser(e) => match e {
+            EventReadError::InvalidString(_) => "InvalidString",
+            EventReadError::OptionalInteger(_) => "OptionalInteger",
+            EventReadError::Integer(_) => "Integer",
+            EventReadError::InvalidKind(_) => "InvalidKind",
+            EventReadError::StringRead(_) => "StringRead",
+            EventReadError::Pot(_) => "Pot",
+            EventReadError::ListOfPlayers(_) => "ListOfPlayers",
+            EventReadError::ListOfUuids(_) => "ListOfUuids",
+            EventReadError::Player(_) => "Player",
+            EventReadError::ErrorCode(_) => "ErrorCode",
+            EventReadError::RequestId(_) => "RequestId",
+            EventReadError::ResumeToken(_) => "ResumeToken",
+            EventReadError::TableId(_) => "TableId",
+            EventReadError::TableSummary(_) => "TableSummary",
+            EventReadError::ListOfTables(_) => "ListOfTables",
+            EventReadError::ListOfCards(_) => "ListOfCards",
+            EventReadError::ListOfShownHands(_) => "ListOfShownHands",
+        },
+    }
+}
+
+///Answers `GET /metrics` on `listener`, and 404s everything else.
+pub async fn serve(listener: TcpListener, lobby: Arc<RwLock<Lobby>>) {
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                warn!(error = %e, "Error accepting metrics connection");
+                tokio::time::sleep(ACCEPT_BACKOFF).await;
+                continue;
+            }
+        };
+        let lobby = lobby.clone();
+        tokio::task::spawn(async move {
+            match tokio::time::timeout(REQUEST_TIMEOUT, respond(stream, &lobby)).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => info!(%peer, error = %e, "Error serving metrics"),
+                Err(_) => info!(%peer, "Metrics request timed out"),
+            }
+        });
+    }
+}
+
+async fn respond(mut stream: TcpStream, lobby: &RwLock<Lobby>) -> std::io::Result<()> {
+    let mut request = Vec::with_capacity(256);
+    let mut buf = [0; 512];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 || request.len() + n > MAX_REQUEST_BYTES {
+            return Ok(());
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+
+    let (status, body) = if request.starts_with(b"GET /metrics ") {
+        ("200 OK", METRICS.render(lobby).await)
+    } else {
+        ("404 Not Found", String::new())
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lobby::Lobby;
+    use crate::metrics::Metrics;
+    use crate::table::TableRules;
+    use fishandchippy::events::server::EventToServer;
+    use fishandchippy::game_types::table::TableId;
+    use fishandchippy::ser_glue::driver::DriverError;
+    use tokio::sync::RwLock;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn renders_every_metric() {
+        let lobby = Lobby::new(TableRules::default(), 4);
+        {
+            let table = lobby.get(TableId::MAIN).unwrap();
+            let mut table = table.state.write().await;
+            table.add_player(Uuid::new_v4(), "Ferris".to_string());
+            table.pot.current_value = 50;
+        }
+        let lobby = RwLock::new(lobby);
+
+        let metrics = Metrics::new();
+        let _connection = metrics.connection_opened();
+        metrics.player_introduced();
+        metrics.event_received(&EventToServer::Rebuy);
+        metrics.event_received(&EventToServer::Rebuy);
+        metrics.decode_failed(&DriverError::TooLong {
+            max_message_bytes: 1,
+        });
+        assert_eq!(metrics.broadcast_lagged(7), 1);
+
+        let rendered = metrics.render(&lobby).await;
+        for line in [
+            "fishand_connected_clients 1",
+            "fishand_introduced_players 1",
+            "fishand_tables 1",
+            "fishand_chips_in_play 1050",
+            "fishand_broadcast_lag_total 1",
+            "fishand_missed_broadcasts_total 7",
+            "fishand_events_received_total{kind=\"Rebuy\"} 2",
+            "fishand_decode_errors_total{variant=\"TooLong\"} 1",
+        ] {
+            assert!(
+                rendered.lines().any(|l| l == line),
+                "missing {line:?} in:\n{rendered}"
+            );
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use crate::lobby::{LobbySnapshot, LobbySnapshotReadError, SNAPSHOT_VERSION};
+use fishandchippy::ser_glue::{DeserMachine, Deserable, ReadFromError, Serable};
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind};
+use std::path::PathBuf;
+
+///Somewhere to keep a [`LobbySnapshot`] between restarts.
+pub trait Store: Send + Sync {
+    ///Gets the last saved snapshot, or `None` if nothing's been saved yet.
+    fn load(&self) -> Result<Option<LobbySnapshot>, PersistenceError>;
+    fn save(&self, snapshot: &LobbySnapshot) -> Result<(), PersistenceError>;
+}
+
+#[derive(Debug)]
+pub enum PersistenceError {
+    Io(std::io::Error),
+    Corrupt(ReadFromError<LobbySnapshotReadError>),
+    ///The snapshot was made by a build with a different [`SNAPSHOT_VERSION`].
+    UnsupportedVersion {
+        found: u32,
+    },
+}
+
+impl Display for PersistenceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Error accessing snapshot: {e}"),
+            Self::Corrupt(e) => write!(f, "Snapshot is corrupt: {e}"),
+            Self::UnsupportedVersion { found } => write!(
+                f,
+                "Snapshot is version {found}, but only version {SNAPSHOT_VERSION} is supported"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PersistenceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Corrupt(e) => Some(e),
+            Self::UnsupportedVersion { .. } => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for PersistenceError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+///Keeps the snapshot in one file, encoded with [`Serable`].
+#[derive(Debug, Clone)]
+pub struct FileStore {
+    path: PathBuf,
+}
+
+impl FileStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl Store for FileStore {
+    fn load(&self) -> Result<Option<LobbySnapshot>, PersistenceError> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let snapshot = LobbySnapshot::deser()
+            .read_from(BufReader::new(file))
+            .map_err(PersistenceError::Corrupt)?;
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(PersistenceError::UnsupportedVersion {
+                found: snapshot.version,
+            });
+        }
+        Ok(Some(snapshot))
+    }
+
+    fn save(&self, snapshot: &LobbySnapshot) -> Result<(), PersistenceError> {
+        //write everything out next to the real file first, so a crash part of the way through can't leave half a snapshot
+        let tmp_path = self.path.with_extension("tmp");
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        snapshot.ser_to_writer(&mut writer)?;
+        writer
+            .into_inner()
+            .map_err(std::io::IntoInnerError::into_error)?
+            .sync_all()?;
+
+        std::fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lobby::Lobby;
+    use crate::persistence::{FileStore, PersistenceError, Store};
+    use crate::table::TableRules;
+    use fishandchippy::events::ResumeToken;
+    use fishandchippy::game_types::table::TableId;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn file_store_round_trips_lobby() {
+        let rules = TableRules::default();
+        let mut lobby = Lobby::new(rules, 4);
+        let side_table = lobby.create_table("Side table").unwrap();
+
+        let (resuming, leaving) = (Uuid::new_v4(), Uuid::new_v4());
+        {
+            let mut state = side_table.state.write().await;
+            state.add_player(resuming, "Ferris".to_string());
+            state.add_player(leaving, "Corro".to_string());
+            state.buy_in(resuming, 500).unwrap();
+        }
+        let token = ResumeToken::new_random();
+        lobby.detach(token, resuming, "Ferris".to_string(), Some(side_table));
+
+        let path = std::env::temp_dir().join(format!("fishand-{}.snapshot", Uuid::new_v4()));
+        let store = FileStore::new(&path);
+        assert!(store.load().unwrap().is_none());
+
+        let snapshot = lobby.snapshot().await;
+        store.save(&snapshot).unwrap();
+        let loaded = store.load().unwrap().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, snapshot);
+
+        let mut restored = Lobby::restore(loaded, rules, 4);
+        let summaries = restored.summaries().await;
+        assert_eq!(
+            summaries
+                .iter()
+                .map(|s| s.name.as_str())
+                .collect::<Vec<_>>(),
+            ["Main table", "Side table"]
+        );
+        //only the player who could come back keeps their seat
+        assert_eq!(summaries[1].player_count, 1);
+
+        let session = restored.reattach(token).unwrap();
+        let table = session.table.unwrap();
+        assert_eq!(table.id, TableId(1));
+        assert_eq!(table.state.read().await.players[&resuming].balance, 1_500);
+        assert_eq!(restored.create_table("Another").unwrap().id, TableId(2));
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        let path = std::env::temp_dir().join(format!("fishand-{}.snapshot", Uuid::new_v4()));
+        std::fs::write(&path, [0xFF; 3]).unwrap();
+        let result = FileStore::new(&path).load();
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(result, Err(PersistenceError::Corrupt(_))));
+    }
+}
@@ -0,0 +1,842 @@
+use crate::hand::{Action, ActionError, HandState, Showdown, StartHandError};
+use fishandchippy::game_types::cards::Card;
+use fishandchippy::game_types::player::Player;
+use fishandchippy::game_types::pot::Pot;
+use fishandchippy::ser_glue::{Deserable, Serable};
+use rand::RngCore;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::time::SystemTime;
+use tracing::info;
+use uuid::Uuid;
+
+///How many [`BalanceChange`]s each table keeps in memory. Older ones are only in the `audit` log target.
+const AUDIT_LOG_LEN: usize = 256;
+
+///Chip limits for a table, enforced by the server.
+#[derive(Debug, Copy, Clone)]
+pub struct TableRules {
+    ///What every player gets the first time they sit down.
+    pub starting_balance: u32,
+    ///The smallest [`EventToServer::BuyIn`](fishandchippy::events::server::EventToServer::BuyIn) allowed.
+    pub min_buy_in: u32,
+    ///The biggest [`EventToServer::BuyIn`](fishandchippy::events::server::EventToServer::BuyIn) allowed.
+    pub max_buy_in: u32,
+    ///What a player gets for an [`EventToServer::Rebuy`](fishandchippy::events::server::EventToServer::Rebuy).
+    pub rebuy_amount: u32,
+    ///How many times each player can rebuy.
+    pub max_rebuys: u32,
+    ///How many players can sit at the table at once.
+    pub max_players: usize,
+    ///The smallest bet, and the smallest amount a raise can go up by. This is also the big blind - the small blind is half that.
+    pub min_bet: u32,
+}
+
+impl Default for TableRules {
+    fn default() -> Self {
+        Self {
+            starting_balance: 1_000,
+            min_buy_in: 100,
+            max_buy_in: 5_000,
+            rebuy_amount: 1_000,
+            max_rebuys: 3,
+            max_players: 10,
+            min_bet: 10,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BalanceChangeReason {
+    StartingStack,
+    BuyIn,
+    Rebuy,
+    AddedToPot,
+    WonPot,
+    ///Got back chips from the pot that nobody could win, like bets that hadn't been swept in when they left, or a hand that got cut off by a restart.
+    Refunded,
+    ///Got up from the table, leaving their chips with it until they come back.
+    CashedOut,
+    ///Sat back down, and got back what they had when they left.
+    SatBackDown,
+}
+
+///One entry in the [`Table`] audit log.
+#[derive(Debug, Clone)]
+pub struct BalanceChange {
+    pub at: SystemTime,
+    pub player: Uuid,
+    pub reason: BalanceChangeReason,
+    pub old_balance: u32,
+    pub new_balance: u32,
+}
+
+impl Display for BalanceChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let millis = self
+            .at
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        write!(
+            f,
+            "{millis} {} {:?}: {} -> {}",
+            self.player, self.reason, self.old_balance, self.new_balance
+        )
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BalanceError {
+    UnknownPlayer,
+    NotEnough { balance: u32 },
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BuyInError {
+    UnknownPlayer,
+    OutOfRange { min: u32, max: u32 },
+    AlreadyBoughtIn,
+    StillHasChips { balance: u32 },
+    NoRebuysLeft { max: u32 },
+}
+
+impl Display for BuyInError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownPlayer => write!(f, "You're not at the table"),
+            Self::OutOfRange { min, max } => {
+                write!(f, "Buy-ins have to be between {min} and {max} chips")
+            }
+            Self::AlreadyBoughtIn => write!(f, "You've already bought in"),
+            Self::StillHasChips { balance } => {
+                write!(
+                    f,
+                    "You can only rebuy once you're out of chips, and you have {balance}"
+                )
+            }
+            Self::NoRebuysLeft { max } => write!(f, "You've already used all {max} rebuy(s)"),
+        }
+    }
+}
+
+///Server-only bookkeeping for each player who has ever sat at the table, which isn't sent to clients. It's kept after they leave, so getting up and sitting back down doesn't get anyone a fresh stack or more rebuys.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash, Serable, Deserable)]
+pub struct Seat {
+    buy_ins: u32,
+    rebuys: u32,
+    ///What they had when they last got up, which they get back when they sit down again.
+    cashed_out: u32,
+}
+
+///Everything about a [`Table`] that's worth keeping across restarts. The rules come from the config instead, and the audit log has already been written out.
+///
+/// Any hand being played is thrown away, and everyone still at the table gets back what they'd bet in it.
+#[derive(Debug, Clone, Eq, PartialEq, Serable, Deserable)]
+pub struct TableSnapshot {
+    pub pot: Pot,
+    pub players: HashMap<Uuid, Player>,
+    seats: HashMap<Uuid, Seat>,
+    hand_bets: HashMap<Uuid, u32>,
+}
+
+///What happened after someone acted in a hand.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct HandProgress {
+    ///The whole board, if more of it was dealt.
+    pub board: Option<Vec<Card>>,
+    ///How the hand ended, if it's over. The pot still needs paying out.
+    pub showdown: Option<Showdown>,
+}
+
+///Where a vote to hand out the pot by hand has got to.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum AwardVote {
+    ///Everyone at the table who isn't away wants the pot to go to these winners, sorted.
+    Passed(Vec<Uuid>),
+    ///Only `votes` of the `needed` players have asked for these winners so far.
+    Waiting { votes: usize, needed: usize },
+}
+
+///All changes to player balances should go through here, so they end up in the audit log.
+#[derive(Debug, Default, Clone)]
+pub struct Table {
+    pub pot: Pot,
+    pub players: HashMap<Uuid, Player>,
+    pub rules: TableRules,
+    ///The hand being played, if there is one.
+    pub hand: Option<HandState>,
+    ///Who had the button last hand.
+    button: Option<Uuid>,
+    ///Players who are still seated but have lost their connection, who get left out of hands until they're back.
+    away: HashSet<Uuid>,
+    seats: HashMap<Uuid, Seat>,
+    ///How much each player has put in during the hand being played, so it can be given back if the hand never finishes.
+    hand_bets: HashMap<Uuid, u32>,
+    ///Who each player wants to award the pot to, and what the pot looked like when they asked. Votes stop counting once the pot changes.
+    award_votes: HashMap<Uuid, (Vec<Uuid>, Pot)>,
+    ///The most recent [`AUDIT_LOG_LEN`] balance changes, oldest first.
+    audit_log: VecDeque<BalanceChange>,
+}
+
+impl Table {
+    pub fn new(rules: TableRules) -> Self {
+        Self {
+            rules,
+            ..Self::default()
+        }
+    }
+
+    pub fn restore(rules: TableRules, snapshot: TableSnapshot) -> Self {
+        let mut table = Self {
+            pot: snapshot.pot,
+            //nobody's connected straight after a restart
+            away: snapshot.players.keys().copied().collect(),
+            players: snapshot.players,
+            rules,
+            hand: None,
+            button: None,
+            seats: snapshot.seats,
+            hand_bets: HashMap::new(),
+            award_votes: HashMap::new(),
+            audit_log: VecDeque::new(),
+        };
+
+        //the hand can't be picked back up, so undo it - only anyone who left part way through leaves their chips behind
+        let mut left_in = table.pot.total();
+        for (uuid, bet) in snapshot.hand_bets {
+            if table
+                .credit(uuid, bet, BalanceChangeReason::Refunded)
+                .is_some()
+            {
+                left_in = left_in.saturating_sub(bet);
+            }
+        }
+        if left_in != table.pot.total() {
+            table.pot = Pot {
+                current_value: left_in,
+                ..Pot::default()
+            };
+        }
+        table
+    }
+
+    pub fn snapshot(&self) -> TableSnapshot {
+        TableSnapshot {
+            pot: self.pot.clone(),
+            players: self.players.clone(),
+            seats: self.seats.clone(),
+            hand_bets: self.hand_bets.clone(),
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.players.len() >= self.rules.max_players
+    }
+
+    ///Sits a player down. They get [`TableRules::starting_balance`] the first time, and whatever they left with after that.
+    pub fn add_player(&mut self, uuid: Uuid, name: String) {
+        self.players.insert(uuid, Player { name, balance: 0 });
+        if let Some(seat) = self.seats.get_mut(&uuid) {
+            let cashed_out = std::mem::take(&mut seat.cashed_out);
+            self.credit(uuid, cashed_out, BalanceChangeReason::SatBackDown);
+        } else {
+            self.seats.insert(uuid, Seat::default());
+            self.credit(
+                uuid,
+                self.rules.starting_balance,
+                BalanceChangeReason::StartingStack,
+            );
+        }
+    }
+
+    ///Deals a new hand to everyone with chips who isn't away, moving the button on and putting the blinds in the pot.
+    pub fn start_hand(&mut self, rng: &mut impl RngCore) -> Result<&HandState, StartHandError> {
+        if self.hand.is_some() {
+            return Err(StartHandError::InProgress);
+        }
+        let players = self
+            .players
+            .iter()
+            .filter(|(uuid, player)| player.balance > 0 && !self.away.contains(uuid))
+            .map(|(uuid, player)| (*uuid, player.balance));
+        let hand = HandState::deal(players, self.button, self.rules.min_bet, rng)?;
+        self.button = Some(hand.button());
+        let blinds: Vec<_> = hand.bets().collect();
+        for (uuid, blind) in blinds {
+            self.bet(uuid, blind);
+        }
+        Ok(self.hand.insert(hand))
+    }
+
+    ///Marks a player as having lost their connection, or come back. Players who go away fold any hand they're in, so they can't hold everyone else up.
+    pub fn set_away(&mut self, uuid: Uuid, away: bool) -> HandProgress {
+        if !away {
+            self.away.remove(&uuid);
+            return HandProgress::default();
+        }
+        if !self.players.contains_key(&uuid) {
+            return HandProgress::default();
+        }
+        self.away.insert(uuid);
+        if let Some(hand) = &mut self.hand {
+            hand.fold(uuid);
+        }
+        self.settle()
+    }
+
+    ///Does `action` for `uuid` in the hand being played, putting any chips it needs in the pot, and then moves the hand on as far as it'll go.
+    pub fn act(&mut self, uuid: Uuid, action: Action) -> Result<HandProgress, ActionError> {
+        let hand = self.hand.as_mut().ok_or(ActionError::NoHand)?;
+        let balance = self
+            .players
+            .get(&uuid)
+            .ok_or(ActionError::NotInHand)?
+            .balance;
+        let amount = hand.act(uuid, action, balance)?;
+
+        if amount > 0 {
+            self.bet(uuid, amount);
+        }
+        Ok(self.settle())
+    }
+
+    ///Moves chips the hand has already checked `uuid` can afford from their balance into the pot.
+    fn bet(&mut self, uuid: Uuid, amount: u32) {
+        let _ = self.debit(uuid, amount, BalanceChangeReason::AddedToPot);
+        *self.pot.ready_to_put_in.entry(uuid).or_default() += amount;
+        *self.hand_bets.entry(uuid).or_default() += amount;
+    }
+
+    ///Moves the hand being played on until someone has to act, sweeping bets into the pot after each betting round.
+    pub fn settle(&mut self) -> HandProgress {
+        let mut progress = HandProgress::default();
+        while let Some(hand) = &mut self.hand {
+            let showdown = if hand.players().count() <= 1 {
+                //everyone else folded, so there's no need to show anything
+                Some(Showdown {
+                    shown: vec![],
+                    ranking: hand
+                        .last_one_standing()
+                        .into_iter()
+                        .map(|uuid| vec![uuid])
+                        .collect(),
+                })
+            } else if hand.betting_done() {
+                let showdown = hand.advance();
+                if showdown.is_none() {
+                    progress.board = Some(hand.board().to_vec());
+                }
+                showdown
+            } else {
+                return progress;
+            };
+
+            let all_in: Vec<_> = hand.all_in().collect();
+            self.pot.close_betting_round(&all_in);
+            if showdown.is_some() {
+                self.hand = None;
+                self.hand_bets.clear();
+                progress.showdown = showdown;
+            }
+        }
+        progress
+    }
+
+    ///Records that `player` wants the pot split between `winners`. It only gets awarded once everyone at the table who isn't away has asked for the same winners, so nobody can take the pot for themselves.
+    pub fn vote_to_award(&mut self, player: Uuid, winners: &[Uuid]) -> AwardVote {
+        let mut winners = winners.to_vec();
+        winners.sort_unstable();
+        winners.dedup();
+
+        //away players can't vote, so they'd hold the pot up until their seat expired
+        let can_vote: HashSet<_> = self
+            .players
+            .keys()
+            .filter(|uuid| !self.away.contains(uuid))
+            .copied()
+            .collect();
+        let pot = &self.pot;
+        self.award_votes
+            .retain(|uuid, (_, voted_on)| can_vote.contains(uuid) && voted_on == pot);
+        self.award_votes
+            .insert(player, (winners.clone(), self.pot.clone()));
+
+        let votes = self
+            .award_votes
+            .values()
+            .filter(|(wanted, _)| *wanted == winners)
+            .count();
+        let needed = can_vote.len();
+        if votes >= needed {
+            self.award_votes.clear();
+            AwardVote::Passed(winners)
+        } else {
+            AwardVote::Waiting { votes, needed }
+        }
+    }
+
+    ///Removes a player, keeping their balance on their [`Seat`] for when they come back. If there's a hand being played, they fold and their bets stay in for whoever wins. Otherwise, anything they had ready to put into the pot goes back on their balance first.
+    pub fn remove_player(&mut self, uuid: &Uuid) -> Option<Player> {
+        let ready = self.pot.ready_to_put_in.remove(uuid).unwrap_or(0);
+        if let Some(hand) = &mut self.hand {
+            hand.fold(*uuid);
+            self.pot.current_value = self.pot.current_value.saturating_add(ready);
+        } else if ready > 0 {
+            self.credit(*uuid, ready, BalanceChangeReason::Refunded);
+        }
+        self.away.remove(uuid);
+        let player = self.players.remove(uuid)?;
+        if let Some(seat) = self.seats.get_mut(uuid) {
+            seat.cashed_out = player.balance;
+        }
+        self.record(*uuid, BalanceChangeReason::CashedOut, player.balance, 0);
+        Some(player)
+    }
+
+    pub fn credit(
+        &mut self,
+        uuid: Uuid,
+        amount: u32,
+        reason: BalanceChangeReason,
+    ) -> Option<&Player> {
+        let player = self.players.get_mut(&uuid)?;
+        let old_balance = player.balance;
+        player.balance = old_balance.saturating_add(amount);
+        let new_balance = player.balance;
+
+        self.record(uuid, reason, old_balance, new_balance);
+        self.players.get(&uuid)
+    }
+
+    pub fn debit(
+        &mut self,
+        uuid: Uuid,
+        amount: u32,
+        reason: BalanceChangeReason,
+    ) -> Result<&Player, BalanceError> {
+        let player = self
+            .players
+            .get_mut(&uuid)
+            .ok_or(BalanceError::UnknownPlayer)?;
+        let old_balance = player.balance;
+        player.balance = old_balance
+            .checked_sub(amount)
+            .ok_or(BalanceError::NotEnough {
+                balance: old_balance,
+            })?;
+        let new_balance = player.balance;
+
+        self.record(uuid, reason, old_balance, new_balance);
+        self.players.get(&uuid).ok_or(BalanceError::UnknownPlayer)
+    }
+
+    ///Each player can buy in once, for an amount within the [`TableRules`].
+    pub fn buy_in(&mut self, uuid: Uuid, amount: u32) -> Result<&Player, BuyInError> {
+        let TableRules {
+            min_buy_in,
+            max_buy_in,
+            ..
+        } = self.rules;
+        let seat = self.seats.get_mut(&uuid).ok_or(BuyInError::UnknownPlayer)?;
+        if seat.buy_ins > 0 {
+            return Err(BuyInError::AlreadyBoughtIn);
+        }
+        if !(min_buy_in..=max_buy_in).contains(&amount) {
+            return Err(BuyInError::OutOfRange {
+                min: min_buy_in,
+                max: max_buy_in,
+            });
+        }
+        seat.buy_ins += 1;
+
+        self.credit(uuid, amount, BalanceChangeReason::BuyIn)
+            .ok_or(BuyInError::UnknownPlayer)
+    }
+
+    ///Players who are out of chips can get [`TableRules::rebuy_amount`] more, up to [`TableRules::max_rebuys`] times.
+    pub fn rebuy(&mut self, uuid: Uuid) -> Result<&Player, BuyInError> {
+        let TableRules {
+            rebuy_amount,
+            max_rebuys,
+            ..
+        } = self.rules;
+        let balance = self
+            .players
+            .get(&uuid)
+            .ok_or(BuyInError::UnknownPlayer)?
+            .balance;
+        let seat = self.seats.get_mut(&uuid).ok_or(BuyInError::UnknownPlayer)?;
+        if balance > 0 {
+            return Err(BuyInError::StillHasChips { balance });
+        }
+        if seat.rebuys >= max_rebuys {
+            return Err(BuyInError::NoRebuysLeft { max: max_rebuys });
+        }
+        seat.rebuys += 1;
+
+        self.credit(uuid, rebuy_amount, BalanceChangeReason::Rebuy)
+            .ok_or(BuyInError::UnknownPlayer)
+    }
+
+    fn record(
+        &mut self,
+        player: Uuid,
+        reason: BalanceChangeReason,
+        old_balance: u32,
+        new_balance: u32,
+    ) {
+        let change = BalanceChange {
+            at: SystemTime::now(),
+            player,
+            reason,
+            old_balance,
+            new_balance,
+        };
+        info!(
+            target: "audit",
+            %player,
+            ?reason,
+            old_balance,
+            new_balance,
+            "Balance changed"
+        );
+        if self.audit_log.len() >= AUDIT_LOG_LEN {
+            self.audit_log.pop_front();
+        }
+        self.audit_log.push_back(change);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hand::{Action, StartHandError};
+    use crate::table::{
+        AUDIT_LOG_LEN, AwardVote, BalanceChangeReason, BalanceError, BuyInError, HandProgress,
+        Table, TableRules,
+    };
+    use rand::SeedableRng;
+    use rand_pcg::Pcg32;
+    use uuid::Uuid;
+
+    const RULES: TableRules = TableRules {
+        starting_balance: 50,
+        min_buy_in: 10,
+        max_buy_in: 100,
+        rebuy_amount: 20,
+        max_rebuys: 1,
+        max_players: 2,
+        min_bet: 10,
+    };
+
+    #[test]
+    fn enforces_buy_in_limits() {
+        let mut table = Table::new(RULES);
+        let uuid = Uuid::new_v4();
+        table.add_player(uuid, "Alice".to_string());
+        assert_eq!(table.players[&uuid].balance, 50);
+
+        assert_eq!(
+            table.buy_in(uuid, 101).unwrap_err(),
+            BuyInError::OutOfRange { min: 10, max: 100 }
+        );
+        assert_eq!(table.buy_in(uuid, 100).unwrap().balance, 150);
+        assert_eq!(
+            table.buy_in(uuid, 10).unwrap_err(),
+            BuyInError::AlreadyBoughtIn
+        );
+
+        assert_eq!(
+            table.rebuy(uuid).unwrap_err(),
+            BuyInError::StillHasChips { balance: 150 }
+        );
+        table
+            .debit(uuid, 150, BalanceChangeReason::AddedToPot)
+            .unwrap();
+        assert_eq!(table.rebuy(uuid).unwrap().balance, 20);
+        table
+            .debit(uuid, 20, BalanceChangeReason::AddedToPot)
+            .unwrap();
+        assert_eq!(
+            table.rebuy(uuid).unwrap_err(),
+            BuyInError::NoRebuysLeft { max: 1 }
+        );
+        assert_eq!(
+            table.buy_in(Uuid::new_v4(), 50).unwrap_err(),
+            BuyInError::UnknownPlayer
+        );
+    }
+
+    #[test]
+    fn records_every_balance_change() {
+        let mut table = Table::new(RULES);
+        let uuid = Uuid::new_v4();
+        table.add_player(uuid, "Bob".to_string());
+        table.buy_in(uuid, 10).unwrap();
+        assert_eq!(
+            table.debit(uuid, 1_000, BalanceChangeReason::AddedToPot),
+            Err(BalanceError::NotEnough { balance: 60 })
+        );
+        table
+            .debit(uuid, 25, BalanceChangeReason::AddedToPot)
+            .unwrap();
+        table.credit(uuid, 5, BalanceChangeReason::WonPot);
+
+        let log: Vec<_> = table
+            .audit_log
+            .iter()
+            .map(|change| {
+                assert_eq!(change.player, uuid);
+                (change.reason, change.old_balance, change.new_balance)
+            })
+            .collect();
+        assert_eq!(
+            log,
+            [
+                (BalanceChangeReason::StartingStack, 0, 50),
+                (BalanceChangeReason::BuyIn, 50, 60),
+                (BalanceChangeReason::AddedToPot, 60, 35),
+                (BalanceChangeReason::WonPot, 35, 40),
+            ]
+        );
+
+        //only the newest changes are kept around
+        for _ in 0..AUDIT_LOG_LEN {
+            table.credit(uuid, 1, BalanceChangeReason::WonPot);
+        }
+        assert_eq!(table.audit_log.len(), AUDIT_LOG_LEN);
+        assert_eq!(table.audit_log.front().unwrap().old_balance, 40);
+    }
+
+    #[test]
+    fn leaving_gives_back_unswept_bets() {
+        let mut table = Table::new(RULES);
+        let uuid = Uuid::new_v4();
+        table.add_player(uuid, "Carol".to_string());
+        table
+            .debit(uuid, 30, BalanceChangeReason::AddedToPot)
+            .unwrap();
+        table.pot.ready_to_put_in.insert(uuid, 30);
+
+        assert_eq!(table.remove_player(&uuid).unwrap().balance, 50);
+        assert_eq!(table.pot.total(), 0);
+        let reasons: Vec<_> = table.audit_log.iter().rev().map(|c| c.reason).collect();
+        assert_eq!(
+            reasons[..2],
+            [
+                BalanceChangeReason::CashedOut,
+                BalanceChangeReason::Refunded
+            ]
+        );
+    }
+
+    #[test]
+    fn leaving_and_rejoining_keeps_balance_and_limits() {
+        let mut table = Table::new(RULES);
+        let uuid = Uuid::new_v4();
+        table.add_player(uuid, "Dave".to_string());
+        table.buy_in(uuid, 10).unwrap();
+        table
+            .debit(uuid, 60, BalanceChangeReason::AddedToPot)
+            .unwrap();
+        assert_eq!(table.rebuy(uuid).unwrap().balance, 20);
+        table
+            .debit(uuid, 15, BalanceChangeReason::AddedToPot)
+            .unwrap();
+
+        assert_eq!(table.remove_player(&uuid).unwrap().balance, 5);
+        table.add_player(uuid, "Dave".to_string());
+        assert_eq!(table.players[&uuid].balance, 5);
+        assert_eq!(
+            table.audit_log.back().unwrap().reason,
+            BalanceChangeReason::SatBackDown
+        );
+
+        table
+            .debit(uuid, 5, BalanceChangeReason::AddedToPot)
+            .unwrap();
+        table.remove_player(&uuid);
+        table.add_player(uuid, "Dave".to_string());
+        assert_eq!(table.players[&uuid].balance, 0);
+        assert_eq!(
+            table.rebuy(uuid).unwrap_err(),
+            BuyInError::NoRebuysLeft { max: 1 }
+        );
+        assert_eq!(
+            table.buy_in(uuid, 10).unwrap_err(),
+            BuyInError::AlreadyBoughtIn
+        );
+    }
+
+    #[test]
+    fn away_players_dont_get_a_vote() {
+        let mut table = Table::new(TableRules::default());
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        for uuid in [a, b, c] {
+            table.add_player(uuid, uuid.to_string());
+        }
+        table.pot.current_value = 100;
+
+        assert_eq!(
+            table.vote_to_award(c, &[c]),
+            AwardVote::Waiting {
+                votes: 1,
+                needed: 3
+            }
+        );
+        table.set_away(c, true);
+        assert_eq!(
+            table.vote_to_award(a, &[a]),
+            AwardVote::Waiting {
+                votes: 1,
+                needed: 2
+            }
+        );
+        assert_eq!(table.vote_to_award(b, &[a]), AwardVote::Passed(vec![a]));
+    }
+
+    #[test]
+    fn awarding_the_pot_needs_everyone_to_agree() {
+        let mut table = Table::new(TableRules::default());
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        table.add_player(a, "Alice".to_string());
+        table.add_player(b, "Bob".to_string());
+        table.pot.current_value = 100;
+
+        assert_eq!(
+            table.vote_to_award(a, &[a]),
+            AwardVote::Waiting {
+                votes: 1,
+                needed: 2
+            }
+        );
+        //changing the pot throws away any votes about it
+        table.pot.current_value = 200;
+        assert_eq!(
+            table.vote_to_award(b, &[a]),
+            AwardVote::Waiting {
+                votes: 1,
+                needed: 2
+            }
+        );
+        assert_eq!(
+            table.vote_to_award(a, &[b, a]),
+            AwardVote::Waiting {
+                votes: 1,
+                needed: 2
+            }
+        );
+
+        let mut both = vec![a, b];
+        both.sort_unstable();
+        assert_eq!(table.vote_to_award(b, &[a, b, a]), AwardVote::Passed(both));
+        //passing a vote uses everyone's up
+        assert_eq!(
+            table.vote_to_award(a, &[a]),
+            AwardVote::Waiting {
+                votes: 1,
+                needed: 2
+            }
+        );
+    }
+
+    #[test]
+    fn runs_the_board_out_when_everyone_is_all_in() {
+        let mut table = Table::new(TableRules::default());
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        table.add_player(a, "Alice".to_string());
+        table.add_player(b, "Bob".to_string());
+        table.start_hand(&mut Pcg32::seed_from_u64(5)).unwrap();
+
+        let first = table.hand.as_ref().unwrap().to_act().unwrap();
+        let second = if first == a { b } else { a };
+        assert_eq!(
+            table.act(first, Action::AllIn).unwrap(),
+            HandProgress::default()
+        );
+        assert_eq!(table.players[&first].balance, 0);
+
+        let progress = table.act(second, Action::Call).unwrap();
+        assert_eq!(progress.board.map(|board| board.len()), Some(5));
+        assert_eq!(progress.showdown.unwrap().shown.len(), 2);
+        assert!(table.hand.is_none());
+        //paying out is left to whoever's telling the players about it
+        assert_eq!(table.pot.total(), 2_000);
+        assert!(table.pot.ready_to_put_in.is_empty());
+    }
+
+    #[test]
+    fn players_who_go_away_sit_hands_out() {
+        let mut table = Table::new(TableRules::default());
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        table.add_player(a, "Alice".to_string());
+        table.add_player(b, "Bob".to_string());
+        let mut rng = Pcg32::seed_from_u64(5);
+        let first_button = table.start_hand(&mut rng).unwrap().button();
+
+        //losing connection folds them, which ends a heads up hand
+        let showdown = table.set_away(b, true).showdown.unwrap();
+        assert_eq!(showdown.ranking, [vec![a]]);
+        assert!(table.hand.is_none());
+        assert_eq!(
+            table.start_hand(&mut rng).unwrap_err(),
+            StartHandError::WrongPlayerCount { found: 1 }
+        );
+
+        table.set_away(b, false);
+        let hand = table.start_hand(&mut rng).unwrap();
+        assert_eq!(hand.players().count(), 2);
+        assert_ne!(hand.button(), first_button);
+    }
+
+    #[test]
+    fn short_stacks_can_only_win_what_they_matched() {
+        let mut table = Table::new(TableRules::default());
+        let mut players: Vec<_> = (0..3).map(|_| Uuid::new_v4()).collect();
+        players.sort_unstable();
+        let [short, a, b] = players[..] else {
+            unreachable!()
+        };
+        for (uuid, name) in [(short, "Short"), (a, "Alice"), (b, "Bob")] {
+            table.add_player(uuid, name.to_string());
+        }
+        table.buy_in(a, 1_000).unwrap();
+        table.buy_in(b, 1_000).unwrap();
+        table.start_hand(&mut Pcg32::seed_from_u64(5)).unwrap();
+        //b has the button, so short and a are in for the blinds
+        assert_eq!(table.pot.ready_to_put_in[&short], 5);
+        assert_eq!(table.players[&a].balance, 1_990);
+
+        table.act(b, Action::Call).unwrap();
+        table.act(short, Action::AllIn).unwrap();
+        table.act(a, Action::Call).unwrap();
+        assert_eq!(table.act(b, Action::Call).unwrap().board.unwrap().len(), 3);
+        assert_eq!(table.pot.side_pots.len(), 1);
+
+        table.act(a, Action::Raise { to: 500 }).unwrap();
+        table.act(b, Action::Call).unwrap();
+        table.act(a, Action::Check).unwrap();
+        table.act(b, Action::Check).unwrap();
+        table.act(a, Action::Check).unwrap();
+        let showdown = table.act(b, Action::Check).unwrap().showdown.unwrap();
+
+        let side_pots: Vec<_> = table
+            .pot
+            .side_pots
+            .iter()
+            .map(|side_pot| (side_pot.amount, side_pot.eligible.clone()))
+            .collect();
+        assert_eq!(side_pots, [(3_000, vec![short, a, b]), (1_000, vec![a, b])]);
+
+        let won = table.pot.award(&showdown.ranking);
+        assert_eq!(won.iter().map(|(_, amount)| amount).sum::<u32>(), 4_000);
+        let short_won = won
+            .iter()
+            .find(|(uuid, _)| *uuid == short)
+            .map_or(0, |(_, amount)| *amount);
+        assert!(short_won <= 3_000);
+        if showdown.ranking[0] == [short] {
+            assert_eq!(short_won, 3_000);
+        }
+    }
+}
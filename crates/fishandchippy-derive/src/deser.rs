@@ -0,0 +1,430 @@
+use crate::model::{
+    Container, Field, Fields, Shape, Style, camel_to_shouty, crate_path, ident, tuple_of,
+};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::Ident;
+
+///One field being read, and therefore one state in the generated state machine.
+struct Step<'a> {
+    fields: &'a Fields,
+    index: usize,
+    variant: Option<&'a Ident>,
+}
+
+impl Step<'_> {
+    fn field(&self) -> &Field {
+        &self.fields.fields[self.index]
+    }
+
+    ///Used for error variant names, and with a prefix for state names.
+    fn label(&self) -> Ident {
+        let field = self.field();
+        match (self.variant, self.fields.style) {
+            (Some(variant), _) => format_ident!("{variant}{}", field.camel),
+            (None, Style::Unnamed) => format_ident!("Field{}", field.camel),
+            (None, _) => ident(&field.camel),
+        }
+    }
+
+    fn state(&self) -> Ident {
+        format_ident!("Reading{}", self.label())
+    }
+
+    ///Fields already read when in this state.
+    fn prior(&self) -> &[Field] {
+        &self.fields.fields[..self.index]
+    }
+
+    ///Extras not yet handed to their field's deserialiser when in this state.
+    fn pending_extras(&self) -> impl Iterator<Item = &Field> {
+        self.fields.fields[self.index + 1..]
+            .iter()
+            .filter(|f| f.carries_extra)
+    }
+
+    fn human_name(&self, container: &Ident) -> String {
+        let field = &self.field().member;
+        let field = quote!(#field).to_string();
+        let owner = self
+            .variant
+            .map_or_else(|| container.to_string(), |v| format!("{container}::{v}"));
+        format!("field `{field}` of `{owner}`")
+    }
+
+    fn next(&self) -> Option<Self> {
+        (self.index + 1 < self.fields.fields.len()).then(|| Self {
+            fields: self.fields,
+            index: self.index + 1,
+            variant: self.variant,
+        })
+    }
+}
+
+fn steps<'a>(fields: &'a Fields, variant: Option<&'a Ident>) -> impl Iterator<Item = Step<'a>> {
+    (0..fields.fields.len()).map(move |index| Step {
+        fields,
+        index,
+        variant,
+    })
+}
+
+fn deser_ty(ty: &syn::Type) -> TokenStream {
+    let krate = crate_path();
+    quote!(<#ty as #krate::ser_glue::Deserable>::Deserer)
+}
+
+fn error_ty(ty: &syn::Type) -> TokenStream {
+    let krate = crate_path();
+    let deser = deser_ty(ty);
+    quote!(<#deser as #krate::ser_glue::DeserMachine>::Error)
+}
+
+///Builds the expression for entering the given step, assuming all prior fields, pending extras and `limits` are bound.
+fn enter(step: &Step) -> TokenStream {
+    let krate = crate_path();
+    let state = step.state();
+    let field = step.field();
+    let deser = deser_ty(&field.ty);
+
+    let start = if field.carries_extra {
+        let extra = field.extra_binding();
+        quote!(<#deser as #krate::ser_glue::DeserMachine>::new_with_starting_input(#extra, limits))
+    } else {
+        quote!(<#deser as #krate::ser_glue::DeserMachine>::new(limits))
+    };
+    let prior = step.prior().iter().map(|f| &f.binding);
+    let pending = step.pending_extras().map(Field::extra_binding);
+
+    quote!(Self::#state { #(#prior,)* #(#pending,)* deser: #start, limits })
+}
+
+#[allow(clippy::too_many_lines)] //it's mostly one big quote!
+pub fn expand(container: &Container) -> TokenStream {
+    let krate = crate_path();
+    let ident = &container.ident;
+    let vis = &container.vis;
+    let deserer = container.deserer_ident();
+    let error = container.error_path();
+
+    let map_error = |step: &Step| {
+        if container.error.is_some() {
+            let err_ty = error_ty(&step.field().ty);
+            quote!(<#error as ::core::convert::From<#err_ty>>::from(e))
+        } else {
+            let label = step.label();
+            quote!(#error::#label(e))
+        }
+    };
+
+    let all_steps: Vec<Step> = match &container.shape {
+        Shape::Struct(fields) => steps(fields, None).collect(),
+        Shape::Enum(variants) => variants
+            .iter()
+            .flat_map(|v| steps(&v.fields, Some(&v.ident)))
+            .collect(),
+    };
+
+    //the states for actually reading fields are shared between structs and enums
+    let mut states = vec![];
+    let mut wants_read_arms = vec![];
+    let mut finish_arms = vec![];
+    let mut process_arms = vec![];
+
+    for step in &all_steps {
+        let state = step.state();
+        let field = step.field();
+        let deser = deser_ty(&field.ty);
+        let prior: Vec<_> = step.prior().iter().map(|f| &f.binding).collect();
+        let prior_tys = step.prior().iter().map(|f| &f.ty);
+        let pending: Vec<_> = step.pending_extras().map(Field::extra_binding).collect();
+        let pending_tys = step.pending_extras().map(|f| {
+            let deser = deser_ty(&f.ty);
+            quote!(<#deser as #krate::ser_glue::DeserMachine>::ExtraInput)
+        });
+
+        states.push(quote! {
+            #state {
+                #(#prior: #prior_tys,)*
+                #(#pending: #pending_tys,)*
+                deser: #deser,
+                limits: #krate::ser_glue::DecodeLimits,
+            }
+        });
+        wants_read_arms.push(
+            quote!(Self::#state { deser, .. } => #krate::ser_glue::DeserMachine::wants_read(deser),),
+        );
+        finish_arms.push(quote! {
+            Self::#state { deser, .. } => #krate::ser_glue::DeserMachine::finish_bytes_for_writing(deser, n),
+        });
+
+        let binding = &field.binding;
+        let on_done = step.next().map_or_else(
+            || {
+                let path = step
+                    .variant
+                    .map_or_else(|| quote!(#ident), |variant| quote!(#ident::#variant));
+                let output = step.fields.construct(&path);
+                quote!(#krate::ser_glue::FsmResult::Done(#output))
+            },
+            |next| {
+                let enter = enter(&next);
+                quote!(#krate::ser_glue::FsmResult::Continue(#enter))
+            },
+        );
+        let map_error = map_error(step);
+
+        process_arms.push(quote! {
+            Self::#state { #(#prior,)* #(#pending,)* deser, limits } => match #krate::ser_glue::DeserMachine::process(deser) {
+                ::core::result::Result::Err(e) => ::core::result::Result::Err(#map_error),
+                ::core::result::Result::Ok(#krate::ser_glue::FsmResult::Continue(deser)) => {
+                    ::core::result::Result::Ok(#krate::ser_glue::FsmResult::Continue(
+                        Self::#state { #(#prior,)* #(#pending,)* deser, limits }
+                    ))
+                }
+                ::core::result::Result::Ok(#krate::ser_glue::FsmResult::Done(#binding)) => {
+                    ::core::result::Result::Ok(#on_done)
+                }
+            },
+        });
+    }
+
+    let extra_input;
+    let new;
+    let give_starting_input;
+    match &container.shape {
+        Shape::Struct(fields) => {
+            let entry = steps(fields, None).next().map_or_else(
+                || {
+                    let output = fields.construct(&quote!(#ident));
+                    states.push(quote!(Empty));
+                    wants_read_arms
+                        .push(quote!(Self::Empty => #krate::ser_glue::DesiredInput::ProcessMe,));
+                    finish_arms.push(quote!(Self::Empty => {}));
+                    process_arms.push(quote! {
+                        Self::Empty => ::core::result::Result::Ok(#krate::ser_glue::FsmResult::Done(#output)),
+                    });
+                    quote!(Self::Empty)
+                },
+                |first| enter(&first),
+            );
+
+            let extras: Vec<_> = fields.extra_fields().collect();
+            if extras.is_empty() {
+                extra_input = quote!(());
+                new = entry;
+                give_starting_input = quote! {
+                    fn give_starting_input(&mut self, (): Self::ExtraInput) {}
+                };
+            } else {
+                let tys: Vec<_> = extras
+                    .iter()
+                    .map(|f| {
+                        let deser = deser_ty(&f.ty);
+                        quote!(<#deser as #krate::ser_glue::DeserMachine>::ExtraInput)
+                    })
+                    .collect();
+                let bindings: Vec<_> = extras
+                    .iter()
+                    .map(|f| {
+                        let extra = f.extra_binding();
+                        quote!(#extra)
+                    })
+                    .collect();
+                let bindings = tuple_of(&bindings);
+
+                extra_input = tuple_of(&tys);
+                new = quote!(Self::AwaitingExtras(limits));
+                give_starting_input = quote! {
+                    fn give_starting_input(&mut self, extras: Self::ExtraInput) {
+                        if let Self::AwaitingExtras(limits) = *self {
+                            let #bindings = extras;
+                            *self = #entry;
+                        }
+                    }
+                };
+
+                states.push(quote!(AwaitingExtras(#krate::ser_glue::DecodeLimits)));
+                wants_read_arms.push(
+                    quote!(Self::AwaitingExtras(_) => #krate::ser_glue::DesiredInput::Extra,),
+                );
+                finish_arms.push(quote!(Self::AwaitingExtras(_) => {}));
+                process_arms.push(quote! {
+                    Self::AwaitingExtras(limits) => ::core::result::Result::Ok(#krate::ser_glue::FsmResult::Continue(Self::AwaitingExtras(limits))),
+                });
+            }
+        }
+        Shape::Enum(variants) => {
+            extra_input = quote!(());
+            new = quote!(Self::Start(0, limits));
+            give_starting_input = quote! {
+                fn give_starting_input(&mut self, (): Self::ExtraInput) {}
+            };
+
+            let consts: Vec<_> = variants
+                .iter()
+                .map(|v| format_ident!("DISCRIMINANT_{}", camel_to_shouty(&v.ident.to_string())))
+                .collect();
+            let const_defs = variants.iter().zip(&consts).map(|(v, name)| {
+                let discriminant = &v.discriminant;
+                quote!(const #name: u8 = #discriminant;)
+            });
+            let discriminant_arms = variants.iter().zip(&consts).map(|(v, name)| {
+                let result = steps(&v.fields, Some(&v.ident)).next().map_or_else(
+                    || {
+                        let variant = &v.ident;
+                        let output = v.fields.construct(&quote!(#ident::#variant));
+                        quote!(#krate::ser_glue::FsmResult::Done(#output))
+                    },
+                    |first| {
+                        let enter = enter(&first);
+                        quote!(#krate::ser_glue::FsmResult::Continue(#enter))
+                    },
+                );
+                quote!(#name => ::core::result::Result::Ok(#result),)
+            });
+            let invalid = if container.error.is_some() {
+                quote!(<#error as ::core::convert::From<#krate::ser_glue::InvalidDiscriminant>>::from(#krate::ser_glue::InvalidDiscriminant(n)))
+            } else {
+                quote!(#error::InvalidDiscriminant(n))
+            };
+
+            states.push(quote!(Start(u8, #krate::ser_glue::DecodeLimits)));
+            states.push(quote!(GotStart(u8, #krate::ser_glue::DecodeLimits)));
+            wants_read_arms.push(quote! {
+                Self::Start(space, _) => #krate::ser_glue::DesiredInput::Byte(space),
+                Self::GotStart(..) => #krate::ser_glue::DesiredInput::ProcessMe,
+            });
+            finish_arms.push(quote! {
+                Self::Start(start, limits) => {
+                    if n == 1 {
+                        *self = Self::GotStart(*start, *limits);
+                    }
+                }
+                Self::GotStart(..) => {}
+            });
+            process_arms.push(quote! {
+                Self::Start(n, limits) => ::core::result::Result::Ok(#krate::ser_glue::FsmResult::Continue(Self::Start(n, limits))),
+                #[allow(unused_variables)] //not used if no variants have fields
+                Self::GotStart(n, limits) => {
+                    #(#const_defs)*
+
+                    match n {
+                        #(#discriminant_arms)*
+                        n => ::core::result::Result::Err(#invalid),
+                    }
+                }
+            });
+        }
+    }
+
+    let error_def = if container.error.is_some() {
+        quote!()
+    } else {
+        error_definition(container, &all_steps)
+    };
+
+    quote! {
+        #[derive(Debug)]
+        #[allow(clippy::enum_variant_names)] //every state is `Reading` something
+        #vis enum #deserer {
+            #(#states,)*
+        }
+
+        #error_def
+
+        #[automatically_derived]
+        impl #krate::ser_glue::Deserable for #ident {
+            type Deserer = #deserer;
+        }
+
+        #[automatically_derived]
+        impl #krate::ser_glue::DeserMachine for #deserer {
+            type ExtraInput = #extra_input;
+            type Output = #ident;
+            type Error = #error;
+
+            #[allow(unused_variables)] //not used by empty structs
+            fn new(limits: #krate::ser_glue::DecodeLimits) -> Self {
+                #new
+            }
+
+            fn wants_read(&mut self) -> #krate::ser_glue::DesiredInput<'_> {
+                match self {
+                    #(#wants_read_arms)*
+                }
+            }
+
+            #give_starting_input
+
+            #[allow(unused_variables)] //not used if there are no fields
+            fn finish_bytes_for_writing(&mut self, n: usize) {
+                match self {
+                    #(#finish_arms)*
+                }
+            }
+
+            fn process(self) -> ::core::result::Result<#krate::ser_glue::FsmResult<Self, Self::Output>, Self::Error> {
+                match self {
+                    #(#process_arms)*
+                }
+            }
+        }
+    }
+}
+
+fn error_definition(container: &Container, steps: &[Step]) -> TokenStream {
+    let ident = &container.ident;
+    let vis = &container.vis;
+    let error = format_ident!("{ident}ReadError");
+
+    let mut variants = vec![];
+    let mut display_arms = vec![];
+    let mut source_arms = vec![];
+
+    for step in steps {
+        let label = step.label();
+        let err_ty = error_ty(&step.field().ty);
+        let message = format!("Error deserialising {}: {{}}", step.human_name(ident));
+
+        variants.push(quote!(#label(#err_ty)));
+        display_arms.push(quote!(Self::#label(e) => write!(f, #message, e),));
+        source_arms.push(quote!(Self::#label(e) => ::core::option::Option::Some(e),));
+    }
+
+    if matches!(container.shape, Shape::Enum(_)) {
+        let message = format!("Invalid discriminant for `{ident}`: {{}}");
+        variants.push(quote!(InvalidDiscriminant(u8)));
+        display_arms.push(quote!(Self::InvalidDiscriminant(n) => write!(f, #message, n),));
+        source_arms.push(quote!(Self::InvalidDiscriminant(_) => ::core::option::Option::None,));
+    }
+
+    //empty structs can't fail, but still need an error type
+    let (display_body, source_body) = if display_arms.is_empty() {
+        (quote!(match *self {}), quote!(match *self {}))
+    } else {
+        (
+            quote!(match self { #(#display_arms)* }),
+            quote!(match self { #(#source_arms)* }),
+        )
+    };
+
+    quote! {
+        #[derive(Debug)]
+        #vis enum #error {
+            #(#variants,)*
+        }
+
+        impl ::core::fmt::Display for #error {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                #display_body
+            }
+        }
+
+        impl ::std::error::Error for #error {
+            fn source(&self) -> ::core::option::Option<&(dyn ::std::error::Error + 'static)> {
+                #source_body
+            }
+        }
+    }
+}
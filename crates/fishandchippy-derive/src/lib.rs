@@ -0,0 +1,34 @@
+#![warn(clippy::all, clippy::nursery, clippy::pedantic)]
+//! Derive macros for the `Serable` and `Deserable` traits in `fishandchippy::ser_glue`.
+//!
+//! Both derives understand a `#[ser_glue(...)]` helper attribute:
+//! - on the type: `error = Path` to re-use an existing error type instead of generating one (it must implement `From` for every field's error and for `ser_glue::InvalidDiscriminant`), and `deserer = Ident` to rename the generated state machine.
+//! - on enum variants: `discriminant = expr` to pick the `u8` written before the variant's fields. Defaults to the variant's index.
+//! - on fields: `extra` to pass that field's extra input/output through to the container. This is done automatically for `Integer` and signed integer fields.
+
+mod deser;
+mod model;
+mod ser;
+
+use proc_macro::TokenStream;
+use syn::{DeriveInput, parse_macro_input};
+
+#[proc_macro_derive(Serable, attributes(ser_glue))]
+pub fn derive_serable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    model::Container::parse(&input)
+        .map_or_else(syn::Error::into_compile_error, |container| {
+            ser::expand(&container)
+        })
+        .into()
+}
+
+#[proc_macro_derive(Deserable, attributes(ser_glue))]
+pub fn derive_deserable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    model::Container::parse(&input)
+        .map_or_else(syn::Error::into_compile_error, |container| {
+            deser::expand(&container)
+        })
+        .into()
+}
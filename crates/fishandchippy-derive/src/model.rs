@@ -0,0 +1,287 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{Attribute, Data, DeriveInput, Expr, Ident, Member, Path, Type, Visibility};
+
+///Types whose extra input/output gets passed through to the container without needing `#[ser_glue(extra)]`.
+const SIGNED_STATE_TYPES: &[&str] = &["Integer", "i8", "i16", "i32", "i64", "i128", "isize"];
+
+pub struct Container {
+    pub ident: Ident,
+    pub vis: Visibility,
+    pub error: Option<Path>,
+    pub deserer: Option<Ident>,
+    pub shape: Shape,
+}
+
+pub enum Shape {
+    Struct(Fields),
+    Enum(Vec<Variant>),
+}
+
+pub struct Variant {
+    pub ident: Ident,
+    pub discriminant: Expr,
+    pub fields: Fields,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum Style {
+    Named,
+    Unnamed,
+    Unit,
+}
+
+pub struct Fields {
+    pub style: Style,
+    pub fields: Vec<Field>,
+}
+
+pub struct Field {
+    ///How to get at this field from `self`
+    pub member: Member,
+    ///The name used for local bindings and state machine fields
+    pub binding: Ident,
+    ///`UpperCamelCase` version of the name, used in state and error variant names
+    pub camel: String,
+    pub ty: Type,
+    pub carries_extra: bool,
+}
+
+impl Container {
+    pub fn parse(input: &DeriveInput) -> syn::Result<Self> {
+        if !input.generics.params.is_empty() {
+            return Err(syn::Error::new_spanned(
+                &input.generics,
+                "ser_glue derives don't support generic types",
+            ));
+        }
+
+        let mut error = None;
+        let mut deserer = None;
+        for attr in ser_glue_attrs(&input.attrs) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("error") {
+                    error = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("deserer") {
+                    deserer = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `error` or `deserer`"))
+                }
+            })?;
+        }
+
+        let shape = match &input.data {
+            Data::Struct(data) => Shape::Struct(Fields::parse(&data.fields)?),
+            Data::Enum(data) => {
+                let mut variants = Vec::with_capacity(data.variants.len());
+                for (i, variant) in data.variants.iter().enumerate() {
+                    let Ok(i) = u8::try_from(i) else {
+                        return Err(syn::Error::new_spanned(
+                            variant,
+                            "ser_glue enums can have at most 256 variants",
+                        ));
+                    };
+
+                    let mut discriminant = None;
+                    for attr in ser_glue_attrs(&variant.attrs) {
+                        attr.parse_nested_meta(|meta| {
+                            if meta.path.is_ident("discriminant") {
+                                discriminant = Some(meta.value()?.parse()?);
+                                Ok(())
+                            } else {
+                                Err(meta.error("expected `discriminant`"))
+                            }
+                        })?;
+                    }
+
+                    let fields = Fields::parse(&variant.fields)?;
+                    if let Some(field) = fields.fields.iter().find(|f| f.carries_extra) {
+                        return Err(syn::Error::new_spanned(
+                            &field.ty,
+                            "enum variants can't pass extras through - use an unsigned integer or a type without extras",
+                        ));
+                    }
+
+                    variants.push(Variant {
+                        ident: variant.ident.clone(),
+                        discriminant: discriminant.unwrap_or_else(|| syn::parse_quote!(#i)),
+                        fields,
+                    });
+                }
+                Shape::Enum(variants)
+            }
+            Data::Union(_) => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "ser_glue derives don't support unions",
+                ));
+            }
+        };
+
+        Ok(Self {
+            ident: input.ident.clone(),
+            vis: input.vis.clone(),
+            error,
+            deserer,
+            shape,
+        })
+    }
+
+    pub fn deserer_ident(&self) -> Ident {
+        self.deserer
+            .clone()
+            .unwrap_or_else(|| format_ident!("{}Deserialiser", self.ident))
+    }
+
+    pub fn error_path(&self) -> TokenStream {
+        self.error.as_ref().map_or_else(
+            || {
+                let ident = format_ident!("{}ReadError", self.ident);
+                quote!(#ident)
+            },
+            |path| quote!(#path),
+        )
+    }
+}
+
+impl Fields {
+    fn parse(fields: &syn::Fields) -> syn::Result<Self> {
+        let style = match fields {
+            syn::Fields::Named(_) => Style::Named,
+            syn::Fields::Unnamed(_) => Style::Unnamed,
+            syn::Fields::Unit => Style::Unit,
+        };
+
+        let mut parsed = Vec::with_capacity(fields.len());
+        for (i, field) in fields.iter().enumerate() {
+            let mut carries_extra = is_signed_state_type(&field.ty);
+            for attr in ser_glue_attrs(&field.attrs) {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("extra") {
+                        carries_extra = true;
+                        Ok(())
+                    } else {
+                        Err(meta.error("expected `extra`"))
+                    }
+                })?;
+            }
+
+            let (member, binding, camel) = match &field.ident {
+                Some(ident) => {
+                    let name = ident.to_string();
+                    let name = name.strip_prefix("r#").unwrap_or(&name);
+                    if name == "deser" || name == "limits" || name.starts_with("extra_") {
+                        return Err(syn::Error::new_spanned(
+                            ident,
+                            "`deser`, `limits` and `extra_*` are reserved field names for ser_glue derives",
+                        ));
+                    }
+                    (
+                        Member::Named(ident.clone()),
+                        ident.clone(),
+                        snake_to_camel(name),
+                    )
+                }
+                None => (
+                    Member::Unnamed(i.into()),
+                    format_ident!("f{i}"),
+                    i.to_string(),
+                ),
+            };
+
+            parsed.push(Field {
+                member,
+                binding,
+                camel,
+                ty: field.ty.clone(),
+                carries_extra,
+            });
+        }
+
+        Ok(Self {
+            style,
+            fields: parsed,
+        })
+    }
+
+    ///Builds `Path { a, b }`, `Path(a, b)` or `Path` out of the field bindings.
+    pub fn construct(&self, path: &TokenStream) -> TokenStream {
+        let bindings = self.fields.iter().map(|f| &f.binding);
+        match self.style {
+            Style::Named => quote!(#path { #(#bindings),* }),
+            Style::Unnamed => quote!(#path ( #(#bindings),* )),
+            Style::Unit => quote!(#path),
+        }
+    }
+
+    pub fn extra_fields(&self) -> impl Iterator<Item = &Field> {
+        self.fields.iter().filter(|f| f.carries_extra)
+    }
+}
+
+impl Field {
+    pub fn extra_binding(&self) -> Ident {
+        format_ident!("extra_{}", self.binding)
+    }
+}
+
+///Combines a list of types into `()`, the single type, or a tuple.
+pub fn tuple_of(types: &[TokenStream]) -> TokenStream {
+    match types {
+        [single] => single.clone(),
+        many => quote!((#(#many),*)),
+    }
+}
+
+pub fn crate_path() -> TokenStream {
+    quote!(::fishandchippy)
+}
+
+fn ser_glue_attrs(attrs: &[Attribute]) -> impl Iterator<Item = &Attribute> {
+    attrs.iter().filter(|a| a.path().is_ident("ser_glue"))
+}
+
+fn is_signed_state_type(ty: &Type) -> bool {
+    let Type::Path(path) = ty else {
+        return false;
+    };
+    path.qself.is_none()
+        && path
+            .path
+            .segments
+            .last()
+            .is_some_and(|seg| SIGNED_STATE_TYPES.iter().any(|name| seg.ident == name))
+}
+
+fn snake_to_camel(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut upper_next = true;
+    for c in s.chars() {
+        if c == '_' {
+            upper_next = true;
+        } else if upper_next {
+            out.extend(c.to_uppercase());
+            upper_next = false;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+pub fn camel_to_shouty(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 4);
+    for (i, c) in s.chars().enumerate() {
+        if c.is_uppercase() && i != 0 {
+            out.push('_');
+        }
+        out.extend(c.to_uppercase());
+    }
+    out
+}
+
+pub fn ident(s: &str) -> Ident {
+    Ident::new(s, Span::call_site())
+}
@@ -0,0 +1,104 @@
+use crate::model::{Container, Fields, Shape, crate_path, tuple_of};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+pub fn expand(container: &Container) -> TokenStream {
+    let krate = crate_path();
+    let ident = &container.ident;
+
+    let (extra_output, body) = match &container.shape {
+        Shape::Struct(fields) => {
+            let extra_types: Vec<_> = fields
+                .extra_fields()
+                .map(|f| {
+                    let ty = &f.ty;
+                    quote!(<#ty as #krate::ser_glue::Serable>::ExtraOutput)
+                })
+                .collect();
+
+            let sers = ser_fields(fields, true);
+            let extras: Vec<_> = fields
+                .extra_fields()
+                .map(|f| {
+                    let extra = f.extra_binding();
+                    quote!(#extra)
+                })
+                .collect();
+            let extras = if extras.is_empty() {
+                quote!()
+            } else {
+                tuple_of(&extras)
+            };
+
+            (
+                tuple_of(&extra_types),
+                quote! {
+                    #sers
+                    #extras
+                },
+            )
+        }
+        Shape::Enum(variants) => {
+            let arms = variants.iter().map(|variant| {
+                let pattern = variant.fields.construct(&{
+                    let variant = &variant.ident;
+                    quote!(Self::#variant)
+                });
+                let discriminant = &variant.discriminant;
+                let sers = ser_fields(&variant.fields, false);
+
+                quote! {
+                    #pattern => {
+                        into.push(#discriminant);
+                        #sers
+                    }
+                }
+            });
+
+            (
+                quote!(()),
+                quote! {
+                    match self {
+                        #(#arms)*
+                    }
+                },
+            )
+        }
+    };
+
+    quote! {
+        #[automatically_derived]
+        impl #krate::ser_glue::Serable for #ident {
+            type ExtraOutput = #extra_output;
+
+            fn ser_into(&self, into: &mut ::std::vec::Vec<u8>) -> Self::ExtraOutput {
+                #body
+            }
+        }
+    }
+}
+
+///Serialises each field in order, either reading them from `self` or from bindings made by matching on a variant.
+fn ser_fields(fields: &Fields, from_self: bool) -> TokenStream {
+    let krate = crate_path();
+
+    let sers = fields.fields.iter().map(|field| {
+        let ty = &field.ty;
+        let value = if from_self {
+            let member = &field.member;
+            quote!(&self.#member)
+        } else {
+            let binding = &field.binding;
+            quote!(#binding)
+        };
+
+        if field.carries_extra {
+            let extra = field.extra_binding();
+            quote!(let #extra = <#ty as #krate::ser_glue::Serable>::ser_into(#value, into);)
+        } else {
+            quote!(let () = <#ty as #krate::ser_glue::Serable>::ser_into(#value, into);)
+        }
+    });
+
+    quote!(#(#sers)*)
+}
@@ -1,33 +1,95 @@
+use crate::events::error_code::ErrorCodeReadError;
+use crate::game_types::cards::CardReadError;
+use crate::game_types::hand_eval::ShownHandReadError;
 use crate::game_types::player::PlayerReadError;
 use crate::game_types::pot::PotReadError;
+use crate::game_types::table::{TableIdReadError, TableSummaryReadError};
 use crate::integer::IntegerReadError;
 use crate::ser_glue::list::BasicListReadError;
+use crate::ser_glue::option::OptionReadError;
 use crate::ser_glue::string::StringReadError;
 use crate::ser_glue::tuple::TupleReadError;
+use crate::ser_glue::{Deserable, InvalidDiscriminant, Serable};
 use std::convert::Infallible;
 use std::fmt::{Display, Formatter};
 use std::string::FromUtf8Error;
+use uuid::Uuid;
 
 pub mod client;
+pub mod error_code;
 pub mod server;
 
+///Bump this whenever the wire format of any event changes, so old clients get turned away instead of misreading things.
+pub const PROTOCOL_VERSION: u32 = 13;
+
+//NB: HELLO and ADMIN_MSG must never change, as they're how clients find out they're incompatible
+const HELLO: u8 = 0;
 const TEXT_MESSAGE: u8 = 1;
 const ADMIN_MSG: u8 = 2;
 const INTRODUCTION: u8 = 3;
+const ERROR: u8 = 4;
+const RESUME: u8 = 5;
 const ADD_TO_POT: u8 = 10;
 const GET_POT: u8 = 11;
+const CLOSE_BETTING_ROUND: u8 = 12;
+const AWARD_POT: u8 = 13;
+const SPLIT_POT: u8 = 14;
+const BUY_IN: u8 = 15;
+const REBUY: u8 = 16;
 const GET_ALL_PLAYERS: u8 = 20;
 const GET_SPECIFIC_PLAYER: u8 = 21;
+const LIST_TABLES: u8 = 30;
+const CREATE_TABLE: u8 = 31;
+const JOIN_TABLE: u8 = 32;
+const LEAVE_TABLE: u8 = 33;
+const TABLE_UPDATED: u8 = 34;
+const START_HAND: u8 = 40;
+const HOLE_CARDS: u8 = 41;
+const BOARD: u8 = 42;
+const SHOWDOWN: u8 = 43;
+const TO_ACT: u8 = 44;
+const CHECK: u8 = 45;
+const CALL: u8 = 46;
+const RAISE: u8 = 47;
+const FOLD: u8 = 48;
+const ALL_IN: u8 = 49;
+
+///Picked by the client and echoed back in any replies, so they can be told apart from broadcasts.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serable, Deserable)]
+pub struct RequestId(pub u32);
+
+///Handed out when a client is introduced, and used to get back to the same player after a disconnect - see [`EventToServer::Resume`](server::EventToServer::Resume).
+///
+/// Anyone with this can act as the player, so it's random and shouldn't be shown to anyone.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serable, Deserable)]
+pub struct ResumeToken(pub Uuid);
+
+impl ResumeToken {
+    #[must_use]
+    pub fn new_random() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
 
 #[derive(Debug)]
 pub enum EventReadError {
     InvalidString(FromUtf8Error),
     Integer(IntegerReadError),
+    OptionalInteger(OptionReadError<IntegerReadError>),
     InvalidKind(u8),
     StringRead(StringReadError),
     Pot(PotReadError),
     ListOfPlayers(BasicListReadError<TupleReadError<Infallible, PlayerReadError>>),
+    ListOfUuids(BasicListReadError<Infallible>),
     Player(PlayerReadError),
+    ErrorCode(ErrorCodeReadError),
+    RequestId(OptionReadError<RequestIdReadError>),
+    ResumeToken(ResumeTokenReadError),
+    TableId(TableIdReadError),
+    TableSummary(TableSummaryReadError),
+    ListOfTables(BasicListReadError<TableSummaryReadError>),
+    ListOfCards(BasicListReadError<CardReadError>),
+    ListOfShownHands(BasicListReadError<ShownHandReadError>),
 }
 
 impl From<FromUtf8Error> for EventReadError {
@@ -40,6 +102,11 @@ impl From<IntegerReadError> for EventReadError {
         Self::Integer(value)
     }
 }
+impl From<OptionReadError<IntegerReadError>> for EventReadError {
+    fn from(value: OptionReadError<IntegerReadError>) -> Self {
+        Self::OptionalInteger(value)
+    }
+}
 impl From<StringReadError> for EventReadError {
     fn from(value: StringReadError) -> Self {
         Self::StringRead(value)
@@ -55,22 +122,87 @@ impl From<BasicListReadError<TupleReadError<Infallible, PlayerReadError>>> for E
         Self::ListOfPlayers(value)
     }
 }
+impl From<BasicListReadError<Infallible>> for EventReadError {
+    fn from(value: BasicListReadError<Infallible>) -> Self {
+        Self::ListOfUuids(value)
+    }
+}
 impl From<PlayerReadError> for EventReadError {
     fn from(value: PlayerReadError) -> Self {
         Self::Player(value)
     }
 }
+impl From<ErrorCodeReadError> for EventReadError {
+    fn from(value: ErrorCodeReadError) -> Self {
+        Self::ErrorCode(value)
+    }
+}
+impl From<OptionReadError<RequestIdReadError>> for EventReadError {
+    fn from(value: OptionReadError<RequestIdReadError>) -> Self {
+        Self::RequestId(value)
+    }
+}
+impl From<ResumeTokenReadError> for EventReadError {
+    fn from(value: ResumeTokenReadError) -> Self {
+        Self::ResumeToken(value)
+    }
+}
+impl From<TableIdReadError> for EventReadError {
+    fn from(value: TableIdReadError) -> Self {
+        Self::TableId(value)
+    }
+}
+impl From<TableSummaryReadError> for EventReadError {
+    fn from(value: TableSummaryReadError) -> Self {
+        Self::TableSummary(value)
+    }
+}
+impl From<BasicListReadError<TableSummaryReadError>> for EventReadError {
+    fn from(value: BasicListReadError<TableSummaryReadError>) -> Self {
+        Self::ListOfTables(value)
+    }
+}
+impl From<BasicListReadError<CardReadError>> for EventReadError {
+    fn from(value: BasicListReadError<CardReadError>) -> Self {
+        Self::ListOfCards(value)
+    }
+}
+impl From<BasicListReadError<ShownHandReadError>> for EventReadError {
+    fn from(value: BasicListReadError<ShownHandReadError>) -> Self {
+        Self::ListOfShownHands(value)
+    }
+}
+impl From<InvalidDiscriminant> for EventReadError {
+    fn from(InvalidDiscriminant(kind): InvalidDiscriminant) -> Self {
+        Self::InvalidKind(kind)
+    }
+}
+impl From<Infallible> for EventReadError {
+    fn from(value: Infallible) -> Self {
+        match value {}
+    }
+}
 
 impl Display for EventReadError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::InvalidString(str) => write!(f, "Error reading UTF-8: {str}"),
             Self::Integer(int) => write!(f, "Error parsing integer value: {int}"),
+            Self::OptionalInteger(int) => write!(f, "Error parsing optional integer value: {int}"),
             Self::InvalidKind(kind) => write!(f, "Invalid event type provided: {kind}"),
             Self::StringRead(str) => write!(f, "Error reading basic string: {str}"),
             Self::Pot(pot) => write!(f, "Error reading pot: {pot}"),
             Self::ListOfPlayers(players) => write!(f, "Error reading list of players: {players}"),
+            Self::ListOfUuids(uuids) => write!(f, "Error reading list of UUIDs: {uuids}"),
             Self::Player(player) => write!(f, "Error reading specific player: {player}"),
+            Self::ErrorCode(code) => write!(f, "Error reading error code: {code}"),
+            Self::RequestId(id) => write!(f, "Error reading request ID: {id}"),
+            Self::ResumeToken(token) => write!(f, "Error reading resume token: {token}"),
+            Self::TableId(id) => write!(f, "Error reading table ID: {id}"),
+            Self::TableSummary(table) => write!(f, "Error reading table summary: {table}"),
+            Self::ListOfTables(tables) => write!(f, "Error reading list of tables: {tables}"),
+            Self::ListOfCards(cards) => write!(f, "Error reading list of cards: {cards}"),
+            Self::ListOfShownHands(hands) => write!(f, "Error reading shown hands: {hands}"),
         }
     }
 }
@@ -80,10 +212,20 @@ impl std::error::Error for EventReadError {
         match self {
             Self::InvalidString(str) => Some(str),
             Self::Integer(int) => Some(int),
+            Self::OptionalInteger(int) => Some(int),
             Self::StringRead(str) => Some(str),
             Self::Pot(pot) => Some(pot),
             Self::ListOfPlayers(lop) => Some(lop),
+            Self::ListOfUuids(lou) => Some(lou),
             Self::Player(player) => Some(player),
+            Self::ErrorCode(code) => Some(code),
+            Self::RequestId(id) => Some(id),
+            Self::ResumeToken(token) => Some(token),
+            Self::TableId(id) => Some(id),
+            Self::TableSummary(table) => Some(table),
+            Self::ListOfTables(lot) => Some(lot),
+            Self::ListOfCards(loc) => Some(loc),
+            Self::ListOfShownHands(losh) => Some(losh),
             Self::InvalidKind(_) => None,
         }
     }
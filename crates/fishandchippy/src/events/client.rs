@@ -1,193 +1,120 @@
+use crate::events::error_code::ErrorCode;
 use crate::events::{
-    ADMIN_MSG, EventReadError, GET_ALL_PLAYERS, GET_POT, GET_SPECIFIC_PLAYER, INTRODUCTION,
-    TEXT_MESSAGE,
+    ADMIN_MSG, BOARD, ERROR, EventReadError, GET_ALL_PLAYERS, GET_POT, GET_SPECIFIC_PLAYER, HELLO,
+    HOLE_CARDS, INTRODUCTION, JOIN_TABLE, LEAVE_TABLE, LIST_TABLES, RequestId, ResumeToken,
+    SHOWDOWN, START_HAND, TABLE_UPDATED, TEXT_MESSAGE, TO_ACT,
 };
-use crate::game_types::player::{Player, PlayerDeserialiser};
-use crate::game_types::pot::{Pot, PotDeserialiser};
-use crate::ser_glue::map::{BasicMapDeserialiser, BasicMapSer};
-use crate::ser_glue::string::StringDeserialiser;
-use crate::ser_glue::uuid::UuidDeserialiser;
-use crate::ser_glue::{DeserMachine, Deserable, DesiredInput, FsmResult, Serable};
+use crate::game_types::cards::Card;
+use crate::game_types::hand_eval::ShownHand;
+use crate::game_types::player::Player;
+use crate::game_types::pot::Pot;
+use crate::game_types::table::{TableId, TableSummary};
+use crate::ser_glue::{Deserable, Serable};
 use std::collections::HashMap;
 use uuid::Uuid;
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq, Serable, Deserable)]
+#[ser_glue(error = EventReadError, deserer = ClientEventDeserer)]
 pub enum EventToClient {
-    TxtSent(Uuid, String),
+    ///Reply to a compatible [`EventToServer::Hello`](crate::events::server::EventToServer::Hello). Incompatible clients get an [`EventToClient::AdminMsg`] explaining why, and are then disconnected.
+    #[ser_glue(discriminant = HELLO)]
+    Welcome { protocol_version: u32 },
+    #[ser_glue(discriminant = TEXT_MESSAGE)]
+    TxtSent(TableId, Uuid, String),
+    #[ser_glue(discriminant = ADMIN_MSG)]
     AdminMsg(String),
-    Introduced(Uuid),
-    Pot(Pot),
-    AllPlayers(HashMap<Uuid, Player>),
-    SpecificPlayer(Uuid, Player),
+    ///Sent after an [`EventToServer::Introduction`](crate::events::server::EventToServer::Introduction) or [`EventToServer::Resume`](crate::events::server::EventToServer::Resume). The token is new each time, and any old one stops working.
+    #[ser_glue(discriminant = INTRODUCTION)]
+    Introduced {
+        uuid: Uuid,
+        resume_token: ResumeToken,
+    },
+    ///The server turned down a request from this client.
+    #[ser_glue(discriminant = ERROR)]
+    Error {
+        code: ErrorCode,
+        message: String,
+        request_id: Option<RequestId>,
+    },
+    #[ser_glue(discriminant = GET_POT)]
+    Pot(TableId, Pot, Option<RequestId>),
+    #[ser_glue(discriminant = GET_ALL_PLAYERS)]
+    AllPlayers(TableId, HashMap<Uuid, Player>, Option<RequestId>),
+    #[ser_glue(discriminant = GET_SPECIFIC_PLAYER)]
+    SpecificPlayer(TableId, Uuid, Player, Option<RequestId>),
+    #[ser_glue(discriminant = LIST_TABLES)]
+    TableList(Vec<TableSummary>, Option<RequestId>),
+    ///Sent to the client that joined - everyone else at the table gets an [`EventToClient::AdminMsg`].
+    #[ser_glue(discriminant = JOIN_TABLE)]
+    JoinedTable(TableSummary),
+    #[ser_glue(discriminant = LEAVE_TABLE)]
+    LeftTable(TableId),
+    ///Pushed to everyone in the lobby whenever a table is made, or its summary changes.
+    #[ser_glue(discriminant = TABLE_UPDATED)]
+    TableUpdated(TableSummary),
+    ///A new hand has been dealt to these players. Each of them then gets their own [`EventToClient::HoleCards`].
+    #[ser_glue(discriminant = START_HAND)]
+    HandStarted(TableId, Vec<Uuid>),
+    ///The two cards only this player can see.
+    #[ser_glue(discriminant = HOLE_CARDS)]
+    HoleCards(TableId, Vec<Card>),
+    ///Everything on the board so far, sent whenever more is dealt.
+    #[ser_glue(discriminant = BOARD)]
+    Board(TableId, Vec<Card>),
+    ///The hand is over, and everyone still in it turns their cards over. Whoever wins gets sent their new balance.
+    #[ser_glue(discriminant = SHOWDOWN)]
+    Showdown(TableId, Vec<ShownHand>),
+    ///It's `player`'s turn. They can check if they've already put in `current_bet` this betting round, and can raise to anything from `min_raise_to` up.
+    ///
+    /// `min_raise_to` is `None` if they can only call or fold, because the only raise since they last acted was an all in too small to reopen the betting. Going all in is then only allowed if it's no more than a call.
+    #[ser_glue(discriminant = TO_ACT)]
+    ToAct {
+        table: TableId,
+        player: Uuid,
+        current_bet: u32,
+        min_raise_to: Option<u32>,
+    },
 }
 
-impl Serable for EventToClient {
-    type ExtraOutput = ();
-
-    fn ser_into(&self, into: &mut Vec<u8>) -> Self::ExtraOutput {
-        match self {
-            Self::TxtSent(uuid, content) => {
-                into.push(TEXT_MESSAGE);
-                (*uuid, content.as_str()).ser_into(into);
-            }
-            Self::AdminMsg(content) => {
-                into.push(ADMIN_MSG);
-                content.ser_into(into);
-            }
-            Self::Introduced(uuid) => {
-                into.push(INTRODUCTION);
-                uuid.ser_into(into);
-            }
-            Self::Pot(pot) => {
-                into.push(GET_POT);
-                pot.ser_into(into);
-            }
-            Self::AllPlayers(players) => {
-                into.push(GET_ALL_PLAYERS);
-                BasicMapSer(players).ser_into(into);
-            }
-            Self::SpecificPlayer(uuid, player) => {
-                into.push(GET_SPECIFIC_PLAYER);
-                uuid.ser_into(into);
-                player.ser_into(into);
-            }
-        }
-    }
-}
-
-impl Deserable for EventToClient {
-    type Deserer = ClientEventDeserer;
-}
-
-#[derive(Debug)]
-pub enum ClientEventDeserer {
-    Start(u8),
-    GotStart(u8),
-    DeseringTextUuid(UuidDeserialiser),
-    DeseringTextAfterUuid(Uuid, StringDeserialiser),
-    DeseringIntro(UuidDeserialiser),
-    DeseringAdminMsg(StringDeserialiser),
-    DeseringPot(PotDeserialiser),
-    DeseringAllPlayers(BasicMapDeserialiser<UuidDeserialiser, PlayerDeserialiser>),
-    DeseringPlayerUuid(UuidDeserialiser),
-    DeseringPlayerAfterUuid(Uuid, PlayerDeserialiser),
-}
-
-impl DeserMachine for ClientEventDeserer {
-    type ExtraInput = ();
-    type Output = EventToClient;
-    type Error = EventReadError;
-
-    fn new() -> Self {
-        Self::Start(0)
-    }
-
-    fn wants_read(&mut self) -> DesiredInput<'_> {
-        match self {
-            Self::Start(space) => DesiredInput::Byte(space),
-            Self::GotStart(_start) => DesiredInput::ProcessMe,
-            Self::DeseringIntro(deser)
-            | Self::DeseringTextUuid(deser)
-            | Self::DeseringPlayerUuid(deser) => deser.wants_read(),
-            Self::DeseringTextAfterUuid(_, deser) | Self::DeseringAdminMsg(deser) => {
-                deser.wants_read()
-            }
-            Self::DeseringPlayerAfterUuid(_, deser) => deser.wants_read(),
-            Self::DeseringPot(deser) => deser.wants_read(),
-            Self::DeseringAllPlayers(deser) => deser.wants_read(),
-        }
-    }
-
-    fn give_starting_input(&mut self, (): Self::ExtraInput) {}
-
-    fn finish_bytes_for_writing(&mut self, n: usize) {
-        match self {
-            Self::Start(start) => {
-                if n == 1 {
-                    *self = Self::GotStart(*start);
-                }
-            }
-            Self::GotStart(_) => {}
-            Self::DeseringIntro(deser)
-            | Self::DeseringTextUuid(deser)
-            | Self::DeseringPlayerUuid(deser) => deser.finish_bytes_for_writing(n),
-            Self::DeseringTextAfterUuid(_, deser) | Self::DeseringAdminMsg(deser) => {
-                deser.finish_bytes_for_writing(n)
-            }
-            Self::DeseringPlayerAfterUuid(_, deser) => deser.finish_bytes_for_writing(n),
-            Self::DeseringPot(deser) => deser.finish_bytes_for_writing(n),
-            Self::DeseringAllPlayers(deser) => deser.finish_bytes_for_writing(n),
-        }
-    }
-
-    fn process(self) -> Result<FsmResult<Self, Self::Output>, Self::Error> {
+impl EventToClient {
+    ///The ID of the request this is a reply to, if any. Broadcasts never have one.
+    #[must_use]
+    pub const fn request_id(&self) -> Option<RequestId> {
         match self {
-            Self::Start(n) => Ok(FsmResult::Continue(Self::Start(n))),
-            Self::GotStart(n) => match n {
-                TEXT_MESSAGE => Ok(FsmResult::Continue(Self::DeseringTextUuid(Uuid::deser()))),
-                INTRODUCTION => Ok(FsmResult::Continue(Self::DeseringIntro(Uuid::deser()))),
-                ADMIN_MSG => Ok(FsmResult::Continue(Self::DeseringAdminMsg(String::deser()))),
-                GET_POT => Ok(FsmResult::Continue(Self::DeseringPot(Pot::deser()))),
-                GET_ALL_PLAYERS => Ok(FsmResult::Continue(Self::DeseringAllPlayers(
-                    BasicMapDeserialiser::new(),
-                ))),
-                GET_SPECIFIC_PLAYER => {
-                    Ok(FsmResult::Continue(Self::DeseringPlayerUuid(Uuid::deser())))
-                }
-                n => Err(EventReadError::InvalidKind(n)),
-            },
-            Self::DeseringIntro(deser) => Ok(deser
-                .mapped_process::<_, _, std::convert::Infallible>(
-                    Self::DeseringIntro,
-                    EventToClient::Introduced,
-                )
-                .unwrap()),
-            Self::DeseringTextUuid(deser) => match deser.process().unwrap() {
-                FsmResult::Continue(deser) => {
-                    Ok(FsmResult::Continue(Self::DeseringTextUuid(deser)))
-                }
-                FsmResult::Done(uuid) => Ok(FsmResult::Continue(Self::DeseringTextAfterUuid(
-                    uuid,
-                    String::deser(),
-                ))),
-            },
-            Self::DeseringTextAfterUuid(uuid, deser) => deser.mapped_process(
-                |deser| Self::DeseringTextAfterUuid(uuid, deser),
-                |msg| EventToClient::TxtSent(uuid, msg),
-            ),
-            Self::DeseringAdminMsg(deser) => {
-                deser.mapped_process(Self::DeseringAdminMsg, EventToClient::AdminMsg)
-            }
-            Self::DeseringPot(deser) => deser.mapped_process(Self::DeseringPot, EventToClient::Pot),
-            Self::DeseringAllPlayers(deser) => match deser.process()? {
-                FsmResult::Continue(deser) => {
-                    Ok(FsmResult::Continue(Self::DeseringAllPlayers(deser)))
-                }
-                FsmResult::Done(players) => Ok(FsmResult::Done(EventToClient::AllPlayers(players))),
-            },
-            Self::DeseringPlayerUuid(deser) => match deser.process().unwrap() {
-                FsmResult::Continue(deser) => {
-                    Ok(FsmResult::Continue(Self::DeseringPlayerUuid(deser)))
-                }
-                FsmResult::Done(uuid) => Ok(FsmResult::Continue(Self::DeseringPlayerAfterUuid(
-                    uuid,
-                    Player::deser(),
-                ))),
-            },
-            Self::DeseringPlayerAfterUuid(uuid, deser) => deser.mapped_process(
-                |deser| Self::DeseringPlayerAfterUuid(uuid, deser),
-                |player| EventToClient::SpecificPlayer(uuid, player),
-            ),
+            Self::Error { request_id, .. }
+            | Self::Pot(_, _, request_id)
+            | Self::AllPlayers(_, _, request_id)
+            | Self::SpecificPlayer(_, _, _, request_id)
+            | Self::TableList(_, request_id) => *request_id,
+            Self::Welcome { .. }
+            | Self::TxtSent(..)
+            | Self::AdminMsg(_)
+            | Self::Introduced { .. }
+            | Self::JoinedTable(_)
+            | Self::LeftTable(_)
+            | Self::TableUpdated(_)
+            | Self::HandStarted(..)
+            | Self::HoleCards(..)
+            | Self::Board(..)
+            | Self::Showdown(..)
+            | Self::ToAct { .. } => None,
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::events::client::{ClientEventDeserer, EventToClient};
+    use crate::events::client::EventToClient;
+    use crate::events::error_code::ErrorCode;
+    use crate::events::{GET_POT, RequestId, ResumeToken, TEXT_MESSAGE};
+    use crate::game_types::cards::Deck;
+    use crate::game_types::hand_eval::ShownHand;
     use crate::game_types::player::Player;
-    use crate::game_types::pot::Pot;
-    use crate::ser_glue::{DeserMachine, Deserable, DesiredInput, FsmResult, Serable};
+    use crate::game_types::pot::{Pot, SidePot};
+    use crate::game_types::table::{TableId, TableSummary};
+    use crate::integer::Integer;
+    use crate::ser_glue::list::ListSer;
+    use crate::ser_glue::{Deserable, Serable};
     use std::collections::HashMap;
     use uuid::Uuid;
 
@@ -196,7 +123,7 @@ mod tests {
         for example in example_data() {
             eprintln!("testing: {example:#?}");
             let serialised = example.ser().1;
-            let deserialised = deser_from_vec(serialised).unwrap();
+            let deserialised = EventToClient::driver().feed_all(&serialised).unwrap();
             assert_eq!(example, deserialised[0]);
         }
     }
@@ -205,99 +132,188 @@ mod tests {
     fn ser_events_mass() {
         let example_data = example_data().to_vec();
         let mut output = vec![];
-        example_data.iter().for_each(|e| e.ser_into(&mut output));
-        let deserialised = deser_from_vec(output).unwrap();
+        for e in &example_data {
+            e.ser_into(&mut output);
+        }
+        let deserialised = EventToClient::driver().feed_all(&output).unwrap();
         assert_eq!(example_data, deserialised);
     }
 
-    fn example_data() -> [EventToClient; 6] {
+    #[test]
+    fn derived_matches_hand_written_encoding() {
+        let uuid = Uuid::new_v4();
+        let table = TableId(3);
+        let mut expected = vec![TEXT_MESSAGE];
+        Integer::from(3_u32).ser_into(&mut expected);
+        uuid.ser_into(&mut expected);
+        "hello".ser_into(&mut expected);
+        assert_eq!(
+            EventToClient::TxtSent(table, uuid, "hello".to_string())
+                .ser()
+                .1,
+            expected
+        );
+
+        let pot = Pot {
+            current_value: 1_000,
+            ready_to_put_in: HashMap::from([(uuid, 300)]),
+            side_pots: vec![SidePot {
+                amount: 500,
+                eligible: vec![uuid],
+            }],
+        };
+        let mut expected = vec![GET_POT];
+        Integer::from(3_u32).ser_into(&mut expected);
+        Integer::from(pot.current_value).ser_into(&mut expected);
+        Integer::from(1_usize).ser_into(&mut expected);
+        ListSer(&[(uuid, Integer::from(300_u32))]).ser_into(&mut expected);
+        Integer::from(1_usize).ser_into(&mut expected);
+        Integer::from(500_u32).ser_into(&mut expected);
+        Integer::from(1_usize).ser_into(&mut expected);
+        uuid.ser_into(&mut expected);
+        let mut expected_with_id = expected.clone();
+        expected.push(0);
+        assert_eq!(
+            EventToClient::Pot(table, pot.clone(), None).ser().1,
+            expected
+        );
+
+        expected_with_id.push(1);
+        Integer::from(7_u32).ser_into(&mut expected_with_id);
+        assert_eq!(
+            EventToClient::Pot(table, pot, Some(RequestId(7))).ser().1,
+            expected_with_id
+        );
+    }
+
+    #[allow(clippy::too_many_lines)]
+    fn example_data() -> [EventToClient; 18] {
         [
-            EventToClient::TxtSent(Uuid::new_v4(), "argghhhhhhhhh éà🤧🤧🤧".to_string()),
+            EventToClient::Welcome {
+                protocol_version: 1,
+            },
+            EventToClient::TxtSent(
+                TableId(0),
+                Uuid::new_v4(),
+                "argghhhhhhhhh éà🤧🤧🤧".to_string(),
+            ),
             EventToClient::AdminMsg("get den'd ;)".to_string()),
-            EventToClient::Introduced(Uuid::new_v4()),
-            EventToClient::Pot(Pot {
-                current_value: 123_456_789,
-                ready_to_put_in: HashMap::from([
-                    (Uuid::new_v4(), 123),
-                    (Uuid::new_v4(), 456),
-                    (Uuid::new_v4(), 789),
+            EventToClient::Introduced {
+                uuid: Uuid::new_v4(),
+                resume_token: ResumeToken::new_random(),
+            },
+            EventToClient::Error {
+                code: ErrorCode::NotEnoughBalance,
+                message: "you're skint".to_string(),
+                request_id: Some(RequestId(u32::MAX)),
+            },
+            EventToClient::Pot(
+                TableId(1),
+                Pot {
+                    current_value: 123_456_789,
+                    ready_to_put_in: HashMap::from([
+                        (Uuid::new_v4(), 123),
+                        (Uuid::new_v4(), 456),
+                        (Uuid::new_v4(), 789),
+                    ]),
+                    side_pots: vec![
+                        SidePot {
+                            amount: 1_000,
+                            eligible: vec![Uuid::new_v4(), Uuid::new_v4()],
+                        },
+                        SidePot::default(),
+                    ],
+                },
+                None,
+            ),
+            EventToClient::AllPlayers(
+                TableId(2),
+                HashMap::from([
+                    (
+                        Uuid::new_v4(),
+                        Player {
+                            name: "Alice".to_string(),
+                            balance: 1,
+                        },
+                    ),
+                    (
+                        Uuid::new_v4(),
+                        Player {
+                            name: "François".to_string(),
+                            balance: u32::MAX,
+                        },
+                    ),
+                    (
+                        Uuid::new_v4(),
+                        Player {
+                            name: "範例名稱".to_string(),
+                            balance: u32::MAX - 1,
+                        },
+                    ),
                 ]),
-            }),
-            EventToClient::AllPlayers(HashMap::from([
-                (
-                    Uuid::new_v4(),
-                    Player {
-                        name: "Alice".to_string(),
-                        balance: 1,
-                    },
-                ),
-                (
-                    Uuid::new_v4(),
-                    Player {
-                        name: "François".to_string(),
-                        balance: u32::MAX,
-                    },
-                ),
-                (
-                    Uuid::new_v4(),
-                    Player {
-                        name: "範例名稱".to_string(),
-                        balance: u32::MAX - 1,
-                    },
-                ),
-            ])),
+                Some(RequestId(0)),
+            ),
             EventToClient::SpecificPlayer(
+                TableId(u32::MAX),
                 Uuid::new_v4(),
                 Player {
-                    name: "".to_string(),
+                    name: String::new(),
                     balance: 0,
                 },
+                Some(RequestId(12_345)),
             ),
+            EventToClient::TableList(
+                vec![
+                    TableSummary {
+                        id: TableId(0),
+                        name: "Main table".to_string(),
+                        player_count: 4,
+                        pot_size: 1_500,
+                    },
+                    TableSummary {
+                        id: TableId(7),
+                        name: "範例名稱".to_string(),
+                        player_count: 0,
+                        pot_size: 0,
+                    },
+                ],
+                Some(RequestId(9)),
+            ),
+            EventToClient::JoinedTable(TableSummary {
+                id: TableId(7),
+                name: String::new(),
+                player_count: 1,
+                pot_size: u32::MAX,
+            }),
+            EventToClient::LeftTable(TableId(7)),
+            EventToClient::TableUpdated(TableSummary {
+                id: TableId::MAIN,
+                name: "Main table".to_string(),
+                player_count: 2,
+                pot_size: 40,
+            }),
+            EventToClient::HandStarted(TableId::MAIN, vec![Uuid::new_v4(), Uuid::new_v4()]),
+            EventToClient::HoleCards(TableId(3), Deck::new().deal_many(2).unwrap()),
+            EventToClient::Board(TableId(3), vec![]),
+            EventToClient::Showdown(
+                TableId(3),
+                vec![ShownHand {
+                    player: Uuid::new_v4(),
+                    hole_cards: Deck::new().deal_many(2).unwrap(),
+                }],
+            ),
+            EventToClient::ToAct {
+                table: TableId(3),
+                player: Uuid::new_v4(),
+                current_bet: 20,
+                min_raise_to: Some(40),
+            },
+            EventToClient::ToAct {
+                table: TableId(3),
+                player: Uuid::new_v4(),
+                current_bet: 50,
+                min_raise_to: None,
+            },
         ]
     }
-
-    fn deser_from_vec(v: Vec<u8>) -> Result<Vec<EventToClient>, Box<dyn std::error::Error>> {
-        let mut binary = v.into_iter().peekable();
-        let mut deserer = EventToClient::deser();
-        let mut found = vec![];
-
-        loop {
-            if binary.peek().is_none() && matches!(deserer, ClientEventDeserer::Start(_)) {
-                break;
-            }
-
-            match deserer.wants_read() {
-                DesiredInput::Byte(space) => {
-                    if let Some(byte) = binary.next() {
-                        *space = byte;
-                        deserer.finish_bytes_for_writing(1);
-                    }
-                }
-                DesiredInput::Bytes(space) => {
-                    let mut n = 0;
-                    for next_space in space {
-                        let Some(byte) = binary.next() else {
-                            break;
-                        };
-                        *next_space = byte;
-
-                        n += 1;
-                    }
-                    deserer.finish_bytes_for_writing(n);
-                }
-                DesiredInput::ProcessMe => {
-                    deserer = match deserer.process()? {
-                        FsmResult::Continue(cont) => cont,
-                        FsmResult::Done(evt) => {
-                            found.push(evt);
-                            EventToClient::deser()
-                        }
-                    }
-                }
-                DesiredInput::Extra => unreachable!(),
-            }
-        }
-
-        Ok(found)
-    }
 }
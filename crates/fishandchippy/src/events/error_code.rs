@@ -0,0 +1,65 @@
+use crate::ser_glue::{Deserable, Serable};
+
+///Why the server turned down a request - sent in [`EventToClient::Error`](crate::events::client::EventToClient::Error).
+///
+/// The discriminants are part of the wire format, so never re-use or change one.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serable, Deserable)]
+pub enum ErrorCode {
+    ///The request needs the client to have introduced themselves first.
+    #[ser_glue(discriminant = 1)]
+    NotIntroduced,
+    ///The client has already introduced themselves.
+    #[ser_glue(discriminant = 2)]
+    AlreadyIntroduced,
+    ///The client has already said hello.
+    #[ser_glue(discriminant = 3)]
+    AlreadyGreeted,
+    ///The player doesn't have enough chips to do that.
+    #[ser_glue(discriminant = 4)]
+    NotEnoughBalance,
+    ///No player has that UUID.
+    #[ser_glue(discriminant = 5)]
+    UnknownPlayer,
+    ///Chips still need sweeping into the pot before it can be awarded.
+    #[ser_glue(discriminant = 6)]
+    BettingRoundOpen,
+    ///The pot has to go to at least one player.
+    #[ser_glue(discriminant = 7)]
+    NoWinners,
+    ///The buy-in or rebuy isn't allowed by the table's limits.
+    #[ser_glue(discriminant = 8)]
+    BuyInRejected,
+    ///No table has that ID.
+    #[ser_glue(discriminant = 9)]
+    UnknownTable,
+    ///The request needs the client to be sat at a table.
+    #[ser_glue(discriminant = 10)]
+    NotAtTable,
+    ///The server won't host any more tables.
+    #[ser_glue(discriminant = 11)]
+    TooManyTables,
+    ///Table names can't be blank or too long.
+    #[ser_glue(discriminant = 12)]
+    InvalidTableName,
+    ///The resume token has expired, or was never handed out.
+    #[ser_glue(discriminant = 13)]
+    UnknownResumeToken,
+    ///Every seat at the table is taken.
+    #[ser_glue(discriminant = 14)]
+    TableFull,
+    ///Only one hand can be played at a table at once, and the server decides who wins it.
+    #[ser_glue(discriminant = 15)]
+    HandInProgress,
+    ///A hand needs at least two players with chips, and few enough that the deck doesn't run out.
+    #[ser_glue(discriminant = 16)]
+    WrongPlayerCount,
+    ///Betting actions can only be used while a hand is being played.
+    #[ser_glue(discriminant = 17)]
+    NoHandInProgress,
+    ///Someone else has to act first.
+    #[ser_glue(discriminant = 18)]
+    NotYourTurn,
+    ///The betting action isn't allowed right now, like checking when there's a bet to call.
+    #[ser_glue(discriminant = 19)]
+    IllegalAction,
+}
@@ -1,147 +1,87 @@
 use crate::events::{
-    ADD_TO_POT, EventReadError, GET_ALL_PLAYERS, GET_SPECIFIC_PLAYER, INTRODUCTION, TEXT_MESSAGE,
+    ADD_TO_POT, ALL_IN, AWARD_POT, BUY_IN, CALL, CHECK, CLOSE_BETTING_ROUND, CREATE_TABLE,
+    EventReadError, FOLD, GET_ALL_PLAYERS, GET_SPECIFIC_PLAYER, HELLO, INTRODUCTION, JOIN_TABLE,
+    LEAVE_TABLE, LIST_TABLES, RAISE, REBUY, RESUME, RequestId, ResumeToken, SPLIT_POT, START_HAND,
+    TEXT_MESSAGE,
 };
-use crate::integer::{Integer, IntegerDeserialiser, SignedState};
-use crate::ser_glue::string::StringDeserialiser;
-use crate::ser_glue::uuid::UuidDeserialiser;
-use crate::ser_glue::{DeserMachine, Deserable, DesiredInput, FsmResult, Serable};
+use crate::game_types::table::TableId;
+use crate::ser_glue::{Deserable, Serable};
 use uuid::Uuid;
 
-#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serable, Deserable)]
+#[ser_glue(error = EventReadError, deserer = ServerEventDeserer)]
 pub enum EventToServer {
+    ///Must be the first event on a connection - see [`PROTOCOL_VERSION`](crate::events::PROTOCOL_VERSION).
+    #[ser_glue(discriminant = HELLO)]
+    Hello { protocol_version: u32 },
+    #[ser_glue(discriminant = TEXT_MESSAGE)]
     SendMessage { content: String },
+    #[ser_glue(discriminant = INTRODUCTION)]
     Introduction { name: String },
-    GetStartInformation,
-    GetSpecificPlayer(Uuid),
+    ///Sent instead of an [`EventToServer::Introduction`] to pick up as the same player after a disconnect, including their seat, balance and anything they had ready to put in the pot. Only works for a little while after the disconnect.
+    #[ser_glue(discriminant = RESUME)]
+    Resume { token: ResumeToken },
+    ///Replied to with [`EventToClient::AllPlayers`](crate::events::client::EventToClient::AllPlayers) and [`EventToClient::Pot`](crate::events::client::EventToClient::Pot), which carry the same request ID.
+    #[ser_glue(discriminant = GET_ALL_PLAYERS)]
+    GetStartInformation { request_id: Option<RequestId> },
+    #[ser_glue(discriminant = GET_SPECIFIC_PLAYER)]
+    GetSpecificPlayer(Uuid, Option<RequestId>),
+    ///Only allowed when there isn't a hand being played - use the betting actions then instead.
+    #[ser_glue(discriminant = ADD_TO_POT)]
     AddToPot(u32),
-}
-
-impl Serable for EventToServer {
-    type ExtraOutput = ();
-
-    fn ser_into(&self, into: &mut Vec<u8>) -> Self::ExtraOutput {
-        match self {
-            Self::SendMessage { content } => {
-                into.push(TEXT_MESSAGE);
-                content.ser_into(into);
-            }
-            Self::Introduction { name } => {
-                into.push(INTRODUCTION);
-                name.ser_into(into);
-            }
-            Self::GetStartInformation => {
-                into.push(GET_ALL_PLAYERS);
-            }
-            Self::GetSpecificPlayer(uuid) => {
-                into.push(GET_SPECIFIC_PLAYER);
-                uuid.ser_into(into);
-            }
-            Self::AddToPot(n) => {
-                into.push(ADD_TO_POT);
-                Integer::from(*n).ser_into(into);
-            }
-        }
-    }
-}
-
-impl Deserable for EventToServer {
-    type Deserer = ServerEventDeserer;
-}
-
-#[derive(Debug)]
-pub enum ServerEventDeserer {
-    Start(u8),
-    GotStart(u8),
-    DeseringTxtMsg(StringDeserialiser),
-    DeseringIntroduction(StringDeserialiser),
-    DeseringAddToPot(IntegerDeserialiser),
-    DeseringGetSpecificPlayer(UuidDeserialiser),
-}
-
-impl DeserMachine for ServerEventDeserer {
-    type ExtraInput = ();
-    type Output = EventToServer;
-    type Error = EventReadError;
-
-    fn new() -> Self {
-        Self::Start(0)
-    }
-
-    fn wants_read(&mut self) -> DesiredInput<'_> {
-        match self {
-            Self::Start(space) => DesiredInput::Byte(space),
-            Self::GotStart(_start) => DesiredInput::ProcessMe,
-            Self::DeseringTxtMsg(deser) | Self::DeseringIntroduction(deser) => deser.wants_read(),
-            Self::DeseringAddToPot(deser) => deser.wants_read(),
-            Self::DeseringGetSpecificPlayer(deser) => deser.wants_read(),
-        }
-    }
-
-    fn give_starting_input(&mut self, (): Self::ExtraInput) {}
-
-    fn finish_bytes_for_writing(&mut self, n: usize) {
-        match self {
-            Self::Start(start) => {
-                if n == 1 {
-                    *self = Self::GotStart(*start);
-                }
-            }
-            Self::GotStart(_) => {}
-            Self::DeseringTxtMsg(deser) | Self::DeseringIntroduction(deser) => {
-                deser.finish_bytes_for_writing(n)
-            }
-            Self::DeseringAddToPot(deser) => deser.finish_bytes_for_writing(n),
-            Self::DeseringGetSpecificPlayer(deser) => deser.finish_bytes_for_writing(n),
-        }
-    }
-
-    fn process(self) -> Result<FsmResult<Self, Self::Output>, Self::Error> {
-        match self {
-            Self::Start(n) => Ok(FsmResult::Continue(Self::Start(n))),
-            Self::GotStart(n) => match n {
-                TEXT_MESSAGE => Ok(FsmResult::Continue(Self::DeseringTxtMsg(String::deser()))),
-                INTRODUCTION => Ok(FsmResult::Continue(Self::DeseringIntroduction(
-                    String::deser(),
-                ))),
-                GET_ALL_PLAYERS => Ok(FsmResult::Done(EventToServer::GetStartInformation)),
-                GET_SPECIFIC_PLAYER => Ok(FsmResult::Continue(Self::DeseringGetSpecificPlayer(
-                    Uuid::deser(),
-                ))),
-                ADD_TO_POT => Ok(FsmResult::Continue(Self::DeseringAddToPot(
-                    Integer::deser_with_input(SignedState::Unsigned),
-                ))),
-                n => Err(EventReadError::InvalidKind(n)),
-            },
-            Self::DeseringTxtMsg(deser) => deser.mapped_process(Self::DeseringTxtMsg, |content| {
-                EventToServer::SendMessage { content }
-            }),
-            Self::DeseringIntroduction(deser) => deser
-                .mapped_process(Self::DeseringIntroduction, |name| {
-                    EventToServer::Introduction { name }
-                }),
-            Self::DeseringAddToPot(deser) => match deser.process()? {
-                FsmResult::Continue(deser) => {
-                    Ok(FsmResult::Continue(Self::DeseringAddToPot(deser)))
-                }
-                FsmResult::Done(amt) => {
-                    let amt: u32 = amt.try_into()?;
-                    Ok(FsmResult::Done(EventToServer::AddToPot(amt)))
-                }
-            },
-            Self::DeseringGetSpecificPlayer(deser) => Ok(deser
-                .mapped_process::<_, _, std::convert::Infallible>(
-                    Self::DeseringGetSpecificPlayer,
-                    EventToServer::GetSpecificPlayer,
-                )
-                .unwrap()),
-        }
-    }
+    ///Sweeps everything that's ready to put in into the pot. Only allowed when there isn't a hand being played, as those move on by themselves.
+    #[ser_glue(discriminant = CLOSE_BETTING_ROUND)]
+    CloseBettingRound,
+    ///Votes to give the whole pot to one player, which only happens once everyone still connected to the table has voted for the same thing. The betting round must be closed first, and there can't be a hand being played, as the server works out who wins those.
+    #[ser_glue(discriminant = AWARD_POT)]
+    AwardPot { winner: Uuid },
+    ///Splits the pot evenly between players - see [`Pot::split_between`](crate::game_types::pot::Pot::split_between). The same rules as [`EventToServer::AwardPot`] apply.
+    #[ser_glue(discriminant = SPLIT_POT)]
+    SplitPot { winners: Vec<Uuid> },
+    ///Adds chips to the player's balance. Each player can only buy in once, and the server decides how much is allowed.
+    #[ser_glue(discriminant = BUY_IN)]
+    BuyIn(u32),
+    ///Tops up a player who has run out of chips, by an amount the server decides.
+    #[ser_glue(discriminant = REBUY)]
+    Rebuy,
+    ///Replied to with [`EventToClient::TableList`](crate::events::client::EventToClient::TableList).
+    #[ser_glue(discriminant = LIST_TABLES)]
+    ListTables { request_id: Option<RequestId> },
+    ///Makes a new table, and then joins it.
+    #[ser_glue(discriminant = CREATE_TABLE)]
+    CreateTable { name: String },
+    ///Sits down at a table, leaving the current one first. Replied to with [`EventToClient::JoinedTable`](crate::events::client::EventToClient::JoinedTable).
+    #[ser_glue(discriminant = JOIN_TABLE)]
+    JoinTable(TableId),
+    ///Gets up from the current table, giving up any chips there.
+    #[ser_glue(discriminant = LEAVE_TABLE)]
+    LeaveTable,
+    ///Deals a new hand to everyone at the table with chips, and takes the blinds from the two players after the button. The server then asks each of them to act in turn with [`EventToClient::ToAct`](crate::events::client::EventToClient::ToAct), dealing the next street whenever a betting round ends.
+    #[ser_glue(discriminant = START_HAND)]
+    StartHand,
+    ///Bets nothing, which is only allowed when there's nothing to call.
+    #[ser_glue(discriminant = CHECK)]
+    Check,
+    ///Matches the current bet.
+    #[ser_glue(discriminant = CALL)]
+    Call,
+    ///Brings our bet for this betting round up to `to`, which has to be at least the `min_raise_to` we were sent.
+    #[ser_glue(discriminant = RAISE)]
+    Raise { to: u32 },
+    ///Gives up on the hand, leaving everything already bet in the pot.
+    #[ser_glue(discriminant = FOLD)]
+    Fold,
+    ///Puts in every chip we have, even if that's less than the current bet.
+    #[ser_glue(discriminant = ALL_IN)]
+    AllIn,
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::events::server::{EventToServer, ServerEventDeserer};
-    use crate::ser_glue::{DeserMachine, Deserable, DesiredInput, FsmResult, Serable};
+    use crate::events::server::EventToServer;
+    use crate::events::{RequestId, ResumeToken};
+    use crate::game_types::table::TableId;
+    use crate::ser_glue::{Deserable, Serable};
     use uuid::Uuid;
 
     #[test]
@@ -149,7 +89,7 @@ mod tests {
         for example in example_data() {
             eprintln!("testing: {example:#?}");
             let serialised = example.ser().1;
-            let deserialised = deser_from_vec(serialised).unwrap();
+            let deserialised = EventToServer::driver().feed_all(&serialised).unwrap();
             assert_eq!(example, deserialised[0]);
         }
     }
@@ -158,67 +98,53 @@ mod tests {
     fn ser_events_mass() {
         let example_data = example_data().to_vec();
         let mut output = vec![];
-        example_data.iter().for_each(|e| e.ser_into(&mut output));
-        let deserialised = deser_from_vec(output).unwrap();
+        for e in &example_data {
+            e.ser_into(&mut output);
+        }
+        let deserialised = EventToServer::driver().feed_all(&output).unwrap();
         assert_eq!(example_data, deserialised);
     }
 
-    fn example_data() -> [EventToServer; 5] {
+    fn example_data() -> [EventToServer; 22] {
         [
+            EventToServer::Hello {
+                protocol_version: u32::MAX,
+            },
             EventToServer::SendMessage {
                 content: "sup? 🤣🤣🤣".to_string(),
             },
             EventToServer::Introduction {
                 name: "範例名稱".to_string(),
             },
-            EventToServer::GetStartInformation,
-            EventToServer::GetSpecificPlayer(Uuid::new_v4()),
+            EventToServer::Resume {
+                token: ResumeToken::new_random(),
+            },
+            EventToServer::GetStartInformation {
+                request_id: Some(RequestId(1)),
+            },
+            EventToServer::GetSpecificPlayer(Uuid::new_v4(), None),
             EventToServer::AddToPot(u32::MAX),
+            EventToServer::CloseBettingRound,
+            EventToServer::AwardPot {
+                winner: Uuid::new_v4(),
+            },
+            EventToServer::SplitPot {
+                winners: vec![Uuid::new_v4(), Uuid::new_v4()],
+            },
+            EventToServer::BuyIn(500),
+            EventToServer::Rebuy,
+            EventToServer::ListTables { request_id: None },
+            EventToServer::CreateTable {
+                name: "high rollers 🎲".to_string(),
+            },
+            EventToServer::JoinTable(TableId(u32::MAX)),
+            EventToServer::LeaveTable,
+            EventToServer::StartHand,
+            EventToServer::Check,
+            EventToServer::Call,
+            EventToServer::Raise { to: 1_000 },
+            EventToServer::Fold,
+            EventToServer::AllIn,
         ]
     }
-
-    fn deser_from_vec(v: Vec<u8>) -> Result<Vec<EventToServer>, Box<dyn std::error::Error>> {
-        let mut binary = v.into_iter().peekable();
-        let mut deserer = EventToServer::deser();
-        let mut found = vec![];
-
-        loop {
-            if binary.peek().is_none() && matches!(deserer, ServerEventDeserer::Start(_)) {
-                break;
-            }
-
-            match deserer.wants_read() {
-                DesiredInput::Byte(space) => {
-                    if let Some(byte) = binary.next() {
-                        *space = byte;
-                        deserer.finish_bytes_for_writing(1);
-                    }
-                }
-                DesiredInput::Bytes(space) => {
-                    let mut n = 0;
-                    for next_space in space {
-                        let Some(byte) = binary.next() else {
-                            break;
-                        };
-                        *next_space = byte;
-
-                        n += 1;
-                    }
-                    deserer.finish_bytes_for_writing(n);
-                }
-                DesiredInput::ProcessMe => {
-                    deserer = match deserer.process()? {
-                        FsmResult::Continue(cont) => cont,
-                        FsmResult::Done(evt) => {
-                            found.push(evt);
-                            EventToServer::deser()
-                        }
-                    }
-                }
-                DesiredInput::Extra => unreachable!(),
-            }
-        }
-
-        Ok(found)
-    }
 }
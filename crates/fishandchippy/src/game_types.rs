@@ -1,2 +1,5 @@
+pub mod cards;
+pub mod hand_eval;
 pub mod player;
 pub mod pot;
+pub mod table;
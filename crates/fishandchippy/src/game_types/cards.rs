@@ -0,0 +1,280 @@
+use crate::ser_glue::{Deserable, Serable};
+use rand_core::RngCore;
+use std::fmt::{Display, Formatter};
+
+///The discriminants are part of the wire format, so never re-order these.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Serable, Deserable)]
+pub enum Suit {
+    Clubs,
+    Diamonds,
+    Hearts,
+    Spades,
+}
+
+impl Suit {
+    pub const ALL: [Self; 4] = [Self::Clubs, Self::Diamonds, Self::Hearts, Self::Spades];
+}
+
+impl Display for Suit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let symbol = match self {
+            Self::Clubs => '♣',
+            Self::Diamonds => '♦',
+            Self::Hearts => '♥',
+            Self::Spades => '♠',
+        };
+        write!(f, "{symbol}")
+    }
+}
+
+///Ordered from lowest to highest, with aces high. The discriminants are part of the wire format, so never re-order these.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Serable, Deserable)]
+pub enum Rank {
+    Two,
+    Three,
+    Four,
+    Five,
+    Six,
+    Seven,
+    Eight,
+    Nine,
+    Ten,
+    Jack,
+    Queen,
+    King,
+    Ace,
+}
+
+impl Rank {
+    pub const ALL: [Self; 13] = [
+        Self::Two,
+        Self::Three,
+        Self::Four,
+        Self::Five,
+        Self::Six,
+        Self::Seven,
+        Self::Eight,
+        Self::Nine,
+        Self::Ten,
+        Self::Jack,
+        Self::Queen,
+        Self::King,
+        Self::Ace,
+    ];
+
+    ///The number on the card, with jacks as 11 up to aces as 14.
+    #[must_use]
+    pub const fn value(self) -> u8 {
+        self as u8 + 2
+    }
+}
+
+impl Display for Rank {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ten => write!(f, "T"),
+            Self::Jack => write!(f, "J"),
+            Self::Queen => write!(f, "Q"),
+            Self::King => write!(f, "K"),
+            Self::Ace => write!(f, "A"),
+            number => write!(f, "{}", number.value()),
+        }
+    }
+}
+
+///Cards order by rank first, so sorting a hand puts pairs next to each other.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Serable, Deserable)]
+pub struct Card {
+    pub rank: Rank,
+    pub suit: Suit,
+}
+
+impl Display for Card {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.rank, self.suit)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DealError {
+    ///There weren't enough cards left, so none were dealt.
+    OutOfCards { wanted: usize, left: usize },
+}
+
+impl Display for DealError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OutOfCards { wanted, left } => write!(
+                f,
+                "Tried to deal {wanted} card(s), but only {left} are left in the deck"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DealError {}
+
+///A deck of cards, dealt from the top.
+#[derive(Debug, Clone, Eq, PartialEq, Serable, Deserable)]
+pub struct Deck {
+    ///The top of the deck is at the end, so dealing doesn't have to shift everything along.
+    cards: Vec<Card>,
+}
+
+impl Default for Deck {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deck {
+    ///All 52 cards, in order.
+    #[must_use]
+    pub fn new() -> Self {
+        let cards = Suit::ALL
+            .into_iter()
+            .flat_map(|suit| Rank::ALL.into_iter().map(move |rank| Card { rank, suit }))
+            .collect();
+        Self { cards }
+    }
+
+    ///All 52 cards, shuffled using `rng`. Pass in a seeded RNG to get the same order every time.
+    #[must_use]
+    pub fn shuffled(rng: &mut impl RngCore) -> Self {
+        let mut deck = Self::new();
+        deck.shuffle(rng);
+        deck
+    }
+
+    ///Shuffles whatever's left in the deck, using a Fisher-Yates shuffle so that every order is equally likely.
+    ///
+    /// # Panics
+    /// If the deck has more than [`u32::MAX`] cards, which can only happen if it was deserialised without [`DecodeLimits`](crate::ser_glue::DecodeLimits).
+    pub fn shuffle(&mut self, rng: &mut impl RngCore) {
+        for i in (1..self.cards.len()).rev() {
+            let bound = u32::try_from(i + 1).expect("decks have far fewer than u32::MAX cards");
+            let j = random_below(rng, bound) as usize;
+            self.cards.swap(i, j);
+        }
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.cards.len()
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.cards.is_empty()
+    }
+
+    ///Takes the top card.
+    ///
+    /// # Errors
+    /// If the deck is empty.
+    pub fn deal(&mut self) -> Result<Card, DealError> {
+        self.cards
+            .pop()
+            .ok_or(DealError::OutOfCards { wanted: 1, left: 0 })
+    }
+
+    ///Takes `n` cards from the top, in the order they'd be dealt one by one. Either all `n` get dealt, or none do.
+    ///
+    /// # Errors
+    /// If there are fewer than `n` cards left.
+    pub fn deal_many(&mut self, n: usize) -> Result<Vec<Card>, DealError> {
+        let left = self.cards.len();
+        if n > left {
+            return Err(DealError::OutOfCards { wanted: n, left });
+        }
+        Ok(self.cards.drain(left - n..).rev().collect())
+    }
+}
+
+///Picks a number in `0..bound` without favouring any of them, throwing away the few values that would.
+fn random_below(rng: &mut impl RngCore, bound: u32) -> u32 {
+    let limit = u32::MAX - u32::MAX % bound;
+    loop {
+        let x = rng.next_u32();
+        if x < limit {
+            return x % bound;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game_types::cards::{Card, DealError, Deck, Rank, Suit};
+    use crate::ser_glue::{DeserMachine, Deserable, Serable};
+    use rand_core::SeedableRng;
+    use rand_pcg::Pcg32;
+    use std::collections::HashSet;
+
+    #[test]
+    fn new_deck_has_every_card_once() {
+        let deck = Deck::new();
+        assert_eq!(deck.len(), 52);
+        assert_eq!(deck.cards.iter().collect::<HashSet<_>>().len(), 52);
+        assert_eq!(Rank::Ace.value(), 14);
+        assert_eq!(
+            Card {
+                rank: Rank::Ten,
+                suit: Suit::Hearts
+            }
+            .to_string(),
+            "T♥"
+        );
+    }
+
+    #[test]
+    fn shuffles_reproducibly() {
+        let deck = Deck::shuffled(&mut Pcg32::seed_from_u64(7));
+        assert_eq!(deck, Deck::shuffled(&mut Pcg32::seed_from_u64(7)));
+        assert_ne!(deck, Deck::shuffled(&mut Pcg32::seed_from_u64(8)));
+        assert_ne!(deck, Deck::new());
+
+        let mut sorted = deck.cards;
+        sorted.sort_unstable();
+        let mut expected = Deck::new().cards;
+        expected.sort_unstable();
+        assert_eq!(sorted, expected);
+    }
+
+    #[test]
+    fn deals_until_empty() {
+        let mut deck = Deck::new();
+        let top = *deck.cards.last().unwrap();
+        assert_eq!(deck.deal(), Ok(top));
+
+        assert_eq!(
+            deck.deal_many(52),
+            Err(DealError::OutOfCards {
+                wanted: 52,
+                left: 51
+            })
+        );
+        assert_eq!(deck.len(), 51);
+
+        //the first card dealt is the one that was on top
+        let dealt = deck.deal_many(51).unwrap();
+        assert_eq!(
+            dealt.first(),
+            Some(&Card {
+                rank: Rank::King,
+                suit: Suit::Spades
+            })
+        );
+        assert!(deck.is_empty());
+        assert_eq!(
+            deck.deal(),
+            Err(DealError::OutOfCards { wanted: 1, left: 0 })
+        );
+    }
+
+    #[test]
+    fn round_trips_deck() {
+        let deck = Deck::shuffled(&mut Pcg32::seed_from_u64(1));
+        let ((), bytes) = deck.ser();
+        assert_eq!(Deck::deser().read_from(bytes.as_slice()).unwrap(), deck);
+    }
+}
@@ -0,0 +1,437 @@
+use crate::game_types::cards::{Card, Rank, Suit};
+use crate::ser_glue::{Deserable, Serable};
+use std::fmt::{Display, Formatter};
+use uuid::Uuid;
+
+///How good a hand is. Hands compare category first, then by the ranks inside each variant in the order they're declared, so two hands that compare equal split the pot.
+///
+///Every rank that can matter is kept, with kickers from highest to lowest.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub enum HandRank {
+    HighCard([Rank; 5]),
+    OnePair {
+        pair: Rank,
+        kickers: [Rank; 3],
+    },
+    TwoPair {
+        high: Rank,
+        low: Rank,
+        kicker: Rank,
+    },
+    ThreeOfAKind {
+        trips: Rank,
+        kickers: [Rank; 2],
+    },
+    ///`high` is the top of the straight, so the wheel (A-2-3-4-5) is five-high.
+    Straight {
+        high: Rank,
+    },
+    Flush([Rank; 5]),
+    FullHouse {
+        trips: Rank,
+        pair: Rank,
+    },
+    FourOfAKind {
+        quads: Rank,
+        kicker: Rank,
+    },
+    StraightFlush {
+        high: Rank,
+    },
+    RoyalFlush,
+}
+
+///A player's hole cards, turned over at showdown. Clients can work out what they make with [`HandRank::evaluate`] and the board.
+#[derive(Debug, Clone, Hash, Eq, PartialEq, Serable, Deserable)]
+pub struct ShownHand {
+    pub player: Uuid,
+    pub hole_cards: Vec<Card>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum HandEvalError {
+    ///Hands are made from 5 to 7 cards.
+    WrongNumberOfCards(usize),
+    ///The same card turned up twice, which no deck can deal.
+    DuplicateCard(Card),
+}
+
+impl Display for HandEvalError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::WrongNumberOfCards(n) => {
+                write!(f, "Can only rank 5 to 7 cards, but got {n}")
+            }
+            Self::DuplicateCard(card) => write!(f, "{card} turned up more than once"),
+        }
+    }
+}
+
+impl std::error::Error for HandEvalError {}
+
+///A set of ranks, with one bit for each, in the same order as [`Rank::ALL`].
+#[derive(Debug, Copy, Clone, Default)]
+struct RankSet(u16);
+
+impl RankSet {
+    const fn insert(&mut self, rank: Rank) {
+        self.0 |= 1 << rank as u16;
+    }
+
+    const fn len(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    ///Highest first.
+    fn iter(self) -> impl Iterator<Item = Rank> {
+        Rank::ALL
+            .into_iter()
+            .rev()
+            .filter(move |rank| self.0 & (1 << *rank as u16) != 0)
+    }
+
+    ///The top of the best straight in the set, if there is one.
+    fn straight_high(self) -> Option<Rank> {
+        const FIVE_IN_A_ROW: u16 = 0b1_1111;
+        //aces can also be low, so they get copied underneath the two
+        let ace_low = u16::from(self.0 & (1 << Rank::Ace as u16) != 0);
+        let bits = (self.0 << 1) | ace_low;
+        (0..=9)
+            .rev()
+            .find(|lowest| (bits >> lowest) & FIVE_IN_A_ROW == FIVE_IN_A_ROW)
+            .map(|lowest| Rank::ALL[lowest + 3])
+    }
+
+    ///The highest `N` ranks, or `None` if there aren't that many.
+    fn top<const N: usize>(self) -> Option<[Rank; N]> {
+        let mut out = [Rank::Two; N];
+        let mut iter = self.iter();
+        for slot in &mut out {
+            *slot = iter.next()?;
+        }
+        Some(out)
+    }
+}
+
+impl HandRank {
+    ///Finds the best five card hand that can be made out of `cards`, which would usually be two hole cards and the board.
+    ///
+    /// # Errors
+    /// If there aren't 5 to 7 cards, or if any of them are the same.
+    ///
+    /// # Panics
+    /// Never - five different cards always leave enough ranks over for the kickers.
+    pub fn evaluate(cards: &[Card]) -> Result<Self, HandEvalError> {
+        if !(5..=7).contains(&cards.len()) {
+            return Err(HandEvalError::WrongNumberOfCards(cards.len()));
+        }
+
+        let mut counts = [0_u8; 13];
+        let mut by_suit = [RankSet::default(); 4];
+        let mut all = RankSet::default();
+        for (i, card) in cards.iter().enumerate() {
+            if cards[..i].contains(card) {
+                return Err(HandEvalError::DuplicateCard(*card));
+            }
+            counts[card.rank as usize] += 1;
+            by_suit[card.suit as usize].insert(card.rank);
+            all.insert(card.rank);
+        }
+
+        //with at most 7 cards, only one suit can have 5 of them
+        let flush = Suit::ALL
+            .into_iter()
+            .map(|suit| by_suit[suit as usize])
+            .find(|ranks| ranks.len() >= 5);
+        if let Some(high) = flush.and_then(RankSet::straight_high) {
+            return Ok(if high == Rank::Ace {
+                Self::RoyalFlush
+            } else {
+                Self::StraightFlush { high }
+            });
+        }
+
+        //ranks that turned up at least `n` times, highest first
+        let at_least = |n: u8| {
+            Rank::ALL
+                .into_iter()
+                .rev()
+                .filter(move |rank| counts[*rank as usize] >= n)
+        };
+        let without = |excluded: &[Rank]| {
+            let mut set = all;
+            for rank in excluded {
+                set.0 &= !(1 << *rank as u16);
+            }
+            set
+        };
+
+        if let Some(quads) = at_least(4).next() {
+            let [kicker] = without(&[quads]).top().expect("5+ cards means a kicker");
+            return Ok(Self::FourOfAKind { quads, kicker });
+        }
+
+        if let Some(trips) = at_least(3).next() {
+            //a second set of trips works as the pair
+            if let Some(pair) = at_least(2).find(|rank| *rank != trips) {
+                return Ok(Self::FullHouse { trips, pair });
+            }
+        }
+
+        if let Some(ranks) = flush.and_then(RankSet::top) {
+            return Ok(Self::Flush(ranks));
+        }
+        if let Some(high) = all.straight_high() {
+            return Ok(Self::Straight { high });
+        }
+
+        if let Some(trips) = at_least(3).next() {
+            let kickers = without(&[trips]).top().expect("5+ cards means 2 kickers");
+            return Ok(Self::ThreeOfAKind { trips, kickers });
+        }
+
+        let mut pairs = at_least(2);
+        match (pairs.next(), pairs.next()) {
+            (Some(high), Some(low)) => {
+                //a third pair can still be the kicker
+                let [kicker] = without(&[high, low])
+                    .top()
+                    .expect("5+ cards means a kicker");
+                Ok(Self::TwoPair { high, low, kicker })
+            }
+            (Some(pair), None) => {
+                let kickers = without(&[pair]).top().expect("5+ cards means 3 kickers");
+                Ok(Self::OnePair { pair, kickers })
+            }
+            _ => Ok(Self::HighCard(
+                all.top().expect("5+ cards with no pairs means 5 ranks"),
+            )),
+        }
+    }
+}
+
+impl Display for HandRank {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::HighCard([high, ..]) => write!(f, "{high} high"),
+            Self::OnePair { pair, .. } => write!(f, "Pair of {pair}s"),
+            Self::TwoPair { high, low, .. } => write!(f, "Two pair, {high}s and {low}s"),
+            Self::ThreeOfAKind { trips, .. } => write!(f, "Three {trips}s"),
+            Self::Straight { high } => write!(f, "{high} high straight"),
+            Self::Flush([high, ..]) => write!(f, "{high} high flush"),
+            Self::FullHouse { trips, pair } => write!(f, "{trips}s full of {pair}s"),
+            Self::FourOfAKind { quads, .. } => write!(f, "Four {quads}s"),
+            Self::StraightFlush { high } => write!(f, "{high} high straight flush"),
+            Self::RoyalFlush => write!(f, "Royal flush"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game_types::cards::{Card, Rank, Suit};
+    use crate::game_types::hand_eval::{HandEvalError, HandRank};
+
+    ///Parses hands like `"As Kd 5c"`.
+    fn cards(hand: &str) -> Vec<Card> {
+        hand.split_whitespace()
+            .map(|card| {
+                let mut chars = card.chars();
+                let rank = match chars.next().unwrap() {
+                    'T' => Rank::Ten,
+                    'J' => Rank::Jack,
+                    'Q' => Rank::Queen,
+                    'K' => Rank::King,
+                    'A' => Rank::Ace,
+                    n => Rank::ALL[n.to_digit(10).unwrap() as usize - 2],
+                };
+                let suit = match chars.next().unwrap() {
+                    'c' => Suit::Clubs,
+                    'd' => Suit::Diamonds,
+                    'h' => Suit::Hearts,
+                    's' => Suit::Spades,
+                    other => panic!("unknown suit {other}"),
+                };
+                Card { rank, suit }
+            })
+            .collect()
+    }
+
+    fn eval(hand: &str) -> HandRank {
+        HandRank::evaluate(&cards(hand)).unwrap()
+    }
+
+    #[test]
+    fn finds_every_category() {
+        use Rank::*;
+        assert_eq!(
+            eval("2c 5d 9h Js Kc 3d 7h"),
+            HandRank::HighCard([King, Jack, Nine, Seven, Five])
+        );
+        assert_eq!(
+            eval("9c 9d 2h 5s Kc"),
+            HandRank::OnePair {
+                pair: Nine,
+                kickers: [King, Five, Two]
+            }
+        );
+        assert_eq!(
+            eval("Ac Ad 7h 7s 2c"),
+            HandRank::TwoPair {
+                high: Ace,
+                low: Seven,
+                kicker: Two
+            }
+        );
+        assert_eq!(
+            eval("4c 4d 4h Ks 2c"),
+            HandRank::ThreeOfAKind {
+                trips: Four,
+                kickers: [King, Two]
+            }
+        );
+        assert_eq!(eval("6c 7d 8h 9s Tc"), HandRank::Straight { high: Ten });
+        assert_eq!(
+            eval("2h 7h 9h Jh Kh"),
+            HandRank::Flush([King, Jack, Nine, Seven, Two])
+        );
+        assert_eq!(
+            eval("Qc Qd Qh 3s 3c"),
+            HandRank::FullHouse {
+                trips: Queen,
+                pair: Three
+            }
+        );
+        assert_eq!(
+            eval("8c 8d 8h 8s Ac"),
+            HandRank::FourOfAKind {
+                quads: Eight,
+                kicker: Ace
+            }
+        );
+        assert_eq!(
+            eval("5s 6s 7s 8s 9s"),
+            HandRank::StraightFlush { high: Nine }
+        );
+        assert_eq!(eval("Td Jd Qd Kd Ad"), HandRank::RoyalFlush);
+    }
+
+    #[test]
+    fn handles_tricky_hands() {
+        use Rank::*;
+        //the wheel is five-high, so it loses to a six-high straight
+        assert_eq!(eval("Ac 2d 3h 4s 5c Kd"), HandRank::Straight { high: Five });
+        assert!(eval("Ac 2d 3h 4s 5c") < eval("2c 3d 4h 5s 6c"));
+        assert_eq!(
+            eval("Ah 2h 3h 4h 5h 6c"),
+            HandRank::StraightFlush { high: Five }
+        );
+        //no wrapping around the ace
+        assert_eq!(eval("Qc Kd Ah 2s 3c").to_string(), "A high");
+
+        //the highest straight counts
+        assert_eq!(
+            eval("4c 5d 6h 7s 8c 9d Th"),
+            HandRank::Straight { high: Ten }
+        );
+        //a straight and a flush that aren't the same five cards aren't a straight flush
+        assert_eq!(
+            eval("4h 5h 6h 7h 8c 2h Kd"),
+            HandRank::Flush([Seven, Six, Five, Four, Two])
+        );
+        //a flush with six of the suit keeps the best five
+        assert_eq!(
+            eval("2s 4s 6s 8s Ts Qs 3c"),
+            HandRank::Flush([Queen, Ten, Eight, Six, Four])
+        );
+
+        //two sets of trips make a full house with the best possible pair
+        assert_eq!(
+            eval("7c 7d 7h 9s 9c 9d 2h"),
+            HandRank::FullHouse {
+                trips: Nine,
+                pair: Seven
+            }
+        );
+        //a flush beats a straight on the same cards
+        assert_eq!(
+            eval("4h 5h 6c 7h 8d 9h Kh"),
+            HandRank::Flush([King, Nine, Seven, Five, Four])
+        );
+
+        //three pairs only play the best two, and the third can be beaten by a higher single kicker
+        assert_eq!(
+            eval("Ac Ad Kc Kd 5c 5d Qh"),
+            HandRank::TwoPair {
+                high: Ace,
+                low: King,
+                kicker: Queen
+            }
+        );
+        assert_eq!(
+            eval("Ac Ad Kc Kd 5c 5d 3h"),
+            HandRank::TwoPair {
+                high: Ace,
+                low: King,
+                kicker: Five
+            }
+        );
+        //quads with trips on the side play the best single card
+        assert_eq!(
+            eval("3c 3d 3h 3s Kc Kd Kh"),
+            HandRank::FourOfAKind {
+                quads: Three,
+                kicker: King
+            }
+        );
+    }
+
+    #[test]
+    fn orders_by_kickers() {
+        //only the best five cards count, so the sixth and seventh can't break a tie
+        assert_eq!(eval("Ac Kd Qh Js 9c 3d 2h"), eval("Ad Kc Qs Jh 9d 4c 3s"));
+        assert!(eval("Ac Ad Kh 7s 5c") > eval("Ah As Qh Js Tc"));
+        assert!(eval("Kc Kd 9h 9s Ac") > eval("Kh Ks 9c 9d Qc"));
+        assert!(eval("Jc Jd 9h 9s 2c") > eval("Tc Td 9c 9d Ac"));
+        assert!(eval("2h 7h 9h Jh Kh") > eval("3h 6h 9h Jh Kh"));
+        assert!(eval("Qc Qd Qh 2s 2c") > eval("Jc Jd Jh As Ac"));
+        assert!(eval("Td Jd Qd Kd Ad") > eval("9d Td Jd Qd Kd"));
+        assert!(eval("2c 3d 4h 5s 7c") < eval("2d 3h 4s 6c 7d"));
+
+        //every category beats the one below it
+        let ladder = [
+            "Ac Kd Qh Js 9c",
+            "2c 2d 3h 4s 5c",
+            "2c 2d 3h 3s 4c",
+            "2c 2d 2h 3s 4c",
+            "Ac 2d 3h 4s 5c",
+            "2h 3h 4h 5h 7h",
+            "2c 2d 2h 3s 3c",
+            "2c 2d 2h 2s 3c",
+            "Ah 2h 3h 4h 5h",
+            "Td Jd Qd Kd Ad",
+        ];
+        for pair in ladder.windows(2) {
+            assert!(eval(pair[0]) < eval(pair[1]), "{} < {}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn rejects_impossible_hands() {
+        assert_eq!(
+            HandRank::evaluate(&cards("Ac Kd Qh Js")),
+            Err(HandEvalError::WrongNumberOfCards(4))
+        );
+        assert_eq!(
+            HandRank::evaluate(&cards("Ac Kd Qh Js Tc 9c 8c 7c")),
+            Err(HandEvalError::WrongNumberOfCards(8))
+        );
+        assert_eq!(
+            HandRank::evaluate(&cards("Ac Kd Qh Js Ac")),
+            Err(HandEvalError::DuplicateCard(Card {
+                rank: Rank::Ace,
+                suit: Suit::Clubs
+            }))
+        );
+    }
+}
@@ -1,148 +1,256 @@
-use crate::integer::{Integer, IntegerDeserialiser, IntegerReadError, SignedState};
-use crate::ser_glue::list::{ListDeserialiser, ListSer};
-use crate::ser_glue::tuple::{TupleDeserialiser, TupleReadError};
-use crate::ser_glue::uuid::UuidDeserialiser;
-use crate::ser_glue::{DeserMachine, Deserable, DesiredInput, FsmResult, Serable};
-use std::collections::HashMap;
-use std::fmt::{Display, Formatter};
+use crate::ser_glue::{Deserable, Serable};
+use std::collections::{BTreeMap, HashMap};
 use uuid::Uuid;
 
-#[derive(Debug, Default, Clone, Eq, PartialEq)]
+#[derive(Debug, Default, Clone, Eq, PartialEq, Serable, Deserable)]
 pub struct Pot {
+    ///Chips anyone still in can win. Once there are side pots, this only has what's been added since the last betting round closed.
     pub current_value: u32,
     pub ready_to_put_in: HashMap<Uuid, u32>,
+    ///Parts of the pot that were capped off because someone went all in, oldest (and so open to the most players) first.
+    pub side_pots: Vec<SidePot>,
 }
 
-impl Serable for Pot {
-    type ExtraOutput = ();
+///Part of the [`Pot`] that only some players can win, because it was capped off when someone couldn't put in any more.
+#[derive(Debug, Default, Clone, Hash, Eq, PartialEq, Serable, Deserable)]
+pub struct SidePot {
+    pub amount: u32,
+    ///Everyone who put in enough to win this, sorted. Players who've folded since are left in, but can't win it.
+    pub eligible: Vec<Uuid>,
+}
 
-    fn ser_into(&self, into: &mut Vec<u8>) -> Self::ExtraOutput {
-        Integer::from(self.current_value).ser_into(into);
+impl Pot {
+    ///Everything in the middle, including what hasn't been swept in yet.
+    #[must_use]
+    pub fn total(&self) -> u32 {
+        self.ready_to_put_in
+            .values()
+            .chain(self.side_pots.iter().map(|side_pot| &side_pot.amount))
+            .fold(self.current_value, |acc, value| acc.saturating_add(*value))
+    }
 
-        let to_put_in = self
-            .ready_to_put_in
+    ///Moves everything that's ready to be put in into the pot, giving back who put in how much.
+    ///
+    /// Anyone in `all_in` can only win as much from each player as they put in themselves, so the pot gets capped off into a [`SidePot`] at each of their bets. Once there are side pots, every betting round goes into a new one, so that players who went all in earlier can't win any of it.
+    pub fn close_betting_round(&mut self, all_in: &[Uuid]) -> HashMap<Uuid, u32> {
+        let swept = std::mem::take(&mut self.ready_to_put_in);
+        let mut levels: Vec<u32> = all_in
             .iter()
-            .map(|(uuid, value)| (*uuid, Integer::from(*value)))
-            .collect::<Vec<_>>();
+            .filter_map(|uuid| swept.get(uuid).copied())
+            .filter(|bet| *bet > 0)
+            .collect();
 
-        Integer::from(to_put_in.len()).ser_into(into);
-        ListSer(&to_put_in).ser_into(into); //can discard extras as we know they're constant
-    }
-}
+        if levels.is_empty() && self.side_pots.is_empty() {
+            for value in swept.values() {
+                self.current_value = self.current_value.saturating_add(*value);
+            }
+            return swept;
+        }
 
-#[derive(Debug)]
-pub enum PotDeserialiser {
-    ReadingPotSize(IntegerDeserialiser),
-    ReadingNumberOfPlayers(u32, IntegerDeserialiser),
-    ReadingSoFars(
-        u32,
-        ListDeserialiser<TupleDeserialiser<UuidDeserialiser, IntegerDeserialiser>>,
-    ),
-}
+        //whatever's left over above the biggest all in goes to whoever bet it
+        levels.extend(swept.values().max().filter(|bet| **bet > 0));
+        levels.sort_unstable();
+        levels.dedup();
 
-impl Deserable for Pot {
-    type Deserer = PotDeserialiser;
-}
+        let mut below = 0;
+        for level in levels {
+            let mut amount = std::mem::take(&mut self.current_value);
+            let mut eligible = vec![];
+            for (uuid, bet) in &swept {
+                amount = amount.saturating_add((*bet).min(level).saturating_sub(below));
+                if *bet >= level {
+                    eligible.push(*uuid);
+                }
+            }
+            eligible.sort_unstable();
+            below = level;
 
-#[derive(Debug)]
-pub enum PotReadError {
-    Integer(IntegerReadError),
-    //TODO: is this really the best way of doing this?
-    Player(TupleReadError<std::convert::Infallible, IntegerReadError>),
-}
-impl From<IntegerReadError> for PotReadError {
-    fn from(value: IntegerReadError) -> Self {
-        Self::Integer(value)
+            match self.side_pots.last_mut() {
+                Some(last) if last.eligible == eligible => {
+                    last.amount = last.amount.saturating_add(amount);
+                }
+                _ => self.side_pots.push(SidePot { amount, eligible }),
+            }
+        }
+        swept
     }
-}
-impl From<TupleReadError<std::convert::Infallible, IntegerReadError>> for PotReadError {
-    fn from(value: TupleReadError<std::convert::Infallible, IntegerReadError>) -> Self {
-        Self::Player(value)
+
+    ///Empties the pot, splitting it as evenly as possible between `winners`, whether or not they could win every side pot. Duplicate winners are ignored.
+    ///
+    /// Leftover chips go one each to the winners with the lowest UUIDs, so the result doesn't depend on the order `winners` is in. If there are no winners, the pot is left alone.
+    pub fn split_between(&mut self, winners: &[Uuid]) -> Vec<(Uuid, u32)> {
+        self.award(&[winners.to_vec()])
     }
-}
-impl Display for PotReadError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::Integer(int) => write!(f, "Error parsing integer: {int}"),
-            Self::Player(player) => write!(f, "Error parsing player: {player}"),
+
+    ///Empties the pot, giving each part of it to the best players who can win it, and gives back how much everyone won in total, sorted by UUID.
+    ///
+    /// `ranking` has everyone who's still in, grouped by how good their hand is, best first. Each side pot goes to the best group with someone eligible for it, split between everyone in that group who is. Anything no-one in `ranking` can win, like a side pot where everyone eligible has left, goes to the best group instead. If `ranking` is empty, the pot is left alone.
+    pub fn award(&mut self, ranking: &[Vec<Uuid>]) -> Vec<(Uuid, u32)> {
+        let Some(best) = ranking.iter().find(|group| !group.is_empty()) else {
+            return vec![];
+        };
+
+        let mut won = split(std::mem::take(&mut self.current_value), best);
+        for side_pot in std::mem::take(&mut self.side_pots) {
+            let winners = ranking
+                .iter()
+                .map(|group| {
+                    group
+                        .iter()
+                        .filter(|uuid| side_pot.eligible.contains(uuid))
+                        .copied()
+                        .collect::<Vec<_>>()
+                })
+                .find(|winners| !winners.is_empty())
+                .unwrap_or_else(|| best.clone());
+            won.extend(split(side_pot.amount, &winners));
         }
+
+        let mut totals: BTreeMap<Uuid, u32> = BTreeMap::new();
+        for (uuid, amount) in won {
+            let total = totals.entry(uuid).or_default();
+            *total = total.saturating_add(amount);
+        }
+        totals
+            .into_iter()
+            .filter(|(_, amount)| *amount > 0)
+            .collect()
     }
 }
-impl std::error::Error for PotReadError {
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        match self {
-            Self::Integer(int) => Some(int),
-            Self::Player(player) => Some(player),
-        }
+
+///Splits `value` as evenly as possible between `winners`, with leftover chips going one each to the lowest UUIDs.
+fn split(value: u32, winners: &[Uuid]) -> Vec<(Uuid, u32)> {
+    let mut winners = winners.to_vec();
+    winners.sort_unstable();
+    winners.dedup();
+
+    let Ok(n) = u32::try_from(winners.len()) else {
+        return vec![]; //can't have more than u32::MAX players with chips anyway
+    };
+    if n == 0 {
+        return vec![];
     }
+
+    let share = value / n;
+    let remainder = value % n;
+    winners
+        .into_iter()
+        .zip(0..)
+        .map(|(uuid, i)| (uuid, share + u32::from(i < remainder)))
+        .collect()
 }
 
-impl DeserMachine for PotDeserialiser {
-    type ExtraInput = ();
-    type Output = Pot;
-    type Error = PotReadError;
+#[cfg(test)]
+mod tests {
+    use crate::game_types::pot::{Pot, SidePot};
+    use std::collections::HashMap;
+    use uuid::Uuid;
+
+    #[test]
+    fn closing_round_sweeps_into_pot() {
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut pot = Pot {
+            current_value: 5,
+            ready_to_put_in: HashMap::from([(a, 10), (b, 20)]),
+            ..Pot::default()
+        };
 
-    fn new() -> Self {
-        Self::ReadingPotSize(Integer::deser_with_input(SignedState::Unsigned))
+        assert_eq!(
+            pot.close_betting_round(&[]),
+            HashMap::from([(a, 10), (b, 20)])
+        );
+        assert_eq!(pot.current_value, 35);
+        assert!(pot.ready_to_put_in.is_empty());
+        assert!(pot.side_pots.is_empty());
     }
 
-    fn wants_read(&mut self) -> DesiredInput<'_> {
-        match self {
-            Self::ReadingPotSize(deser) | Self::ReadingNumberOfPlayers(_, deser) => {
-                deser.wants_read()
-            }
-            Self::ReadingSoFars(_, deser) => deser.wants_read(),
-        }
+    #[test]
+    fn caps_side_pots_at_all_ins() {
+        let mut uuids: Vec<_> = (0..4).map(|_| Uuid::new_v4()).collect();
+        uuids.sort_unstable();
+        let [short, medium, a, b] = uuids[..] else {
+            unreachable!()
+        };
+
+        let mut pot = Pot {
+            current_value: 40,
+            ready_to_put_in: HashMap::from([(short, 50), (medium, 80), (a, 100), (b, 100)]),
+            ..Pot::default()
+        };
+        pot.close_betting_round(&[short, medium]);
+        assert_eq!(pot.current_value, 0);
+        assert_eq!(
+            pot.side_pots,
+            [
+                SidePot {
+                    amount: 240,
+                    eligible: vec![short, medium, a, b],
+                },
+                SidePot {
+                    amount: 90,
+                    eligible: vec![medium, a, b],
+                },
+                SidePot {
+                    amount: 40,
+                    eligible: vec![a, b],
+                },
+            ]
+        );
+
+        //later betting rounds are only between whoever's left
+        pot.ready_to_put_in = HashMap::from([(a, 30), (b, 30)]);
+        pot.close_betting_round(&[]);
+        assert_eq!(pot.side_pots.len(), 3);
+        assert_eq!(pot.side_pots[2].amount, 100);
+        assert_eq!(pot.total(), 430);
+
+        //the short stack wins what they can, then the next best hand takes the rest
+        assert_eq!(
+            pot.award(&[vec![short], vec![a, b], vec![medium]]),
+            [(short, 240), (a, 95), (b, 95)]
+        );
+        assert_eq!(pot.total(), 0);
+        assert!(pot.side_pots.is_empty());
     }
 
-    fn give_starting_input(&mut self, (): Self::ExtraInput) {}
+    #[test]
+    fn refunds_uncalled_all_ins() {
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut pot = Pot {
+            ready_to_put_in: HashMap::from([(a, 300), (b, 100)]),
+            ..Pot::default()
+        };
+        pot.close_betting_round(&[a, b]);
 
-    fn finish_bytes_for_writing(&mut self, n: usize) {
-        match self {
-            Self::ReadingPotSize(deser) | Self::ReadingNumberOfPlayers(_, deser) => {
-                deser.finish_bytes_for_writing(n)
-            }
-            Self::ReadingSoFars(_, deser) => deser.finish_bytes_for_writing(n),
-        }
+        //b can only win what they could match, and nobody matched the rest of a's bet
+        assert_eq!(pot.award(&[vec![b], vec![a]]), {
+            let mut won = vec![(a, 200), (b, 200)];
+            won.sort_unstable();
+            won
+        });
     }
 
-    fn process(self) -> Result<FsmResult<Self, Self::Output>, Self::Error> {
-        match self {
-            Self::ReadingPotSize(deser) => match deser.process()? {
-                FsmResult::Continue(deser) => Ok(FsmResult::Continue(Self::ReadingPotSize(deser))),
-                FsmResult::Done(pot) => {
-                    let pot = pot.try_into()?;
-                    Ok(FsmResult::Continue(Self::ReadingNumberOfPlayers(
-                        pot,
-                        Integer::deser_with_input(SignedState::Unsigned),
-                    )))
-                }
-            },
-            Self::ReadingNumberOfPlayers(pot, deser) => match deser.process()? {
-                FsmResult::Continue(deser) => Ok(FsmResult::Continue(
-                    Self::ReadingNumberOfPlayers(pot, deser),
-                )),
-                FsmResult::Done(number_in_pot) => Ok(FsmResult::Continue(Self::ReadingSoFars(
-                    pot,
-                    ListDeserialiser::new_with_starting_input(vec![
-                        ((), SignedState::Unsigned);
-                        number_in_pot.try_into()?
-                    ]),
-                ))),
-            },
-            Self::ReadingSoFars(current_value, deser) => match deser.process()? {
-                FsmResult::Continue(deser) => Ok(FsmResult::Continue(Self::ReadingSoFars(
-                    current_value,
-                    deser,
-                ))),
-                FsmResult::Done(list) => Ok(FsmResult::Done(Pot {
-                    current_value,
-                    ready_to_put_in: list
-                        .into_iter()
-                        .map(|(player, amt)| amt.try_into().map(|amt| (player, amt)))
-                        .collect::<Result<HashMap<_, _>, _>>()?,
-                })),
-            },
-        }
+    #[test]
+    fn splits_remainder_deterministically() {
+        let mut uuids: Vec<_> = (0..3).map(|_| Uuid::new_v4()).collect();
+        uuids.sort_unstable();
+
+        let mut pot = Pot {
+            current_value: 101,
+            ..Pot::default()
+        };
+        let reversed: Vec<_> = uuids.iter().rev().copied().collect();
+        assert_eq!(
+            pot.split_between(&reversed),
+            [(uuids[0], 34), (uuids[1], 34), (uuids[2], 33)]
+        );
+        assert_eq!(pot.current_value, 0);
+
+        let mut pot = Pot {
+            current_value: 7,
+            ..Pot::default()
+        };
+        assert_eq!(pot.split_between(&[uuids[1], uuids[1]]), [(uuids[1], 7)]);
+        assert!(pot.split_between(&[]).is_empty());
     }
 }
@@ -0,0 +1,33 @@
+use crate::ser_glue::{Deserable, Serable};
+use std::fmt::{Display, Formatter};
+
+///Identifies one table on a server. Picked by the server, and never re-used while it's running.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Serable, Deserable)]
+pub struct TableId(pub u32);
+
+impl TableId {
+    ///Made when the server starts, so there's always somewhere to sit.
+    pub const MAIN: Self = Self(0);
+}
+
+impl Display for TableId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+///What someone in the lobby needs to know to pick a table.
+#[derive(Debug, Clone, Hash, Eq, PartialEq, Serable, Deserable)]
+pub struct TableSummary {
+    pub id: TableId,
+    pub name: String,
+    pub player_count: u32,
+    ///Everything in the middle, including chips that haven't been swept in yet.
+    pub pot_size: u32,
+}
+
+impl Display for TableSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} ({})", self.name, self.id)
+    }
+}
@@ -2,9 +2,10 @@
 //yoinked from souris lol
 
 use crate::display_bytes_as_hex_array;
-use crate::ser_glue::{DeserMachine, Deserable, DesiredInput, FsmResult, Serable};
+use crate::ser_glue::{DecodeLimits, DeserMachine, Deserable, DesiredInput, FsmResult, Serable};
 use std::fmt::{Debug, Display, Formatter};
 use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
 
 ///This represents whether a number is signed or unsigned. There are conversions to/from [`u8`]s which use two bytes.
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
@@ -392,7 +393,7 @@ impl DeserMachine for IntegerDeserialiser {
     type Output = Integer;
     type Error = IntegerReadError;
 
-    fn new() -> Self {
+    fn new(_: DecodeLimits) -> Self {
         Self::Start
     }
 
@@ -505,3 +506,118 @@ impl DeserMachine for IntegerDeserialiser {
         }
     }
 }
+
+///Deserialiser for the primitive integers, which get serialised as an [`Integer`].
+///
+/// Unsigned integers always use [`SignedState::Unsigned`], so need no extra input. Signed integers need the [`SignedState`] given back from serialising them.
+#[derive(Debug)]
+pub struct PrimitiveDeserialiser<T> {
+    deser: IntegerDeserialiser,
+    _phantom: PhantomData<T>,
+}
+
+macro_rules! unsigned_ser_glue {
+    ($($t:ty),+) => {
+        $(
+        impl Serable for $t {
+            type ExtraOutput = ();
+
+            fn ser_into(&self, into: &mut Vec<u8>) -> Self::ExtraOutput {
+                Integer::from(*self).ser_into(into);
+            }
+        }
+
+        impl Deserable for $t {
+            type Deserer = PrimitiveDeserialiser<$t>;
+        }
+
+        impl DeserMachine for PrimitiveDeserialiser<$t> {
+            type ExtraInput = ();
+            type Output = $t;
+            type Error = IntegerReadError;
+
+            fn new(limits: DecodeLimits) -> Self {
+                Self {
+                    deser: IntegerDeserialiser::new_with_starting_input(SignedState::Unsigned, limits),
+                    _phantom: PhantomData,
+                }
+            }
+
+            fn wants_read(&mut self) -> DesiredInput<'_> {
+                self.deser.wants_read()
+            }
+
+            fn give_starting_input(&mut self, (): Self::ExtraInput) {}
+
+            fn finish_bytes_for_writing(&mut self, n: usize) {
+                self.deser.finish_bytes_for_writing(n);
+            }
+
+            fn process(self) -> Result<FsmResult<Self, Self::Output>, Self::Error> {
+                match self.deser.process()? {
+                    FsmResult::Continue(deser) => Ok(FsmResult::Continue(Self {
+                        deser,
+                        _phantom: PhantomData,
+                    })),
+                    FsmResult::Done(int) => Ok(FsmResult::Done(int.try_into()?)),
+                }
+            }
+        }
+        )+
+    };
+}
+macro_rules! signed_ser_glue {
+    ($($t:ty),+) => {
+        $(
+        impl Serable for $t {
+            type ExtraOutput = SignedState;
+
+            fn ser_into(&self, into: &mut Vec<u8>) -> Self::ExtraOutput {
+                Integer::from(*self).ser_into(into)
+            }
+        }
+
+        impl Deserable for $t {
+            type Deserer = PrimitiveDeserialiser<$t>;
+        }
+
+        impl DeserMachine for PrimitiveDeserialiser<$t> {
+            type ExtraInput = SignedState;
+            type Output = $t;
+            type Error = IntegerReadError;
+
+            fn new(limits: DecodeLimits) -> Self {
+                Self {
+                    deser: IntegerDeserialiser::new(limits),
+                    _phantom: PhantomData,
+                }
+            }
+
+            fn wants_read(&mut self) -> DesiredInput<'_> {
+                self.deser.wants_read()
+            }
+
+            fn give_starting_input(&mut self, state: Self::ExtraInput) {
+                self.deser.give_starting_input(state);
+            }
+
+            fn finish_bytes_for_writing(&mut self, n: usize) {
+                self.deser.finish_bytes_for_writing(n);
+            }
+
+            fn process(self) -> Result<FsmResult<Self, Self::Output>, Self::Error> {
+                match self.deser.process()? {
+                    FsmResult::Continue(deser) => Ok(FsmResult::Continue(Self {
+                        deser,
+                        _phantom: PhantomData,
+                    })),
+                    FsmResult::Done(int) => Ok(FsmResult::Done(int.try_into()?)),
+                }
+            }
+        }
+        )+
+    };
+}
+
+unsigned_ser_glue!(u8, u16, u32, u64, usize, u128);
+signed_ser_glue!(i8, i16, i32, i64, isize, i128);
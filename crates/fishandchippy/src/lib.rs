@@ -1,5 +1,8 @@
 #![warn(clippy::all, clippy::nursery, clippy::pedantic)]
 
+//lets the derive macros refer to `::fishandchippy` from inside this crate too
+extern crate self as fishandchippy;
+
 pub mod events;
 pub mod game_types;
 pub mod integer;
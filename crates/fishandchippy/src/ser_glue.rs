@@ -1,7 +1,18 @@
-use std::fmt::Debug;
+use std::fmt::{Debug, Display, Formatter};
 
+pub use driver::Driver;
+pub use fishandchippy_derive::{Deserable, Serable};
+pub use io::ReadFromError;
+pub use limits::DecodeLimits;
+
+#[cfg(feature = "tokio")]
+pub mod codec;
+pub mod driver;
+pub mod io;
+pub mod limits;
 pub mod list;
 pub mod map;
+pub mod option;
 pub mod string;
 pub mod tuple;
 pub mod uuid;
@@ -16,6 +27,16 @@ pub trait Serable {
         (extra, vec)
     }
     fn ser_into(&self, into: &mut Vec<u8>) -> Self::ExtraOutput;
+
+    ///Serialises `self` and writes all of the bytes out to `writer`.
+    ///
+    /// # Errors
+    /// Gives back any error from writing.
+    fn ser_to_writer(&self, mut writer: impl std::io::Write) -> std::io::Result<Self::ExtraOutput> {
+        let (extra, bytes) = self.ser();
+        writer.write_all(&bytes)?;
+        Ok(extra)
+    }
 }
 
 //ty amos: https://fasterthanli.me/articles/the-case-for-sans-io#the-structure-of-rc-zip
@@ -39,13 +60,25 @@ pub enum DesiredInput<'a> {
 pub trait Deserable {
     type Deserer: DeserMachine<Output = Self>;
 
+    ///Starts deserialising with [`DecodeLimits::default`] - see [`Deserable::deser_limited`] for untrusted input.
     #[must_use]
     fn deser() -> Self::Deserer {
-        Self::Deserer::new()
+        Self::deser_limited(DecodeLimits::default())
+    }
+    #[must_use]
+    fn deser_limited(limits: DecodeLimits) -> Self::Deserer {
+        Self::Deserer::new(limits)
     }
     #[must_use]
     fn deser_with_input(input: <Self::Deserer as DeserMachine>::ExtraInput) -> Self::Deserer {
-        Self::Deserer::new_with_starting_input(input)
+        Self::Deserer::new_with_starting_input(input, DecodeLimits::default())
+    }
+    #[must_use]
+    fn driver() -> Driver<Self::Deserer>
+    where
+        Self::Deserer: DeserMachine<ExtraInput = ()>,
+    {
+        Driver::new()
     }
 }
 
@@ -54,9 +87,10 @@ pub trait DeserMachine: Sized {
     type Output;
     type Error: std::error::Error;
 
-    fn new() -> Self;
-    fn new_with_starting_input(input: Self::ExtraInput) -> Self {
-        let mut s = Self::new();
+    ///Makes a new state machine, which should pass `limits` on to any state machines it makes.
+    fn new(limits: DecodeLimits) -> Self;
+    fn new_with_starting_input(input: Self::ExtraInput, limits: DecodeLimits) -> Self {
+        let mut s = Self::new(limits);
         s.give_starting_input(input);
         s
     }
@@ -77,4 +111,101 @@ pub trait DeserMachine: Sized {
             FsmResult::Done(done) => Ok(FsmResult::Done(done_variant(done))),
         }
     }
+
+    ///Drives this state machine to completion using bytes from `reader`, reading no further than the end of the value.
+    ///
+    /// Any starting input must already have been given. [`DecodeLimits::max_message_bytes`] isn't checked here, so use [`std::io::Read::take`] for that.
+    ///
+    /// # Errors
+    /// See [`ReadFromError`].
+    fn read_from(
+        self,
+        reader: impl std::io::Read,
+    ) -> Result<Self::Output, ReadFromError<Self::Error>> {
+        io::read_from(self, reader)
+    }
+}
+
+///Error used by derived enum deserialisers when they find a discriminant that doesn't match any variant.
+///
+/// Only needed when using `#[ser_glue(error = ...)]`, as that error needs to implement `From<InvalidDiscriminant>`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct InvalidDiscriminant(pub u8);
+
+impl Display for InvalidDiscriminant {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid discriminant found: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidDiscriminant {}
+
+#[cfg(test)]
+mod tests {
+    use crate::integer::{Integer, SignedState};
+    use crate::ser_glue::{DeserMachine, Deserable, DesiredInput, Driver, Serable};
+
+    #[derive(Debug, PartialEq, Eq, Serable, Deserable)]
+    struct WithExtras {
+        label: String,
+        offset: i32,
+        big: Integer,
+    }
+
+    #[derive(Debug, PartialEq, Eq, Serable, Deserable)]
+    struct Unit;
+
+    #[derive(Debug, PartialEq, Eq, Serable, Deserable)]
+    enum Shapes {
+        Nothing,
+        #[ser_glue(discriminant = 10)]
+        Pair(u8, Vec<String>),
+        Named {
+            unit: Unit,
+        },
+    }
+
+    #[test]
+    fn derive_passes_extras_through() {
+        let example = WithExtras {
+            label: "negative".to_string(),
+            offset: -12_345,
+            big: Integer::from(i128::MIN),
+        };
+        let (extras, bytes) = example.ser();
+        assert_eq!(
+            extras,
+            (SignedState::SignedNegative, SignedState::SignedNegative)
+        );
+
+        let found = Driver::<WithExtrasDeserialiser>::with_starting_input(extras)
+            .feed_all(&bytes)
+            .unwrap();
+        assert_eq!(found, [example]);
+    }
+
+    #[test]
+    fn derive_enums_and_unit_structs() {
+        for example in [
+            Shapes::Nothing,
+            Shapes::Pair(250, vec!["a".to_string(), String::new()]),
+            Shapes::Named { unit: Unit },
+        ] {
+            let ((), bytes) = example.ser();
+            assert_eq!(Shapes::driver().feed_all(&bytes).unwrap(), [example]);
+        }
+
+        assert_eq!(Shapes::Pair(0, vec![]).ser().1[0], 10);
+
+        let mut deserer = Shapes::deser();
+        let DesiredInput::Byte(space) = deserer.wants_read() else {
+            panic!("enums should start by reading their discriminant");
+        };
+        *space = 3;
+        deserer.finish_bytes_for_writing(1);
+        assert!(matches!(
+            deserer.process(),
+            Err(ShapesReadError::InvalidDiscriminant(3))
+        ));
+    }
 }
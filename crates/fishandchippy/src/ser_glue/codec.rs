@@ -0,0 +1,135 @@
+use crate::ser_glue::{DecodeLimits, DeserMachine, Deserable, Driver, ReadFromError, Serable};
+use bytes::{Buf, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+///A [`Decoder`] for `D` and [`Encoder`] for anything [`Serable`], so one codec can be used for both directions of a stream.
+///
+/// Decoding is driven by `D`'s [`DeserMachine`], so partial values are kept inside the codec rather than left in the buffer.
+pub struct FsmCodec<D: Deserable> {
+    driver: Driver<D::Deserer>,
+}
+
+impl<D: Deserable> std::fmt::Debug for FsmCodec<D>
+where
+    Driver<D::Deserer>: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FsmCodec")
+            .field("driver", &self.driver)
+            .finish()
+    }
+}
+
+impl<D> FsmCodec<D>
+where
+    D: Deserable,
+    D::Deserer: DeserMachine<ExtraInput = ()>,
+{
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            driver: D::driver(),
+        }
+    }
+
+    ///See [`Driver::with_limits`].
+    #[must_use]
+    pub fn with_limits(limits: DecodeLimits) -> Self {
+        Self {
+            driver: D::driver().with_limits(limits),
+        }
+    }
+}
+
+impl<D> Default for FsmCodec<D>
+where
+    D: Deserable,
+    D::Deserer: DeserMachine<ExtraInput = ()>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<D> Decoder for FsmCodec<D>
+where
+    D: Deserable,
+    D::Deserer: DeserMachine<ExtraInput = ()>,
+{
+    type Item = D;
+    type Error = ReadFromError<<D::Deserer as DeserMachine>::Error>;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let mut feed = self.driver.feed(src);
+        let found = feed.next();
+        let consumed = feed.consumed();
+        src.advance(consumed);
+
+        Ok(found.transpose()?)
+    }
+
+    fn decode_eof(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let found = self.decode(buf)?;
+        if found.is_none() {
+            self.driver.finish()?;
+        }
+        Ok(found)
+    }
+}
+
+impl<D: Deserable, T: Serable<ExtraOutput = ()>> Encoder<T> for FsmCodec<D> {
+    type Error = std::io::Error;
+
+    fn encode(&mut self, item: T, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let ((), bytes) = item.ser();
+        dst.extend_from_slice(&bytes);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::events::client::EventToClient;
+    use crate::events::server::EventToServer;
+    use crate::ser_glue::ReadFromError;
+    use crate::ser_glue::codec::FsmCodec;
+    use bytes::BytesMut;
+    use tokio_util::codec::{Decoder, Encoder};
+
+    #[test]
+    fn decodes_across_partial_buffers() {
+        let events = [
+            EventToServer::Introduction {
+                name: "Ferris".to_string(),
+            },
+            EventToServer::AddToPot(1_000),
+            EventToServer::GetStartInformation { request_id: None },
+        ];
+
+        let mut encoded = BytesMut::new();
+        for evt in events.clone() {
+            FsmCodec::<EventToClient>::new()
+                .encode(evt, &mut encoded)
+                .unwrap();
+        }
+
+        let mut codec = FsmCodec::<EventToServer>::new();
+        let mut buf = BytesMut::new();
+        let mut found = vec![];
+        for &byte in &encoded {
+            buf.extend_from_slice(&[byte]);
+            while let Some(evt) = codec.decode(&mut buf).unwrap() {
+                found.push(evt);
+            }
+            assert!(buf.is_empty());
+        }
+        assert_eq!(found, events);
+        assert!(codec.decode_eof(&mut buf).unwrap().is_none());
+
+        let mut buf = BytesMut::from(&encoded[..3]);
+        assert!(matches!(
+            codec.decode_eof(&mut buf),
+            Err(ReadFromError::Truncated { .. })
+        ));
+    }
+}
@@ -0,0 +1,291 @@
+use crate::ser_glue::{DecodeLimits, DeserMachine, DesiredInput, FsmResult};
+use std::fmt::{Display, Formatter};
+
+///Feeds byte slices into a [`DeserMachine`], starting a new one each time a value is finished.
+///
+/// Partially read values are kept between calls, so input can arrive in arbitrarily sized chunks. Every value must take up at least one byte.
+#[derive(Debug)]
+pub struct Driver<D: DeserMachine> {
+    //only `None` while being processed
+    deserer: Option<D>,
+    starting_input: D::ExtraInput,
+    limits: DecodeLimits,
+    bytes_into_value: usize,
+}
+
+#[derive(Debug)]
+pub enum DriverError<E> {
+    ///The state machine rejected the input.
+    Deser(E),
+    ///The input ended part of the way through a value.
+    Truncated { bytes_into_value: usize },
+    ///The value went over [`DecodeLimits::max_message_bytes`].
+    TooLong { max_message_bytes: usize },
+}
+
+impl<E: Display> Display for DriverError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Deser(e) => write!(f, "Error deserialising value: {e}"),
+            Self::Truncated { bytes_into_value } => {
+                write!(f, "Input ended {bytes_into_value} byte(s) into a value")
+            }
+            Self::TooLong { max_message_bytes } => {
+                write!(
+                    f,
+                    "Value went over the limit of {max_message_bytes} byte(s)"
+                )
+            }
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for DriverError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Deser(e) => Some(e),
+            Self::Truncated { .. } | Self::TooLong { .. } => None,
+        }
+    }
+}
+
+impl<D: DeserMachine<ExtraInput = ()>> Driver<D> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_starting_input(())
+    }
+}
+
+impl<D: DeserMachine<ExtraInput = ()>> Default for Driver<D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<D> Driver<D>
+where
+    D: DeserMachine,
+    D::ExtraInput: Clone,
+{
+    ///Creates a driver which gives every new state machine a copy of `input`.
+    pub fn with_starting_input(input: D::ExtraInput) -> Self {
+        let limits = DecodeLimits::default();
+        Self {
+            deserer: Some(D::new_with_starting_input(input.clone(), limits)),
+            starting_input: input,
+            limits,
+            bytes_into_value: 0,
+        }
+    }
+
+    ///Replaces the limits given to each state machine, and checks [`DecodeLimits::max_message_bytes`] against each value.
+    ///
+    /// Any partially read value is thrown away.
+    #[must_use]
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self.reset();
+        self
+    }
+
+    ///Whether the driver is between values, ie. nothing has been read for the current one.
+    #[must_use]
+    pub const fn is_at_boundary(&self) -> bool {
+        self.bytes_into_value == 0
+    }
+
+    ///Checks that no partially read value is left over.
+    ///
+    /// # Errors
+    /// Gives back [`DriverError::Truncated`] if the input stopped in the middle of a value.
+    pub const fn finish(&self) -> Result<(), DriverError<D::Error>> {
+        if self.is_at_boundary() {
+            Ok(())
+        } else {
+            Err(DriverError::Truncated {
+                bytes_into_value: self.bytes_into_value,
+            })
+        }
+    }
+
+    ///Reads values out of `bytes`, keeping any partial value around for the next call.
+    pub const fn feed<'d, 'b>(&'d mut self, bytes: &'b [u8]) -> Feed<'d, 'b, D> {
+        Feed {
+            driver: self,
+            bytes,
+            consumed: 0,
+        }
+    }
+
+    ///Reads all of the values out of `bytes`, which must end on a boundary.
+    ///
+    /// # Errors
+    /// Gives back the first error from the state machine, or [`DriverError::Truncated`] if there were bytes left over.
+    pub fn feed_all(&mut self, bytes: &[u8]) -> Result<Vec<D::Output>, DriverError<D::Error>> {
+        let found = self.feed(bytes).collect::<Result<Vec<_>, _>>()?;
+        self.finish()?;
+        Ok(found)
+    }
+
+    fn reset(&mut self) {
+        self.deserer = Some(D::new_with_starting_input(
+            self.starting_input.clone(),
+            self.limits,
+        ));
+        self.bytes_into_value = 0;
+    }
+
+    ///Counts bytes given to the current value, starting afresh if it has gone over the limit.
+    fn add_bytes(&mut self, n: usize) -> Result<(), DriverError<D::Error>> {
+        self.bytes_into_value += n;
+        if self.bytes_into_value > self.limits.max_message_bytes {
+            self.reset();
+            Err(DriverError::TooLong {
+                max_message_bytes: self.limits.max_message_bytes,
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+///Iterator over the values completed by one chunk of bytes - see [`Driver::feed`].
+///
+/// Once a value fails to deserialise or goes over the size limit, the driver starts afresh from the next byte. That is unlikely to be the start of a value, so most callers should give up on the stream.
+pub struct Feed<'d, 'b, D: DeserMachine> {
+    driver: &'d mut Driver<D>,
+    bytes: &'b [u8],
+    consumed: usize,
+}
+
+impl<D: DeserMachine> Feed<'_, '_, D> {
+    ///How many bytes from the chunk have been handed to the state machine so far.
+    #[must_use]
+    pub const fn consumed(&self) -> usize {
+        self.consumed
+    }
+}
+
+impl<D> Iterator for Feed<'_, '_, D>
+where
+    D: DeserMachine,
+    D::ExtraInput: Clone,
+{
+    type Item = Result<D::Output, DriverError<D::Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let remaining = &self.bytes[self.consumed..];
+            if remaining.is_empty() && self.driver.is_at_boundary() {
+                return None;
+            }
+
+            let deserer = self.driver.deserer.as_mut()?;
+            match deserer.wants_read() {
+                DesiredInput::Byte(space) => {
+                    let &byte = remaining.first()?;
+                    *space = byte;
+                    deserer.finish_bytes_for_writing(1);
+                    self.consumed += 1;
+                    if let Err(e) = self.driver.add_bytes(1) {
+                        return Some(Err(e));
+                    }
+                }
+                DesiredInput::Bytes(space) => {
+                    if remaining.is_empty() {
+                        return None;
+                    }
+                    let n = space.len().min(remaining.len());
+                    space[..n].copy_from_slice(&remaining[..n]);
+                    deserer.finish_bytes_for_writing(n);
+                    self.consumed += n;
+                    if let Err(e) = self.driver.add_bytes(n) {
+                        return Some(Err(e));
+                    }
+                }
+                DesiredInput::ProcessMe => {
+                    let deserer = self.driver.deserer.take()?;
+                    match deserer.process() {
+                        Ok(FsmResult::Continue(deserer)) => self.driver.deserer = Some(deserer),
+                        Ok(FsmResult::Done(value)) => {
+                            self.driver.reset();
+                            return Some(Ok(value));
+                        }
+                        Err(e) => {
+                            self.driver.reset();
+                            return Some(Err(DriverError::Deser(e)));
+                        }
+                    }
+                }
+                DesiredInput::Extra => unreachable!("starting input is always given"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::events::server::EventToServer;
+    use crate::ser_glue::driver::DriverError;
+    use crate::ser_glue::{Deserable, Serable};
+
+    fn example_bytes() -> (Vec<EventToServer>, Vec<u8>) {
+        let events = vec![
+            EventToServer::Introduction {
+                name: "Frédéric".to_string(),
+            },
+            EventToServer::AddToPot(70_000),
+            EventToServer::GetStartInformation { request_id: None },
+        ];
+        let mut bytes = vec![];
+        for e in &events {
+            e.ser_into(&mut bytes);
+        }
+        (events, bytes)
+    }
+
+    #[test]
+    fn keeps_state_across_chunks() {
+        let (events, bytes) = example_bytes();
+
+        for chunk_size in 1..bytes.len() {
+            let mut driver = EventToServer::driver();
+            let mut found = vec![];
+            for chunk in bytes.chunks(chunk_size) {
+                let mut feed = driver.feed(chunk);
+                for evt in feed.by_ref() {
+                    found.push(evt.unwrap());
+                }
+                assert_eq!(feed.consumed(), chunk.len());
+            }
+            driver.finish().unwrap();
+            assert_eq!(found, events);
+        }
+    }
+
+    #[test]
+    fn reports_truncation() {
+        let (events, bytes) = example_bytes();
+        let mut driver = EventToServer::driver();
+
+        let found = driver
+            .feed(&bytes[..bytes.len() - 4])
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(found, events[..1]);
+        assert!(!driver.is_at_boundary());
+        assert!(matches!(
+            driver.finish(),
+            Err(DriverError::Truncated {
+                bytes_into_value: 3
+            })
+        ));
+
+        assert!(matches!(
+            EventToServer::driver().feed_all(&bytes[..1]),
+            Err(DriverError::Truncated {
+                bytes_into_value: 1
+            })
+        ));
+    }
+}
@@ -0,0 +1,179 @@
+use crate::ser_glue::driver::DriverError;
+use crate::ser_glue::{DeserMachine, DesiredInput, FsmResult};
+use std::fmt::{Display, Formatter};
+use std::io::{ErrorKind, Read};
+
+#[derive(Debug)]
+pub enum ReadFromError<E> {
+    ///The reader failed.
+    Io(std::io::Error),
+    ///The state machine rejected the input.
+    Deser(E),
+    ///The reader ended before any of the value was read.
+    Eof,
+    ///The reader ended part of the way through a value.
+    Truncated { bytes_into_value: usize },
+    ///The state machine asked for extra input part of the way through.
+    MissingExtra,
+    ///The value went over [`DecodeLimits::max_message_bytes`](crate::ser_glue::DecodeLimits::max_message_bytes).
+    TooLong { max_message_bytes: usize },
+}
+
+impl<E: Display> Display for ReadFromError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Error reading input: {e}"),
+            Self::Deser(e) => write!(f, "Error deserialising value: {e}"),
+            Self::Eof => write!(f, "Input ended before a value started"),
+            Self::Truncated { bytes_into_value } => {
+                write!(f, "Input ended {bytes_into_value} byte(s) into a value")
+            }
+            Self::MissingExtra => write!(f, "State machine wanted extra input"),
+            Self::TooLong { max_message_bytes } => {
+                write!(
+                    f,
+                    "Value went over the limit of {max_message_bytes} byte(s)"
+                )
+            }
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for ReadFromError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Deser(e) => Some(e),
+            Self::Eof | Self::Truncated { .. } | Self::MissingExtra | Self::TooLong { .. } => None,
+        }
+    }
+}
+
+impl<E> From<std::io::Error> for ReadFromError<E> {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl<E> From<DriverError<E>> for ReadFromError<E> {
+    fn from(value: DriverError<E>) -> Self {
+        match value {
+            DriverError::Deser(e) => Self::Deser(e),
+            DriverError::Truncated { bytes_into_value } => Self::Truncated { bytes_into_value },
+            DriverError::TooLong { max_message_bytes } => Self::TooLong { max_message_bytes },
+        }
+    }
+}
+
+///See [`DeserMachine::read_from`].
+pub(super) fn read_from<D: DeserMachine>(
+    mut deserer: D,
+    mut reader: impl Read,
+) -> Result<D::Output, ReadFromError<D::Error>> {
+    let mut bytes_into_value = 0;
+
+    loop {
+        let n = match deserer.wants_read() {
+            DesiredInput::Byte(space) => read_some(&mut reader, std::slice::from_mut(space))?,
+            DesiredInput::Bytes(space) => read_some(&mut reader, space)?,
+            DesiredInput::ProcessMe => {
+                match deserer.process().map_err(ReadFromError::Deser)? {
+                    FsmResult::Continue(d) => deserer = d,
+                    FsmResult::Done(value) => return Ok(value),
+                }
+                continue;
+            }
+            DesiredInput::Extra => return Err(ReadFromError::MissingExtra),
+        };
+
+        if n == 0 {
+            return Err(if bytes_into_value == 0 {
+                ReadFromError::Eof
+            } else {
+                ReadFromError::Truncated { bytes_into_value }
+            });
+        }
+        deserer.finish_bytes_for_writing(n);
+        bytes_into_value += n;
+    }
+}
+
+///Reads into `space`, retrying if interrupted. `0` means the reader has ended (or `space` is empty).
+fn read_some(reader: &mut impl Read, space: &mut [u8]) -> std::io::Result<usize> {
+    loop {
+        match reader.read(space) {
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            res => return res,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::events::ResumeToken;
+    use crate::events::client::EventToClient;
+    use crate::game_types::player::Player;
+    use crate::game_types::table::TableId;
+    use crate::ser_glue::io::ReadFromError;
+    use crate::ser_glue::{DeserMachine, Deserable, Serable};
+    use std::io::{Cursor, Read};
+    use uuid::Uuid;
+
+    ///Only ever gives back one byte at a time, like a slow pipe.
+    struct Trickle<R>(R);
+
+    impl<R: Read> Read for Trickle<R> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let len = buf.len().min(1);
+            self.0.read(&mut buf[..len])
+        }
+    }
+
+    #[test]
+    fn round_trips_through_io() {
+        let events = [
+            EventToClient::AdminMsg(
+                "a longer string, to go through `DesiredInput::Bytes`".to_string(),
+            ),
+            EventToClient::SpecificPlayer(
+                TableId(0),
+                Uuid::new_v4(),
+                Player {
+                    name: "Ferris".to_string(),
+                    balance: 25,
+                },
+                None,
+            ),
+            EventToClient::Introduced {
+                uuid: Uuid::new_v4(),
+                resume_token: ResumeToken::new_random(),
+            },
+        ];
+
+        let mut bytes = vec![];
+        for evt in &events {
+            evt.ser_to_writer(&mut bytes).unwrap();
+        }
+
+        let mut cursor = Cursor::new(bytes.as_slice());
+        let mut trickle = Trickle(Cursor::new(bytes.as_slice()));
+        for evt in &events {
+            assert_eq!(&EventToClient::deser().read_from(&mut cursor).unwrap(), evt);
+            assert_eq!(
+                &EventToClient::deser().read_from(&mut trickle).unwrap(),
+                evt
+            );
+        }
+
+        assert!(matches!(
+            EventToClient::deser().read_from(&mut cursor),
+            Err(ReadFromError::Eof)
+        ));
+        assert!(matches!(
+            EventToClient::deser().read_from(&bytes[..4]),
+            Err(ReadFromError::Truncated {
+                bytes_into_value: 4
+            })
+        ));
+    }
+}
@@ -0,0 +1,119 @@
+///Upper bounds on what a [`DeserMachine`](crate::ser_glue::DeserMachine) will accept, so a peer can't make us allocate huge buffers with a few bytes.
+///
+/// Each machine passes these on to the machines it makes for its contents. The message size is checked by whatever drives the machine, eg. [`Driver`](crate::ser_glue::Driver).
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct DecodeLimits {
+    ///The most bytes any one string can take up.
+    pub max_string_bytes: usize,
+    ///The most elements any one list or map can have.
+    pub max_collection_len: usize,
+    ///The most bytes any one top-level value can take up.
+    pub max_message_bytes: usize,
+}
+
+impl DecodeLimits {
+    ///No limits at all - only use this for trusted input.
+    pub const UNLIMITED: Self = Self {
+        max_string_bytes: usize::MAX,
+        max_collection_len: usize::MAX,
+        max_message_bytes: usize::MAX,
+    };
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self::UNLIMITED
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::events::EventReadError;
+    use crate::events::client::EventToClient;
+    use crate::game_types::player::Player;
+    use crate::game_types::table::TableId;
+    use crate::ser_glue::driver::DriverError;
+    use crate::ser_glue::list::BasicListReadError;
+    use crate::ser_glue::string::StringReadError;
+    use crate::ser_glue::tuple::TupleReadError;
+    use crate::ser_glue::{DecodeLimits, Deserable, Serable};
+    use std::collections::HashMap;
+    use uuid::Uuid;
+
+    const LIMITS: DecodeLimits = DecodeLimits {
+        max_string_bytes: 8,
+        max_collection_len: 2,
+        max_message_bytes: 52,
+    };
+
+    fn decode(evt: &EventToClient) -> Result<Vec<EventToClient>, DriverError<EventReadError>> {
+        let ((), bytes) = evt.ser();
+        EventToClient::driver().with_limits(LIMITS).feed_all(&bytes)
+    }
+
+    fn player(name: &str) -> Player {
+        Player {
+            name: name.to_string(),
+            balance: 0,
+        }
+    }
+
+    #[test]
+    fn allows_values_within_limits() {
+        let evt = EventToClient::AllPlayers(
+            TableId(0),
+            HashMap::from([
+                (Uuid::new_v4(), player("Ferris")),
+                (Uuid::new_v4(), player("Corro")),
+            ]),
+            None,
+        );
+        assert_eq!(decode(&evt).unwrap(), [evt]);
+    }
+
+    #[test]
+    fn rejects_values_over_limits() {
+        assert!(matches!(
+            decode(&EventToClient::AdminMsg("much too long".to_string())),
+            Err(DriverError::Deser(EventReadError::StringRead(
+                StringReadError::TooLong { len: 13, max: 8 }
+            )))
+        ));
+
+        //limits should make it down into the players inside the map
+        assert!(matches!(
+            decode(&EventToClient::AllPlayers(
+                TableId(0),
+                HashMap::from([(Uuid::new_v4(), player("much too long"))]),
+                None
+            )),
+            Err(DriverError::Deser(EventReadError::ListOfPlayers(
+                BasicListReadError::Element(TupleReadError::BError(_))
+            )))
+        ));
+
+        assert!(matches!(
+            decode(&EventToClient::AllPlayers(
+                TableId(0),
+                (0..3).map(|_| (Uuid::new_v4(), player("a"))).collect(),
+                None
+            )),
+            Err(DriverError::Deser(EventReadError::ListOfPlayers(
+                BasicListReadError::TooLong { len: 3, max: 2 }
+            )))
+        ));
+
+        assert!(matches!(
+            decode(&EventToClient::AllPlayers(
+                TableId(0),
+                (0..2)
+                    .map(|_| (Uuid::new_v4(), player("12345678")))
+                    .collect(),
+                None
+            )),
+            Err(DriverError::TooLong {
+                max_message_bytes: 52
+            })
+        ));
+    }
+}
@@ -1,5 +1,5 @@
 use crate::integer::{Integer, IntegerDeserialiser, IntegerReadError, SignedState};
-use crate::ser_glue::{DeserMachine, Deserable, DesiredInput, FsmResult, Serable};
+use crate::ser_glue::{DecodeLimits, DeserMachine, Deserable, DesiredInput, FsmResult, Serable};
 use std::collections::VecDeque;
 use std::fmt::{Debug, Display, Formatter};
 
@@ -16,9 +16,30 @@ where
     }
 }
 
+impl<T> Serable for Vec<T>
+where
+    T: Serable<ExtraOutput = ()>,
+{
+    type ExtraOutput = ();
+
+    fn ser_into(&self, into: &mut Vec<u8>) -> Self::ExtraOutput {
+        BasicListSer(self).ser_into(into);
+    }
+}
+
+impl<T> Deserable for Vec<T>
+where
+    T: Deserable,
+    T::Deserer: DeserMachine<ExtraInput = ()>,
+    <T::Deserer as DeserMachine>::Error: 'static,
+{
+    type Deserer = BasicListDeserialiser<T::Deserer>;
+}
+
 #[derive(Debug)]
 pub enum BasicListReadError<E: std::error::Error> {
     Len(IntegerReadError),
+    TooLong { len: usize, max: usize },
     Element(E),
 }
 
@@ -26,6 +47,9 @@ impl<E: std::error::Error> Display for BasicListReadError<E> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Len(len) => write!(f, "Error getting len of list: {len}"),
+            Self::TooLong { len, max } => {
+                write!(f, "List has {len} elements, but the most allowed is {max}")
+            }
             Self::Element(el) => write!(f, "Error getting list element: {el}"),
         }
     }
@@ -35,16 +59,34 @@ impl<E: std::error::Error + 'static> std::error::Error for BasicListReadError<E>
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             Self::Len(len) => Some(len),
+            Self::TooLong { .. } => None,
             Self::Element(el) => Some(el),
         }
     }
 }
 
 pub enum BasicListDeserialiser<D: DeserMachine<ExtraInput = ()>> {
-    GettingLen(IntegerDeserialiser),
+    GettingLen(IntegerDeserialiser, DecodeLimits),
     GettingElements(ListDeserialiser<D>),
 }
 
+impl<D> Debug for BasicListDeserialiser<D>
+where
+    D: DeserMachine<ExtraInput = ()> + Debug,
+    D::Output: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::GettingLen(deser, limits) => f
+                .debug_tuple("GettingLen")
+                .field(deser)
+                .field(limits)
+                .finish(),
+            Self::GettingElements(deser) => f.debug_tuple("GettingElements").field(deser).finish(),
+        }
+    }
+}
+
 impl<D> DeserMachine for BasicListDeserialiser<D>
 where
     D: DeserMachine<ExtraInput = ()>,
@@ -54,13 +96,13 @@ where
     type Output = Vec<D::Output>;
     type Error = BasicListReadError<D::Error>;
 
-    fn new() -> Self {
-        Self::GettingLen(Integer::deser_with_input(SignedState::Unsigned))
+    fn new(limits: DecodeLimits) -> Self {
+        Self::GettingLen(Integer::deser_with_input(SignedState::Unsigned), limits)
     }
 
     fn wants_read(&mut self) -> DesiredInput<'_> {
         match self {
-            Self::GettingLen(deser) => deser.wants_read(),
+            Self::GettingLen(deser, _) => deser.wants_read(),
             Self::GettingElements(deser) => deser.wants_read(),
         }
     }
@@ -69,24 +111,32 @@ where
 
     fn finish_bytes_for_writing(&mut self, n: usize) {
         match self {
-            Self::GettingLen(deser) => deser.finish_bytes_for_writing(n),
+            Self::GettingLen(deser, _) => deser.finish_bytes_for_writing(n),
             Self::GettingElements(deser) => deser.finish_bytes_for_writing(n),
         }
     }
 
     fn process(self) -> Result<FsmResult<Self, Self::Output>, Self::Error> {
         match self {
-            Self::GettingLen(deser) => match deser.process() {
+            Self::GettingLen(deser, limits) => match deser.process() {
                 Err(e) => Err(BasicListReadError::Len(e)),
-                Ok(FsmResult::Continue(deser)) => Ok(FsmResult::Continue(Self::GettingLen(deser))),
+                Ok(FsmResult::Continue(deser)) => {
+                    Ok(FsmResult::Continue(Self::GettingLen(deser, limits)))
+                }
                 Ok(FsmResult::Done(len)) => {
                     let len = match len.try_into() {
                         Ok(len) => len,
                         Err(e) => return Err(BasicListReadError::Len(e)),
                     };
+                    if len > limits.max_collection_len {
+                        return Err(BasicListReadError::TooLong {
+                            len,
+                            max: limits.max_collection_len,
+                        });
+                    }
 
                     Ok(FsmResult::Continue(Self::GettingElements(
-                        ListDeserialiser::new_with_starting_input(vec![(); len]),
+                        ListDeserialiser::new_with_starting_input(vec![(); len], limits),
                     )))
                 }
             },
@@ -109,11 +159,12 @@ impl<T: Serable> Serable for ListSer<'_, T> {
 
 #[derive(Debug)]
 pub enum ListDeserialiser<D: DeserMachine> {
-    AwaitingExtras,
+    AwaitingExtras(DecodeLimits),
     GettingElements {
         extras: VecDeque<D::ExtraInput>,
         so_far: Vec<D::Output>,
         current: D,
+        limits: DecodeLimits,
     },
     FoundEmptyExtras,
 }
@@ -127,20 +178,20 @@ where
     type Output = Vec<D::Output>;
     type Error = D::Error;
 
-    fn new() -> Self {
-        Self::AwaitingExtras
+    fn new(limits: DecodeLimits) -> Self {
+        Self::AwaitingExtras(limits)
     }
 
     fn wants_read(&mut self) -> DesiredInput<'_> {
         match self {
-            Self::AwaitingExtras => DesiredInput::Extra,
+            Self::AwaitingExtras(_) => DesiredInput::Extra,
             Self::GettingElements { current, .. } => current.wants_read(),
             Self::FoundEmptyExtras => DesiredInput::ProcessMe,
         }
     }
 
     fn give_starting_input(&mut self, extras: Self::ExtraInput) {
-        if matches!(self, Self::AwaitingExtras) {
+        if let Self::AwaitingExtras(limits) = *self {
             let mut extras: VecDeque<D::ExtraInput> = extras.into();
 
             *self = extras.pop_front().map_or_else(
@@ -148,7 +199,8 @@ where
                 |first_extra| Self::GettingElements {
                     extras,
                     so_far: Vec::new(),
-                    current: D::new_with_starting_input(first_extra),
+                    current: D::new_with_starting_input(first_extra, limits),
+                    limits,
                 },
             );
         }
@@ -157,22 +209,24 @@ where
     fn finish_bytes_for_writing(&mut self, n: usize) {
         match self {
             Self::GettingElements { current, .. } => current.finish_bytes_for_writing(n),
-            Self::AwaitingExtras | Self::FoundEmptyExtras => {}
+            Self::AwaitingExtras(_) | Self::FoundEmptyExtras => {}
         }
     }
 
     fn process(self) -> Result<FsmResult<Self, Self::Output>, Self::Error> {
         match self {
-            Self::AwaitingExtras => Ok(FsmResult::Continue(Self::AwaitingExtras)),
+            s @ Self::AwaitingExtras(_) => Ok(FsmResult::Continue(s)),
             Self::GettingElements {
                 mut extras,
                 mut so_far,
                 current,
+                limits,
             } => match current.process()? {
                 FsmResult::Continue(current) => Ok(FsmResult::Continue(Self::GettingElements {
                     extras,
                     so_far,
                     current,
+                    limits,
                 })),
                 FsmResult::Done(next_element) => {
                     so_far.push(next_element);
@@ -180,7 +234,8 @@ where
                         Some(next_extra) => Ok(FsmResult::Continue(Self::GettingElements {
                             extras,
                             so_far,
-                            current: D::new_with_starting_input(next_extra),
+                            current: D::new_with_starting_input(next_extra, limits),
+                            limits,
                         })),
                         None => Ok(FsmResult::Done(so_far)),
                     }
@@ -1,7 +1,7 @@
 use crate::integer::{Integer, IntegerDeserialiser, SignedState};
 use crate::ser_glue::list::BasicListReadError;
 use crate::ser_glue::tuple::{TupleDeserialiser, TupleReadError};
-use crate::ser_glue::{DeserMachine, Deserable, DesiredInput, FsmResult, Serable};
+use crate::ser_glue::{DecodeLimits, DeserMachine, Deserable, DesiredInput, FsmResult, Serable};
 use std::collections::{HashMap, VecDeque};
 use std::fmt::Debug;
 use std::hash::Hash;
@@ -20,6 +20,32 @@ where
     }
 }
 
+#[allow(clippy::implicit_hasher)] //BasicMapSer only takes the default hasher
+impl<K, V> Serable for HashMap<K, V>
+where
+    K: Serable<ExtraOutput = ()>,
+    V: Serable<ExtraOutput = ()>,
+{
+    type ExtraOutput = ();
+
+    fn ser_into(&self, into: &mut Vec<u8>) -> Self::ExtraOutput {
+        BasicMapSer(self).ser_into(into);
+    }
+}
+
+#[allow(clippy::implicit_hasher)] //samesies
+impl<K, V> Deserable for HashMap<K, V>
+where
+    K: Deserable + Eq + Hash + Debug,
+    V: Deserable + Hash + Debug,
+    K::Deserer: DeserMachine<ExtraInput = ()>,
+    V::Deserer: DeserMachine<ExtraInput = ()>,
+    <K::Deserer as DeserMachine>::Error: 'static,
+    <V::Deserer as DeserMachine>::Error: 'static,
+{
+    type Deserer = BasicMapDeserialiser<K::Deserer, V::Deserer>;
+}
+
 #[derive(Debug)]
 pub enum BasicMapDeserialiser<KDeser, VDeser>
 where
@@ -28,7 +54,7 @@ where
     KDeser::Output: Debug,
     VDeser::Output: Debug, //TODO: rm
 {
-    GettingLen(IntegerDeserialiser),
+    GettingLen(IntegerDeserialiser, DecodeLimits),
     GettingElements(MapDeserialiser<KDeser, VDeser>),
 }
 
@@ -45,13 +71,13 @@ where
     type Output = HashMap<KDeser::Output, VDeser::Output>;
     type Error = BasicListReadError<TupleReadError<KDeser::Error, VDeser::Error>>;
 
-    fn new() -> Self {
-        Self::GettingLen(Integer::deser_with_input(SignedState::Unsigned))
+    fn new(limits: DecodeLimits) -> Self {
+        Self::GettingLen(Integer::deser_with_input(SignedState::Unsigned), limits)
     }
 
     fn wants_read(&mut self) -> DesiredInput<'_> {
         match self {
-            Self::GettingLen(deser) => deser.wants_read(),
+            Self::GettingLen(deser, _) => deser.wants_read(),
             Self::GettingElements(deser) => deser.wants_read(),
         }
     }
@@ -60,23 +86,31 @@ where
 
     fn finish_bytes_for_writing(&mut self, n: usize) {
         match self {
-            Self::GettingLen(deser) => deser.finish_bytes_for_writing(n),
+            Self::GettingLen(deser, _) => deser.finish_bytes_for_writing(n),
             Self::GettingElements(deser) => deser.finish_bytes_for_writing(n),
         }
     }
 
     fn process(self) -> Result<FsmResult<Self, Self::Output>, Self::Error> {
         match self {
-            Self::GettingLen(deser) => match deser.process() {
+            Self::GettingLen(deser, limits) => match deser.process() {
                 Err(error) => Err(BasicListReadError::Len(error)),
-                Ok(FsmResult::Continue(deser)) => Ok(FsmResult::Continue(Self::GettingLen(deser))),
+                Ok(FsmResult::Continue(deser)) => {
+                    Ok(FsmResult::Continue(Self::GettingLen(deser, limits)))
+                }
                 Ok(FsmResult::Done(len)) => {
                     let len = match len.try_into() {
                         Ok(len) => len,
                         Err(error) => return Err(BasicListReadError::Len(error)),
                     };
+                    if len > limits.max_collection_len {
+                        return Err(BasicListReadError::TooLong {
+                            len,
+                            max: limits.max_collection_len,
+                        });
+                    }
                     Ok(FsmResult::Continue(Self::GettingElements(
-                        MapDeserialiser::new_with_starting_input(vec![((), ()); len]),
+                        MapDeserialiser::new_with_starting_input(vec![((), ()); len], limits),
                     )))
                 }
             },
@@ -106,11 +140,12 @@ where
     <KDeser as DeserMachine>::Output: Debug,
     <VDeser as DeserMachine>::Output: Debug,
 {
-    AwaitingExtras,
+    AwaitingExtras(DecodeLimits),
     ReadingList {
         extras: VecDeque<(KDeser::ExtraInput, VDeser::ExtraInput)>,
         so_far: HashMap<KDeser::Output, VDeser::Output>,
         current: TupleDeserialiser<KDeser, VDeser>,
+        limits: DecodeLimits,
     },
     FoundEmptyExtras,
 }
@@ -128,20 +163,20 @@ where
     type Output = HashMap<KDeser::Output, VDeser::Output>;
     type Error = TupleReadError<KDeser::Error, VDeser::Error>;
 
-    fn new() -> Self {
-        Self::AwaitingExtras
+    fn new(limits: DecodeLimits) -> Self {
+        Self::AwaitingExtras(limits)
     }
 
     fn wants_read(&mut self) -> DesiredInput<'_> {
         match self {
-            Self::AwaitingExtras => DesiredInput::Extra,
+            Self::AwaitingExtras(_) => DesiredInput::Extra,
             Self::ReadingList { current, .. } => current.wants_read(),
             Self::FoundEmptyExtras => DesiredInput::ProcessMe,
         }
     }
 
     fn give_starting_input(&mut self, magic: Self::ExtraInput) {
-        if matches!(self, Self::AwaitingExtras) {
+        if let Self::AwaitingExtras(limits) = *self {
             let mut extras: VecDeque<_> = magic.into();
 
             *self = extras.pop_front().map_or_else(
@@ -149,7 +184,8 @@ where
                 |first_extra| Self::ReadingList {
                     extras,
                     so_far: HashMap::new(),
-                    current: TupleDeserialiser::new_with_starting_input(first_extra),
+                    current: TupleDeserialiser::new_with_starting_input(first_extra, limits),
+                    limits,
                 },
             );
         }
@@ -163,17 +199,19 @@ where
 
     fn process(self) -> Result<FsmResult<Self, Self::Output>, Self::Error> {
         match self {
-            Self::AwaitingExtras => Ok(FsmResult::Continue(Self::AwaitingExtras)),
+            s @ Self::AwaitingExtras(_) => Ok(FsmResult::Continue(s)),
             Self::FoundEmptyExtras => Ok(FsmResult::Done(HashMap::new())),
             Self::ReadingList {
                 mut extras,
                 mut so_far,
                 current,
+                limits,
             } => match current.process()? {
                 FsmResult::Continue(current) => Ok(FsmResult::Continue(Self::ReadingList {
                     extras,
                     so_far,
                     current,
+                    limits,
                 })),
                 FsmResult::Done((new_key, new_value)) => {
                     so_far.insert(new_key, new_value);
@@ -181,7 +219,8 @@ where
                         Some(next_extra) => Ok(FsmResult::Continue(Self::ReadingList {
                             extras,
                             so_far,
-                            current: TupleDeserialiser::new_with_starting_input(next_extra),
+                            current: TupleDeserialiser::new_with_starting_input(next_extra, limits),
+                            limits,
                         })),
                         None => Ok(FsmResult::Done(so_far)),
                     }
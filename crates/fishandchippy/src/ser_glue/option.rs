@@ -0,0 +1,112 @@
+use crate::ser_glue::{DecodeLimits, DeserMachine, Deserable, DesiredInput, FsmResult, Serable};
+use std::fmt::{Display, Formatter};
+
+const NONE: u8 = 0;
+const SOME: u8 = 1;
+
+impl<T> Serable for Option<T>
+where
+    T: Serable<ExtraOutput = ()>,
+{
+    type ExtraOutput = ();
+
+    fn ser_into(&self, into: &mut Vec<u8>) -> Self::ExtraOutput {
+        match self {
+            None => into.push(NONE),
+            Some(t) => {
+                into.push(SOME);
+                t.ser_into(into);
+            }
+        }
+    }
+}
+
+impl<T> Deserable for Option<T>
+where
+    T: Deserable,
+    T::Deserer: DeserMachine<ExtraInput = ()>,
+    <T::Deserer as DeserMachine>::Error: 'static,
+{
+    type Deserer = OptionDeserialiser<T::Deserer>;
+}
+
+#[derive(Debug)]
+pub enum OptionReadError<E> {
+    InvalidTag(u8),
+    Value(E),
+}
+
+impl<E: Display> Display for OptionReadError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidTag(tag) => write!(f, "Invalid tag for option: {tag}"),
+            Self::Value(e) => write!(f, "Error getting value inside option: {e}"),
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for OptionReadError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::InvalidTag(_) => None,
+            Self::Value(e) => Some(e),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum OptionDeserialiser<D: DeserMachine<ExtraInput = ()>> {
+    Start(u8, DecodeLimits),
+    GotTag(u8, DecodeLimits),
+    ReadingValue(D),
+}
+
+impl<D> DeserMachine for OptionDeserialiser<D>
+where
+    D: DeserMachine<ExtraInput = ()>,
+    D::Error: 'static,
+{
+    type ExtraInput = ();
+    type Output = Option<D::Output>;
+    type Error = OptionReadError<D::Error>;
+
+    fn new(limits: DecodeLimits) -> Self {
+        Self::Start(0, limits)
+    }
+
+    fn wants_read(&mut self) -> DesiredInput<'_> {
+        match self {
+            Self::Start(tag, _) => DesiredInput::Byte(tag),
+            Self::GotTag(..) => DesiredInput::ProcessMe,
+            Self::ReadingValue(deser) => deser.wants_read(),
+        }
+    }
+
+    fn give_starting_input(&mut self, (): Self::ExtraInput) {}
+
+    fn finish_bytes_for_writing(&mut self, n: usize) {
+        match self {
+            Self::Start(tag, limits) => {
+                if n == 1 {
+                    *self = Self::GotTag(*tag, *limits);
+                }
+            }
+            Self::GotTag(..) => {}
+            Self::ReadingValue(deser) => deser.finish_bytes_for_writing(n),
+        }
+    }
+
+    fn process(self) -> Result<FsmResult<Self, Self::Output>, Self::Error> {
+        match self {
+            s @ Self::Start(..) => Ok(FsmResult::Continue(s)),
+            Self::GotTag(NONE, _) => Ok(FsmResult::Done(None)),
+            Self::GotTag(SOME, limits) => {
+                Ok(FsmResult::Continue(Self::ReadingValue(D::new(limits))))
+            }
+            Self::GotTag(tag, _) => Err(OptionReadError::InvalidTag(tag)),
+            Self::ReadingValue(deser) => deser
+                .mapped_process(Self::ReadingValue, Some)
+                .map_err(OptionReadError::Value),
+        }
+    }
+}
@@ -1,11 +1,11 @@
 use crate::integer::{Integer, IntegerDeserialiser, IntegerReadError, SignedState};
-use crate::ser_glue::{DeserMachine, Deserable, DesiredInput, FsmResult, Serable};
+use crate::ser_glue::{DecodeLimits, DeserMachine, Deserable, DesiredInput, FsmResult, Serable};
 use std::fmt::{Display, Formatter};
 use std::string::FromUtf8Error;
 
 #[derive(Debug)]
 pub enum StringDeserialiser {
-    DeseringLen(IntegerDeserialiser),
+    DeseringLen(IntegerDeserialiser, DecodeLimits),
     ReadingContent {
         bytes_left: usize,
         content_so_far: Vec<u8>,
@@ -20,6 +20,7 @@ impl Deserable for String {
 pub enum StringReadError {
     Integer(IntegerReadError),
     String(FromUtf8Error),
+    TooLong { len: usize, max: usize },
 }
 
 impl From<IntegerReadError> for StringReadError {
@@ -38,6 +39,12 @@ impl Display for StringReadError {
         match self {
             Self::Integer(int) => write!(f, "Error reading length: {int}"),
             Self::String(string) => write!(f, "Error reading content as UTF-8: {string}"),
+            Self::TooLong { len, max } => {
+                write!(
+                    f,
+                    "String is {len} bytes long, but the most allowed is {max}"
+                )
+            }
         }
     }
 }
@@ -47,6 +54,7 @@ impl std::error::Error for StringReadError {
         match self {
             Self::Integer(int) => Some(int),
             Self::String(string) => Some(string),
+            Self::TooLong { .. } => None,
         }
     }
 }
@@ -56,13 +64,13 @@ impl DeserMachine for StringDeserialiser {
     type Output = String;
     type Error = StringReadError;
 
-    fn new() -> Self {
-        Self::DeseringLen(Integer::deser_with_input(SignedState::Unsigned))
+    fn new(limits: DecodeLimits) -> Self {
+        Self::DeseringLen(Integer::deser_with_input(SignedState::Unsigned), limits)
     }
 
     fn wants_read(&mut self) -> DesiredInput<'_> {
         match self {
-            Self::DeseringLen(deser) => deser.wants_read(),
+            Self::DeseringLen(deser, _) => deser.wants_read(),
             Self::ReadingContent {
                 bytes_left,
                 content_so_far,
@@ -81,7 +89,7 @@ impl DeserMachine for StringDeserialiser {
 
     fn finish_bytes_for_writing(&mut self, n: usize) {
         match self {
-            Self::DeseringLen(deser) => deser.finish_bytes_for_writing(n),
+            Self::DeseringLen(deser, _) => deser.finish_bytes_for_writing(n),
             Self::ReadingContent { bytes_left, .. } => {
                 *bytes_left -= n;
             }
@@ -90,10 +98,18 @@ impl DeserMachine for StringDeserialiser {
 
     fn process(self) -> Result<FsmResult<Self, Self::Output>, Self::Error> {
         match self {
-            Self::DeseringLen(deser) => match deser.process()? {
-                FsmResult::Continue(deser) => Ok(FsmResult::Continue(Self::DeseringLen(deser))),
+            Self::DeseringLen(deser, limits) => match deser.process()? {
+                FsmResult::Continue(deser) => {
+                    Ok(FsmResult::Continue(Self::DeseringLen(deser, limits)))
+                }
                 FsmResult::Done(int) => {
                     let bytes_left = int.try_into()?;
+                    if bytes_left > limits.max_string_bytes {
+                        return Err(StringReadError::TooLong {
+                            len: bytes_left,
+                            max: limits.max_string_bytes,
+                        });
+                    }
                     Ok(FsmResult::Continue(Self::ReadingContent {
                         bytes_left,
                         content_so_far: vec![0; bytes_left],
@@ -1,4 +1,4 @@
-use crate::ser_glue::{DeserMachine, DesiredInput, FsmResult, Serable};
+use crate::ser_glue::{DecodeLimits, DeserMachine, DesiredInput, FsmResult, Serable};
 use std::fmt::{Display, Formatter};
 
 impl<A: Serable, B: Serable> Serable for (A, B) {
@@ -47,10 +47,11 @@ where
     ADeser: DeserMachine,
     BDeser: DeserMachine,
 {
-    Empty,
+    Empty(DecodeLimits),
     ReadingA {
         reading_a: ADeser,
         b_extra: BDeser::ExtraInput,
+        limits: DecodeLimits,
     },
     ReadingB {
         a: ADeser::Output,
@@ -69,30 +70,31 @@ where
     type Output = (ADeser::Output, BDeser::Output);
     type Error = TupleReadError<ADeser::Error, BDeser::Error>;
 
-    fn new() -> Self {
-        Self::Empty
+    fn new(limits: DecodeLimits) -> Self {
+        Self::Empty(limits)
     }
 
     fn wants_read(&mut self) -> DesiredInput<'_> {
         match self {
-            Self::Empty => DesiredInput::Extra,
+            Self::Empty(_) => DesiredInput::Extra,
             Self::ReadingA { reading_a, .. } => reading_a.wants_read(),
             Self::ReadingB { reading_b, .. } => reading_b.wants_read(),
         }
     }
 
     fn give_starting_input(&mut self, (a_extra, b_extra): Self::ExtraInput) {
-        if matches!(self, Self::Empty) {
+        if let Self::Empty(limits) = *self {
             *self = Self::ReadingA {
-                reading_a: ADeser::new_with_starting_input(a_extra),
+                reading_a: ADeser::new_with_starting_input(a_extra, limits),
                 b_extra,
+                limits,
             }
         }
     }
 
     fn finish_bytes_for_writing(&mut self, n: usize) {
         match self {
-            Self::Empty => {}
+            Self::Empty(_) => {}
             Self::ReadingA { reading_a, .. } => {
                 reading_a.finish_bytes_for_writing(n);
             }
@@ -104,16 +106,22 @@ where
 
     fn process(self) -> Result<FsmResult<Self, Self::Output>, Self::Error> {
         match self {
-            Self::ReadingA { reading_a, b_extra } => {
+            Self::ReadingA {
+                reading_a,
+                b_extra,
+                limits,
+            } => {
                 //can't use ?s because of conflicting impls
                 match reading_a.process() {
                     Ok(reading_a) => match reading_a {
-                        FsmResult::Continue(reading_a) => {
-                            Ok(FsmResult::Continue(Self::ReadingA { reading_a, b_extra }))
-                        }
+                        FsmResult::Continue(reading_a) => Ok(FsmResult::Continue(Self::ReadingA {
+                            reading_a,
+                            b_extra,
+                            limits,
+                        })),
                         FsmResult::Done(a) => Ok(FsmResult::Continue(Self::ReadingB {
                             a,
-                            reading_b: BDeser::new_with_starting_input(b_extra),
+                            reading_b: BDeser::new_with_starting_input(b_extra, limits),
                         })),
                     },
                     Err(e) => Err(TupleReadError::AError(e)),
@@ -128,7 +136,7 @@ where
                 },
                 Err(e) => Err(TupleReadError::BError(e)),
             },
-            s @ Self::Empty => Ok(FsmResult::Continue(s)),
+            s @ Self::Empty(_) => Ok(FsmResult::Continue(s)),
         }
     }
 }
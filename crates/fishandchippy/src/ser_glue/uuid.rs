@@ -1,4 +1,4 @@
-use crate::ser_glue::{DeserMachine, Deserable, DesiredInput, FsmResult, Serable};
+use crate::ser_glue::{DecodeLimits, DeserMachine, Deserable, DesiredInput, FsmResult, Serable};
 use std::convert::Infallible;
 use uuid::Uuid;
 
@@ -25,7 +25,7 @@ impl DeserMachine for UuidDeserialiser {
     type Output = Uuid;
     type Error = Infallible;
 
-    fn new() -> Self {
+    fn new(_: DecodeLimits) -> Self {
         Self {
             content: [0; 16],
             bytes_left: 16,